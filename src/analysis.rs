@@ -0,0 +1,26 @@
+pub mod conservation;
+pub mod continuation;
+pub mod control;
+pub mod estimation;
+pub mod fba;
+pub mod gaps;
+pub mod invariants;
+pub mod lint;
+pub mod reduction;
+pub mod scan;
+pub mod sensitivity;
+pub mod siphons;
+pub mod stability;
+pub mod steady_state;
+pub use conservation::{ConservationLaw, conservation_laws};
+pub use continuation::{Bifurcation, Branch};
+pub use control::{ControlAnalysis, control_analysis};
+pub use estimation::{Data, EstimationOptions, Fit};
+pub use fba::FluxBalance;
+pub use gaps::{Gap, Gaps};
+pub use lint::{Diagnostic, Lint};
+pub use reduction::{Approximation, Reduction, ReductionError};
+pub use sensitivity::Sensitivities;
+pub use siphons::Persistence;
+pub use stability::{Stability, StabilityAnalysis};
+pub use steady_state::{SteadyState, SteadyStateOptions, steady_state};
@@ -0,0 +1,332 @@
+// conservation laws: weighted sums of species amounts that no reaction changes
+//
+// With N the stoichiometric matrix, every y with y^T N = 0 gives a conserved
+// total sum_i y_i V_i x_i, where V_i is the volume of species i's
+// compartment. Constant and boundary species do not change and are each
+// conserved on their own.
+use crate::data::{Matrix, Overflow, Rational};
+use crate::network::{
+    BinaryOp, Complex, Definition, EliminatedSpecies, Expr, Network, ParameterAttributes, Reaction,
+    StoichCoef, expression,
+};
+use crate::numeric::exact;
+
+#[derive(Debug, Clone)]
+pub struct ConservationLaw {
+    // weight of each species' amount
+    pub coefficients: Vec<Rational>,
+    // a species whose balance is redundant given the others, and which can
+    // be eliminated using this law
+    pub dependent: usize,
+}
+
+impl ConservationLaw {
+    // conserved total for concentrations x
+    pub fn total(&self, network: &Network, x: &[f64]) -> f64 {
+        self.coefficients
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_zero())
+            .map(|(i, c)| c.to_f64() * network.species_volume(i) * x[i])
+            .sum()
+    }
+
+    // weights of the concentrations in the conserved total
+    pub fn weights(&self, network: &Network) -> Vec<f64> {
+        self.coefficients
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.to_f64() * network.species_volume(i))
+            .collect()
+    }
+
+    // the law written as a sum of species, e.g. `E + ES`
+    pub fn formula(&self, network: &Network) -> String {
+        let mut out = String::new();
+        for (i, &c) in self.coefficients.iter().enumerate() {
+            if c.is_zero() {
+                continue;
+            }
+            let name = expression::quote_symbol(network.species_name(i));
+            let negative = c < Rational::ZERO;
+            if out.is_empty() {
+                if negative {
+                    out.push('-');
+                }
+            } else {
+                out.push_str(if negative { " - " } else { " + " });
+            }
+            if c.abs() != Rational::ONE {
+                out.push_str(&format!("{} ", c.abs()));
+            }
+            out.push_str(&name);
+        }
+        out
+    }
+}
+
+// stoichiometry with the rows of fixed species cleared
+fn dynamic_stoichiometry(network: &Network) -> Matrix<Rational> {
+    let mut n = network.exact_stoichiometric_matrix();
+    for i in 0..n.rows() {
+        if network.species_attributes(i).is_fixed() {
+            n.row_mut(i).fill(Rational::ZERO);
+        }
+    }
+    n
+}
+
+// a basis of the conservation laws; each has a distinct dependent species
+// with coefficient one, which no other law in the basis involves
+pub fn conservation_laws(network: &Network) -> Result<Vec<ConservationLaw>, Overflow> {
+    let n = dynamic_stoichiometry(network);
+    // basis vectors of the left nullspace follow the free columns of N^T
+    let (_, pivots) = exact::rref(&n.transpose())?;
+    let free = (0..n.rows()).filter(|i| !pivots.contains(i));
+    Ok(exact::left_nullspace(&n)?
+        .into_iter()
+        .zip(free)
+        .map(|(coefficients, dependent)| ConservationLaw {
+            coefficients,
+            dependent,
+        })
+        .collect())
+}
+
+// the network with one dependent species per conservation law replaced by
+// an observable `(total - others) / weight`, whose total is a new parameter
+// `<species>_total` set from the initial conditions. Generated code then
+// integrates independent species only, and its Jacobian is not singular,
+// while reaction comments and SBML reactions still show every species.
+pub fn eliminate_dependent(network: &Network) -> Result<Network, Overflow> {
+    let x0 = network.initial_conditions();
+    let laws: Vec<ConservationLaw> = conservation_laws(network)?
+        .into_iter()
+        .filter(|law| !network.species_attributes(law.dependent).is_fixed())
+        .collect();
+    let dependent: Vec<usize> = laws.iter().map(|law| law.dependent).collect();
+    let taken = |name: &str| {
+        network.find_species(name).is_some()
+            || network.find_parameter(name).is_some()
+            || network.find_compartment(name).is_some()
+            || network.find_definition(name).is_some()
+    };
+    let fresh = |base: String, used: &[String]| {
+        let mut name = base.clone();
+        let mut n = 1;
+        while taken(&name) && !dependent.iter().any(|&d| network.species_name(d) == name)
+            || used.contains(&name)
+        {
+            n += 1;
+            name = format!("{base}_{n}");
+        }
+        name
+    };
+
+    // eliminated species keep their names where the language allows
+    let mut used = Vec::new();
+    let mut renamed = Vec::new();
+    for &d in &dependent {
+        let name = fresh(network.species_name(d).replace('@', "_"), &used);
+        used.push(name.clone());
+        renamed.push((network.species_name(d).to_string(), Expr::symbol(name)));
+    }
+    let rename = |e: &Expr| {
+        renamed
+            .iter()
+            .fold(e.clone(), |e, (s, name)| e.substitute(s, name))
+    };
+
+    let mut reduced = Network::new();
+    for c in 0..network.num_compartments() {
+        let id = reduced.register_compartment(network.compartment_name(c).to_string());
+        *reduced.compartment_attributes_mut(id) = network.compartment_attributes(c).clone();
+    }
+    let mut index = vec![None; network.num_species()];
+    for i in (0..network.num_species()).filter(|i| !dependent.contains(i)) {
+        let id = reduced.register_species(network.species_name(i).to_string());
+        *reduced.species_attributes_mut(id) = network.species_attributes(i).clone();
+        index[i] = Some(id);
+    }
+    for k in 0..network.num_parameters() {
+        let id = reduced.declare_parameter(
+            network.parameter_name(k).to_string(),
+            network.parameter_attributes(k).clone(),
+        );
+        // keep implicit rate constants implicit
+        *reduced.parameter_attributes_mut(id) = network.parameter_attributes(k).clone();
+    }
+
+    // reactions lose the eliminated species, which are recorded with the
+    // original formulas for export; default rate laws that involved them are
+    // written out
+    for (j, rxn) in network.reactions().iter().enumerate() {
+        let mut reactants = Complex::new();
+        let mut products = Complex::new();
+        for (complex, side) in [
+            (rxn.get_reactants(), &mut reactants),
+            (rxn.get_products(), &mut products),
+        ] {
+            for (i, c) in complex.terms() {
+                if let Some(id) = index[i] {
+                    side.add_term(id, c);
+                }
+            }
+        }
+        // rate laws are per volume of the reaction's compartment, which
+        // follows from its first species
+        let volume = reactants
+            .terms()
+            .into_iter()
+            .chain(products.terms())
+            .find_map(|(id, _)| reduced.species_attributes(id).compartment)
+            .map_or(1.0, |c| reduced.compartment_attributes(c).volume());
+        let ratio = network.reaction_volume(j) / volume;
+        let changed = rxn
+            .get_reactants()
+            .terms()
+            .into_iter()
+            .chain(rxn.get_products().terms())
+            .any(|(i, _)| dependent.contains(&i));
+        let name = network.reaction_name(j);
+        let mut reduced_rxn = if rxn.is_reversible() {
+            Reaction::named_reversible(name, reactants, products)
+        } else {
+            Reaction::named_forward(name, reactants, products)
+        };
+        if changed {
+            reduced_rxn.set_original_formula(network.reaction_formula(j));
+        }
+        let rate = match rxn.get_kinetics() {
+            Some(rate) => Some(rename(rate)),
+            None if changed || ratio != 1.0 => Some(rename(&network.mass_action(j))),
+            None => None,
+        };
+        if let Some(rate) = rate {
+            reduced_rxn.set_kinetics(if ratio == 1.0 {
+                rate
+            } else {
+                Expr::binary(BinaryOp::Mul, Expr::Number(ratio), rate)
+            });
+        }
+        reduced.add_reaction(reduced_rxn);
+    }
+
+    for d in network.definitions() {
+        reduced.add_definition(Definition {
+            name: d.name.clone(),
+            value: rename(&d.value),
+            observable: d.observable,
+        });
+    }
+    let mut totals = Vec::new();
+    for (law, (_, name)) in laws.iter().zip(&renamed) {
+        let Expr::Symbol(name) = name else {
+            unreachable!("eliminated species are renamed to symbols")
+        };
+        let d = law.dependent;
+        let terms = |side: fn(&Reaction) -> &Complex| -> Vec<(usize, StoichCoef)> {
+            (0..network.num_reactions())
+                .map(|j| (j, side(&network.reactions()[j]).coefficient(d)))
+                .filter(|(_, c)| !c.is_zero())
+                .collect()
+        };
+        reduced.add_eliminated_species(EliminatedSpecies {
+            observable: name.clone(),
+            attributes: network.species_attributes(d).clone(),
+            reactants: terms(Reaction::get_reactants),
+            products: terms(Reaction::get_products),
+        });
+        let total = fresh(format!("{name}_total"), &used);
+        used.push(total.clone());
+        let weights = law.weights(network);
+        let others = weights
+            .iter()
+            .enumerate()
+            .filter(|&(i, w)| i != law.dependent && *w != 0.0)
+            .fold(Expr::symbol(total.clone()), |e, (i, &w)| {
+                let x = Expr::symbol(network.species_name(i));
+                let term = if w.abs() == 1.0 {
+                    x
+                } else {
+                    Expr::binary(BinaryOp::Mul, Expr::Number(w.abs()), x)
+                };
+                let op = if w > 0.0 {
+                    BinaryOp::Sub
+                } else {
+                    BinaryOp::Add
+                };
+                Expr::binary(op, e, term)
+            });
+        let w = weights[law.dependent];
+        let value = if w == 1.0 {
+            others
+        } else {
+            Expr::binary(BinaryOp::Div, others, Expr::Number(w))
+        };
+        reduced.add_definition(Definition {
+            name: name.clone(),
+            value,
+            observable: true,
+        });
+        let attributes = ParameterAttributes {
+            value: Some(law.total(network, &x0)),
+            description: Some(format!("total {}", law.formula(network))),
+            ..Default::default()
+        };
+        totals.push((total, attributes));
+    }
+    for (total, attributes) in totals {
+        reduced.declare_parameter(total, attributes);
+    }
+    for event in network.events() {
+        reduced.add_event(event.clone());
+    }
+    for bound in network.flux_bounds() {
+        reduced.add_flux_bound(bound.clone());
+    }
+    if let Some((reaction, sense)) = network.objective() {
+        reduced.set_objective(reaction.to_string(), sense);
+    }
+    reduced
+        .order_definitions()
+        .expect("definitions of the reduced network are consistent");
+    reduced.resolve_parameters();
+    Ok(reduced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    #[test]
+    fn finds_conserved_cycle() {
+        let network = network("v1 : A <-> B\nv2 : B -> C\nv3 : C -> A\n");
+        let laws = conservation_laws(&network).unwrap();
+        assert_eq!(laws.len(), 1);
+        assert_eq!(laws[0].weights(&network), vec![1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn elimination_keeps_reaction_structure() {
+        let network =
+            network("species {\n  A = 1\n}\nv1 : A <-> B\nv2 : B -> 2 C\nv3 : 2 C -> A\n");
+        let reduced = eliminate_dependent(&network).unwrap();
+        assert_eq!(reduced.num_species(), 2);
+        assert_eq!(reduced.num_reactions(), 3);
+        let eliminated = reduced.eliminated_species();
+        assert_eq!(eliminated.len(), 1);
+        let c = &eliminated[0];
+        assert_eq!(c.observable, "C");
+        assert_eq!(c.reactants, vec![(2, Rational::integer(2))]);
+        assert_eq!(c.products, vec![(1, Rational::integer(2))]);
+        assert_eq!(reduced.original_reaction_formula(1), "B -> 2 C");
+        assert_eq!(reduced.original_reaction_formula(0), "A <-> B");
+    }
+}
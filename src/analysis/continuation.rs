@@ -0,0 +1,544 @@
+// pseudo-arclength continuation of steady states in one parameter
+//
+// The branch is a curve z(s) = (x(s), p(s)) on which the steady-state
+// residual F(x, p) vanishes, with conservation laws in place of redundant
+// balances. Each step predicts along the unit tangent t, z + h t, and
+// corrects by Newton's method on F = 0 together with t . (z - z_pred) = 0,
+// which stays regular at folds where the parameter turns back. The step
+// grows after easy corrections and halves after failed ones.
+//
+// Folds (saddle-nodes) are where dp/ds changes sign. Hopf bifurcations are
+// where the product of (l_i + l_j) over pairs of eigenvalues of the reduced
+// Jacobian changes sign, with a complex pair on the imaginary axis; a pair
+// of real eigenvalues l and -l also changes its sign and is ignored. Both
+// are located by bisection on the step length.
+use std::error::Error;
+use std::fmt;
+
+use super::conservation::{self, ConservationLaw};
+use super::stability::{self, Stability};
+use super::steady_state::{self, SteadyStateError, SteadyStateOptions, SteadyStateSystem};
+use crate::data::Matrix;
+use crate::network::Network;
+use crate::numeric::Lu;
+use crate::numeric::eigen::{self, Complex, NoConvergence};
+use crate::simulation::RateEquations;
+
+#[derive(Debug)]
+pub enum ContinuationError {
+    SteadyState(SteadyStateError),
+    Eigenvalues(NoConvergence),
+}
+
+impl fmt::Display for ContinuationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContinuationError::SteadyState(e) => write!(f, "{e}"),
+            ContinuationError::Eigenvalues(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for ContinuationError {}
+
+impl From<SteadyStateError> for ContinuationError {
+    fn from(e: SteadyStateError) -> Self {
+        ContinuationError::SteadyState(e)
+    }
+}
+
+impl From<NoConvergence> for ContinuationError {
+    fn from(e: NoConvergence) -> Self {
+        ContinuationError::Eigenvalues(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bifurcation {
+    // saddle-node: two steady states meet and vanish
+    Fold,
+    // a complex pair crosses the imaginary axis with this angular frequency
+    Hopf { frequency: f64 },
+}
+
+impl fmt::Display for Bifurcation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bifurcation::Fold => write!(f, "fold"),
+            Bifurcation::Hopf { .. } => write!(f, "hopf"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BranchPoint {
+    pub parameter: f64,
+    pub concentrations: Vec<f64>,
+    pub stability: Stability,
+    pub bifurcation: Option<Bifurcation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Branch {
+    pub parameter: usize,
+    // in order along the branch, from the lower to the upper end when the
+    // branch has no folds
+    pub points: Vec<BranchPoint>,
+}
+
+impl Branch {
+    pub fn bifurcations(&self) -> impl Iterator<Item = &BranchPoint> {
+        self.points.iter().filter(|p| p.bifurcation.is_some())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContinuationOptions {
+    // parameter range; the branch ends where it leaves the range
+    pub lower: f64,
+    pub upper: f64,
+    // initial and largest arclength steps; zero picks a fraction of the range
+    pub step: f64,
+    pub max_step: f64,
+    // points in each direction from the starting steady state
+    pub max_points: usize,
+    pub steady: SteadyStateOptions,
+}
+
+impl Default for ContinuationOptions {
+    fn default() -> Self {
+        Self {
+            lower: 0.0,
+            upper: 1.0,
+            step: 0.0,
+            max_step: 0.0,
+            max_points: 1000,
+            steady: SteadyStateOptions::default(),
+        }
+    }
+}
+
+const MAX_CORRECTIONS: usize = 10;
+// bisection steps when locating a bifurcation
+const BISECTIONS: usize = 40;
+
+fn max_norm(v: &[f64]) -> f64 {
+    v.iter().fold(0.0, |m, x| m.max(x.abs()))
+}
+
+fn normalize(mut v: Vec<f64>) -> Vec<f64> {
+    let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    for x in &mut v {
+        *x /= norm;
+    }
+    v
+}
+
+// a point of the branch with what is needed to continue from it
+#[derive(Debug, Clone)]
+struct State {
+    z: Vec<f64>,
+    tangent: Vec<f64>,
+    eigenvalues: Vec<Complex>,
+}
+
+impl State {
+    fn parameter(&self) -> f64 {
+        *self.z.last().expect("z ends with the parameter")
+    }
+
+    // dp/ds changes sign at a fold
+    fn fold_test(&self) -> f64 {
+        *self.tangent.last().expect("tangent ends with dp/ds")
+    }
+
+    // sign of the product of l_i + l_j over pairs of eigenvalues
+    fn hopf_test(&self) -> f64 {
+        let mut product = Complex::new(1.0, 0.0);
+        for (a, &li) in self.eigenvalues.iter().enumerate() {
+            for &lj in &self.eigenvalues[a + 1..] {
+                product = product * (li + lj);
+                let size = product.abs();
+                if size > 0.0 {
+                    product = product / Complex::new(size, 0.0);
+                }
+            }
+        }
+        product.re.signum()
+    }
+}
+
+struct Continuation<'a> {
+    network: &'a Network,
+    base: &'a RateEquations,
+    laws: &'a [ConservationLaw],
+    problem: SteadyStateSystem<'a>,
+    parameter: usize,
+    options: &'a ContinuationOptions,
+}
+
+impl Continuation<'_> {
+    fn system(&self, p: f64) -> RateEquations {
+        let mut system = self.base.clone();
+        system.set_parameter(self.parameter, p);
+        system
+    }
+
+    // F(x, p) and its Jacobian [F_x F_p]
+    fn residual(&self, z: &[f64]) -> (Vec<f64>, Matrix) {
+        let n = z.len() - 1;
+        let (x, p) = (&z[..n], z[n]);
+        let system = self.system(p);
+        let problem = self.problem.with_system(&system);
+        let f = problem.residual(x);
+        let fx = problem.jacobian(x);
+        let fp = system.parameter_jacobian(x);
+        let dependents: Vec<usize> = problem.dependents().collect();
+        let mut j = Matrix::zeros(n, n + 1);
+        for i in 0..n {
+            j.row_mut(i)[..n].copy_from_slice(fx.row(i));
+            if !dependents.contains(&i) {
+                j[(i, n)] = fp[(i, self.parameter)];
+            }
+        }
+        (f, j)
+    }
+
+    // [F_z; t^T] bordered by the direction t
+    fn bordered(j: &Matrix, t: &[f64]) -> Matrix {
+        let n = j.rows();
+        let mut a = Matrix::zeros(n + 1, n + 1);
+        for i in 0..n {
+            a.row_mut(i).copy_from_slice(j.row(i));
+        }
+        a.row_mut(n).copy_from_slice(t);
+        a
+    }
+
+    // unit tangent at z, oriented along the previous tangent
+    fn tangent(&self, j: &Matrix, previous: &[f64]) -> Option<Vec<f64>> {
+        let n = j.rows();
+        let mut rhs = vec![0.0; n + 1];
+        rhs[n] = 1.0;
+        let t = Lu::factor(Self::bordered(j, previous))?.solve(&rhs);
+        Some(normalize(t))
+    }
+
+    fn eigenvalues(&self, z: &[f64]) -> Result<Vec<Complex>, NoConvergence> {
+        let n = z.len() - 1;
+        let system = self.system(z[n]);
+        let (jacobian, _) = stability::reduced_jacobian(self.network, &system, self.laws, &z[..n]);
+        eigen::eigenvalues(&jacobian)
+    }
+
+    // Newton's method on F = 0 within the hyperplane through the predicted
+    // point normal to the tangent
+    fn correct(&self, predicted: &[f64], tangent: &[f64]) -> Option<(Vec<f64>, Matrix, usize)> {
+        let n = predicted.len() - 1;
+        let mut z = predicted.to_vec();
+        for iteration in 0..=MAX_CORRECTIONS {
+            let (mut f, j) = self.residual(&z);
+            if !f.iter().all(|v| v.is_finite()) {
+                return None;
+            }
+            if max_norm(&f) <= self.options.steady.tolerance {
+                return Some((z, j, iteration));
+            }
+            if iteration == MAX_CORRECTIONS {
+                break;
+            }
+            f.push(
+                tangent
+                    .iter()
+                    .zip(z.iter().zip(predicted))
+                    .map(|(t, (a, b))| t * (a - b))
+                    .sum(),
+            );
+            let dz = Lu::factor(Self::bordered(&j, tangent))?.solve(&f);
+            for (zi, d) in z.iter_mut().zip(dz) {
+                *zi -= d;
+            }
+            // the branch ends where concentrations turn negative
+            if z[..n].iter().any(|&x| x < -self.options.steady.tolerance) {
+                return None;
+            }
+        }
+        None
+    }
+
+    // the point a step h along the tangent from `from`
+    fn step(&self, from: &State, h: f64) -> Option<(State, usize)> {
+        let predicted: Vec<f64> = from
+            .z
+            .iter()
+            .zip(&from.tangent)
+            .map(|(z, t)| z + h * t)
+            .collect();
+        let (z, j, iterations) = self.correct(&predicted, &from.tangent)?;
+        let tangent = self.tangent(&j, &from.tangent)?;
+        let eigenvalues = self.eigenvalues(&z).ok()?;
+        Some((
+            State {
+                z,
+                tangent,
+                eigenvalues,
+            },
+            iterations,
+        ))
+    }
+
+    // bisect on the step length for a sign change of the test function
+    fn locate(&self, from: &State, h: f64, test: impl Fn(&State) -> f64) -> Option<State> {
+        let sign = test(from).signum();
+        let (mut lo, mut hi) = (0.0, h);
+        let mut found = None;
+        for _ in 0..BISECTIONS {
+            let mid = 0.5 * (lo + hi);
+            let (state, _) = self.step(from, mid)?;
+            if test(&state).signum() == sign {
+                lo = mid;
+            } else {
+                hi = mid;
+                found = Some(state);
+            }
+        }
+        found
+    }
+
+    fn point(&self, state: &State, bifurcation: Option<Bifurcation>) -> BranchPoint {
+        let n = state.z.len() - 1;
+        BranchPoint {
+            parameter: state.parameter(),
+            concentrations: state.z[..n].to_vec(),
+            stability: stability::classify(&state.eigenvalues),
+            bifurcation,
+        }
+    }
+
+    // bifurcations between two consecutive states
+    fn bifurcations(&self, from: &State, to: &State, h: f64) -> Vec<(f64, BranchPoint)> {
+        let mut found = Vec::new();
+        if from.fold_test().signum() != to.fold_test().signum()
+            && let Some(state) = self.locate(from, h, State::fold_test)
+        {
+            found.push((
+                distance(from, &state),
+                self.point(&state, Some(Bifurcation::Fold)),
+            ));
+        }
+        if from.hopf_test() != to.hopf_test()
+            && let Some(state) = self.locate(from, h, State::hopf_test)
+        {
+            // a complex pair on the imaginary axis, not a pair l, -l
+            let scale = state
+                .eigenvalues
+                .iter()
+                .fold(1.0_f64, |m, l| m.max(l.abs()));
+            if let Some(pair) = state
+                .eigenvalues
+                .iter()
+                .filter(|l| l.im > 1e-6 * scale)
+                .min_by(|a, b| a.re.abs().total_cmp(&b.re.abs()))
+                && pair.re.abs() <= 1e-4 * scale
+            {
+                let hopf = Bifurcation::Hopf { frequency: pair.im };
+                found.push((distance(from, &state), self.point(&state, Some(hopf))));
+            }
+        }
+        found.sort_by(|a, b| a.0.total_cmp(&b.0));
+        found
+    }
+
+    // steady state at the range's end `bound`, from a state just beyond it
+    fn end(&self, from: &State, beyond: &State, bound: f64) -> Option<BranchPoint> {
+        let n = from.z.len() - 1;
+        let u = (bound - from.parameter()) / (beyond.parameter() - from.parameter());
+        let guess: Vec<f64> = (0..n)
+            .map(|i| from.z[i] + u * (beyond.z[i] - from.z[i]))
+            .collect();
+        let system = self.system(bound);
+        let (x, _) = self
+            .problem
+            .with_system(&system)
+            .newton(guess, &self.options.steady)
+            .ok()?;
+        let mut z = x;
+        z.push(bound);
+        let eigenvalues = self.eigenvalues(&z).ok()?;
+        let state = State {
+            z,
+            tangent: from.tangent.clone(),
+            eigenvalues,
+        };
+        Some(self.point(&state, None))
+    }
+
+    // follow the branch from the start until it leaves the range, fails to
+    // converge or has max_points points
+    fn trace(&self, start: &State, h0: f64, max_step: f64) -> Vec<BranchPoint> {
+        let mut points = Vec::new();
+        let mut current = start.clone();
+        let mut h = h0;
+        let min_step = h0 * 1e-6;
+        while points.len() < self.options.max_points {
+            let Some((next, iterations)) = self.step(&current, h) else {
+                h /= 2.0;
+                if h < min_step {
+                    break;
+                }
+                continue;
+            };
+            points.extend(
+                self.bifurcations(&current, &next, h)
+                    .into_iter()
+                    .map(|(_, p)| p),
+            );
+            let p = next.parameter();
+            if p < self.options.lower || p > self.options.upper {
+                let bound = if p < self.options.lower {
+                    self.options.lower
+                } else {
+                    self.options.upper
+                };
+                points.extend(self.end(&current, &next, bound));
+                break;
+            }
+            points.push(self.point(&next, None));
+            if iterations <= 3 {
+                h = (h * 1.5).min(max_step);
+            }
+            current = next;
+        }
+        points
+    }
+}
+
+fn distance(a: &State, b: &State) -> f64 {
+    a.z.iter()
+        .zip(&b.z)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+// the branch of steady states through the one reached from the initial
+// conditions, with the parameter first set within the range
+pub fn continuation(
+    network: &Network,
+    parameter: usize,
+    options: &ContinuationOptions,
+) -> Result<Branch, ContinuationError> {
+    let mut base = RateEquations::new(network).map_err(SteadyStateError::from)?;
+    let p0 = base.parameters()[parameter].clamp(options.lower, options.upper);
+    base.set_parameter(parameter, p0);
+    let laws = conservation::conservation_laws(network).map_err(SteadyStateError::from)?;
+    let x0 = network.initial_conditions();
+    let state =
+        steady_state::steady_state_from(network, &base, &laws, x0.clone(), &options.steady)?;
+    let continuation = Continuation {
+        network,
+        base: &base,
+        laws: &laws,
+        problem: SteadyStateSystem::new(network, &base, &laws, &x0),
+        parameter,
+        options,
+    };
+
+    let mut z = state.concentrations;
+    z.push(p0);
+    let range = options.upper - options.lower;
+    let h0 = if options.step > 0.0 {
+        options.step
+    } else {
+        range / 100.0
+    };
+    let max_step = if options.max_step > 0.0 {
+        options.max_step
+    } else {
+        range / 10.0
+    };
+    let (_, j) = continuation.residual(&z);
+    let mut up = vec![0.0; z.len()];
+    *up.last_mut().expect("z ends with the parameter") = 1.0;
+    let tangent = continuation
+        .tangent(&j, &up)
+        .ok_or(SteadyStateError::SingularJacobian)?;
+    let eigenvalues = continuation.eigenvalues(&z)?;
+    let start = State {
+        z,
+        tangent: tangent.clone(),
+        eigenvalues,
+    };
+    let reverse = State {
+        tangent: tangent.iter().map(|t| -t).collect(),
+        ..start.clone()
+    };
+
+    let mut points: Vec<BranchPoint> = continuation.trace(&reverse, h0, max_step);
+    points.reverse();
+    points.push(continuation.point(&start, None));
+    points.extend(continuation.trace(&start, h0, max_step));
+    Ok(Branch { parameter, points })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    fn options(lower: f64, upper: f64) -> ContinuationOptions {
+        ContinuationOptions {
+            lower,
+            upper,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_both_folds_of_a_bistable_switch() {
+        // steady states satisfy p = y^3 - 3 y with y = X - 3, folding at y = -1 and 1
+        let network =
+            network("species X = 5\nparam p = 0\n0 -> X : p + 3 * (X - 3) - (X - 3) ^ 3\n");
+        let p = network.find_parameter("p").unwrap();
+        let branch = continuation(&network, p, &options(-2.5, 2.5)).unwrap();
+        let mut folds: Vec<(f64, f64)> = branch
+            .bifurcations()
+            .map(|point| {
+                assert_eq!(point.bifurcation, Some(Bifurcation::Fold));
+                (point.parameter, point.concentrations[0])
+            })
+            .collect();
+        folds.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert_eq!(folds.len(), 2);
+        assert!((folds[0].0 + 2.0).abs() < 1e-6 && (folds[0].1 - 4.0).abs() < 1e-6);
+        assert!((folds[1].0 - 2.0).abs() < 1e-6 && (folds[1].1 - 2.0).abs() < 1e-6);
+        // the middle branch between the folds is unstable
+        assert!(branch.points.iter().any(|point| {
+            (point.concentrations[0] - 3.0).abs() < 0.5
+                && point.stability == Stability::UnstableNode
+        }));
+    }
+
+    #[test]
+    fn finds_the_hopf_bifurcation_of_the_brusselator() {
+        // Hopf at b = 1 + a^2 with frequency a, here a = 1
+        let network = network(
+            "species {\n  X = 1\n  Y = 1\n}\nparam b = 1\n\
+             0 -> X : 1\nX -> Y : b * X\n2 X + Y -> 3 X : X ^ 2 * Y\nX -> 0 : X\n",
+        );
+        let b = network.find_parameter("b").unwrap();
+        let branch = continuation(&network, b, &options(1.0, 3.0)).unwrap();
+        let hopf: Vec<&BranchPoint> = branch.bifurcations().collect();
+        assert_eq!(hopf.len(), 1);
+        assert!((hopf[0].parameter - 2.0).abs() < 1e-6);
+        match hopf[0].bifurcation {
+            Some(Bifurcation::Hopf { frequency }) => assert!((frequency - 1.0).abs() < 1e-6),
+            other => panic!("expected a Hopf bifurcation, not {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// metabolic control analysis at a steady state
+//
+// Each reaction's rate is scaled by an activity e_j, as if its enzyme level
+// changed. Control coefficients are the normalized responses of the steady
+// state to these activities, C = d ln y / d ln e_j, and elasticities are
+// the normalized local derivatives of the rates, d ln r_j / d ln x_i.
+// Flux control coefficients of each flux sum to one over the reactions and
+// concentration control coefficients of each species sum to zero.
+// Coefficients normalized by a zero flux or concentration are undefined and
+// left as NaN.
+use super::conservation;
+use super::sensitivity::normalized;
+use super::steady_state::{self, SteadyStateError, SteadyStateOptions, SteadyStateSystem};
+use crate::data::Matrix;
+use crate::network::Network;
+use crate::numeric::Lu;
+use crate::simulation::RateEquations;
+
+#[derive(Debug, Clone)]
+pub struct ControlAnalysis {
+    pub concentrations: Vec<f64>,
+    pub fluxes: Vec<f64>,
+    // reactions by species, d ln r_j / d ln x_i
+    pub elasticities: Matrix,
+    // species by reactions, d ln x_i / d ln e_j
+    pub concentration_control: Matrix,
+    // reactions by reactions, d ln J_k / d ln e_j
+    pub flux_control: Matrix,
+}
+
+pub fn control_analysis(
+    network: &Network,
+    options: &SteadyStateOptions,
+) -> Result<ControlAnalysis, SteadyStateError> {
+    let state = steady_state::steady_state(network, options)?;
+    let system = RateEquations::new(network)?;
+    let laws = conservation::conservation_laws(network)?;
+    let x = state.concentrations;
+    let problem = SteadyStateSystem::new(network, &system, &laws, &x);
+    let lu = Lu::factor(problem.jacobian(&x)).ok_or(SteadyStateError::SingularJacobian)?;
+    let dependents: Vec<usize> = problem.dependents().collect();
+
+    let r = system.rates(&x);
+    let dr = system.rate_jacobian(&x);
+    let n = x.len();
+    let m = r.len();
+    let stoichiometry = system.stoichiometry();
+
+    let mut elasticities = Matrix::zeros(m, n);
+    for j in 0..m {
+        for i in 0..n {
+            elasticities[(j, i)] = normalized(r[j], dr[(j, i)], x[i]);
+        }
+    }
+
+    let mut concentration_control = Matrix::zeros(n, m);
+    let mut flux_control = Matrix::zeros(m, m);
+    for j in 0..m {
+        // d f / d ln e_j is column j of N_V times r_j
+        let b: Vec<f64> = (0..n)
+            .map(|i| {
+                if dependents.contains(&i) {
+                    0.0
+                } else {
+                    -stoichiometry[(i, j)] * r[j]
+                }
+            })
+            .collect();
+        let dx = lu.solve(&b);
+        for i in 0..n {
+            concentration_control[(i, j)] = normalized(x[i], dx[i], 1.0);
+        }
+        let dflux = dr.mul_vec(&dx);
+        for k in 0..m {
+            let direct = if k == j { r[j] } else { 0.0 };
+            flux_control[(k, j)] = normalized(r[k], dflux[k] + direct, 1.0);
+        }
+    }
+    Ok(ControlAnalysis {
+        concentrations: x,
+        fluxes: r,
+        elasticities,
+        concentration_control,
+        flux_control,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    #[test]
+    fn summation_theorems_hold() {
+        let network = network(
+            "species {\n  A = 1\n  B = 1\n}\n\
+             v0 : 0 -> A : 1\nv1 : A <-> B : 2 * A - B\nv2 : B -> 0 : 3 * B / (1 + B)\n",
+        );
+        let mca = control_analysis(&network, &SteadyStateOptions::default()).unwrap();
+        for k in 0..network.num_reactions() {
+            let sum: f64 = mca.flux_control.row(k).iter().sum();
+            assert!((sum - 1.0).abs() < 1e-9);
+        }
+        for i in 0..network.num_species() {
+            let sum: f64 = mca.concentration_control.row(i).iter().sum();
+            assert!(sum.abs() < 1e-9);
+        }
+        // the supply fixes every flux
+        assert!((mca.flux_control[(2, 0)] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_flux_leaves_only_its_rows_undefined() {
+        let network = network(
+            "param k = 0\nv0 : 0 -> A : 1\nv1 : A -> 0 : 2 * A\n\
+             side : A -> C : k * A\nv2 : C -> 0 : C\n",
+        );
+        let mca = control_analysis(&network, &SteadyStateOptions::default()).unwrap();
+        let side = network.find_reaction("side").unwrap();
+        let c = network.find_species("C").unwrap();
+        assert_eq!(mca.fluxes[side], 0.0);
+        assert_eq!(mca.concentrations[c], 0.0);
+        assert!(mca.flux_control.row(side).iter().all(|x| x.is_nan()));
+        assert!(mca.concentration_control.row(c).iter().all(|x| x.is_nan()));
+        let v1 = network.find_reaction("v1").unwrap();
+        let sum: f64 = mca.flux_control.row(v1).iter().sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+    }
+}
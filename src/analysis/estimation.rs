@@ -0,0 +1,572 @@
+// parameter estimation from time-course data
+//
+// Parameters are fitted by weighted least squares: each residual is the
+// difference between simulated and measured concentration divided by the
+// measurement's standard deviation. Levenberg-Marquardt steps use the
+// Jacobian of the residuals from the forward sensitivity equations. Positive
+// parameters may be fitted on a log scale, and every step is projected onto
+// the declared bounds. Confidence intervals come from the covariance
+// (J^T J)^-1 at the optimum, scaled by the residual variance when the data
+// carry no standard deviations.
+use std::error::Error;
+use std::fmt;
+
+use super::sensitivity;
+use crate::data::Matrix;
+use crate::network::Network;
+use crate::numeric::Lu;
+use crate::numeric::ode::OdeOptions;
+use crate::simulation::{RateEquations, SimulationError};
+
+#[derive(Debug)]
+pub enum EstimationError {
+    // line number and description
+    Data(usize, String),
+    NoData,
+    Simulation(SimulationError),
+    // more parameters than data points
+    Underdetermined(usize, usize),
+}
+
+impl fmt::Display for EstimationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EstimationError::Data(line, msg) => write!(f, "Data line {line}: {msg}"),
+            EstimationError::NoData => write!(f, "No measurements to fit"),
+            EstimationError::Simulation(e) => write!(f, "{e}"),
+            EstimationError::Underdetermined(p, n) => {
+                write!(f, "Cannot fit {p} parameters to {n} measurements")
+            }
+        }
+    }
+}
+
+impl Error for EstimationError {}
+
+impl From<SimulationError> for EstimationError {
+    fn from(e: SimulationError) -> Self {
+        EstimationError::Simulation(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub time: f64,
+    pub species: usize,
+    pub value: f64,
+    pub sd: Option<f64>,
+}
+
+// measured concentrations, read from CSV with a `time` column, one column
+// per measured species and optional `<species>_sd` columns of standard
+// deviations; empty cells are missing values
+#[derive(Debug, Clone, Default)]
+pub struct Data {
+    pub measurements: Vec<Measurement>,
+}
+
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Column {
+    Time,
+    Value(usize),
+    Sd(usize),
+}
+
+impl Data {
+    pub fn parse(text: &str, network: &Network) -> Result<Data, EstimationError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        let Some((_, header)) = lines.next() else {
+            return Err(EstimationError::NoData);
+        };
+        let columns = split_csv(header)
+            .iter()
+            .map(|name| {
+                if name == "time" || name == "t" {
+                    Ok(Column::Time)
+                } else if let Some(i) = network.find_species(name) {
+                    Ok(Column::Value(i))
+                } else if let Some(i) = name
+                    .strip_suffix("_sd")
+                    .and_then(|s| network.find_species(s))
+                {
+                    Ok(Column::Sd(i))
+                } else {
+                    Err(EstimationError::Data(1, format!("unknown column '{name}'")))
+                }
+            })
+            .collect::<Result<Vec<Column>, EstimationError>>()?;
+        if !columns.iter().any(|c| matches!(c, Column::Time)) {
+            return Err(EstimationError::Data(1, "missing time column".to_string()));
+        }
+
+        let mut data = Data::default();
+        for (index, line) in lines {
+            let number = index + 1;
+            let fields = split_csv(line);
+            if fields.len() != columns.len() {
+                return Err(EstimationError::Data(
+                    number,
+                    format!("expected {} fields, found {}", columns.len(), fields.len()),
+                ));
+            }
+            let parse = |s: &str| -> Result<Option<f64>, EstimationError> {
+                if s.is_empty() || s.eq_ignore_ascii_case("na") {
+                    return Ok(None);
+                }
+                s.parse()
+                    .map(Some)
+                    .map_err(|_| EstimationError::Data(number, format!("invalid number '{s}'")))
+            };
+            let mut time = None;
+            let mut values = Vec::new();
+            let mut sds = Vec::new();
+            for (column, field) in columns.iter().zip(&fields) {
+                match column {
+                    Column::Time => time = parse(field)?,
+                    Column::Value(i) => values.push((*i, parse(field)?)),
+                    Column::Sd(i) => sds.push((*i, parse(field)?)),
+                }
+            }
+            let time =
+                time.ok_or_else(|| EstimationError::Data(number, "missing time".to_string()))?;
+            if !(time.is_finite() && time >= 0.0) {
+                return Err(EstimationError::Data(
+                    number,
+                    format!("time must be finite and nonnegative, not {time}"),
+                ));
+            }
+            for (species, value) in values {
+                let Some(value) = value else { continue };
+                let sd = sds
+                    .iter()
+                    .find(|(i, _)| *i == species)
+                    .and_then(|(_, sd)| *sd);
+                if sd.is_some_and(|sd| sd <= 0.0) {
+                    return Err(EstimationError::Data(
+                        number,
+                        "standard deviations must be positive".to_string(),
+                    ));
+                }
+                data.measurements.push(Measurement {
+                    time,
+                    species,
+                    value,
+                    sd,
+                });
+            }
+        }
+        if data.measurements.is_empty() {
+            return Err(EstimationError::NoData);
+        }
+        Ok(data)
+    }
+
+    // distinct measurement times in increasing order
+    pub fn times(&self) -> Vec<f64> {
+        let mut times: Vec<f64> = self.measurements.iter().map(|m| m.time).collect();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+        times
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    Linear,
+    // fit the logarithm of positive parameters
+    Log,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EstimationOptions {
+    pub scale: Scale,
+    pub max_iterations: usize,
+    // relative decrease of the objective below which the fit has converged
+    pub tolerance: f64,
+    pub ode: OdeOptions,
+}
+
+impl Default for EstimationOptions {
+    fn default() -> Self {
+        Self {
+            scale: Scale::Log,
+            max_iterations: 200,
+            tolerance: 1e-10,
+            ode: OdeOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParameterEstimate {
+    pub parameter: usize,
+    pub value: f64,
+    pub std_error: f64,
+    // 95% confidence interval
+    pub lower: f64,
+    pub upper: f64,
+    pub log_scale: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fit {
+    pub estimates: Vec<ParameterEstimate>,
+    // weighted sum of squared residuals
+    pub objective: f64,
+    pub iterations: usize,
+    // simulated value and weighted residual for each measurement
+    pub predictions: Vec<f64>,
+    pub residuals: Vec<f64>,
+}
+
+// a fitted parameter: its index, bounds and scale
+struct Free {
+    parameter: usize,
+    lower: f64,
+    upper: f64,
+    log: bool,
+}
+
+impl Free {
+    fn to_internal(&self, p: f64) -> f64 {
+        if self.log { p.ln() } else { p }
+    }
+
+    fn to_value(&self, theta: f64) -> f64 {
+        if self.log { theta.exp() } else { theta }
+    }
+
+    // d p / d theta
+    fn scale(&self, p: f64) -> f64 {
+        if self.log { p } else { 1.0 }
+    }
+
+    fn max_step(&self, theta: f64) -> f64 {
+        if self.log { 1.0 } else { theta.abs().max(1.0) }
+    }
+
+    fn clamp(&self, theta: f64) -> f64 {
+        let lower = self.to_internal(self.lower);
+        let upper = self.to_internal(self.upper);
+        theta.max(lower).min(upper)
+    }
+}
+
+struct Problem<'a> {
+    network: &'a Network,
+    data: &'a Data,
+    free: Vec<Free>,
+    times: Vec<f64>,
+    ode: OdeOptions,
+}
+
+// residuals and their Jacobian with respect to the internal parameters
+type Evaluation = (Vec<f64>, Vec<f64>, Matrix);
+
+impl Problem<'_> {
+    fn values(&self, theta: &[f64]) -> Vec<f64> {
+        self.free
+            .iter()
+            .zip(theta)
+            .map(|(f, &t)| f.to_value(t))
+            .collect()
+    }
+
+    fn evaluate(&self, theta: &[f64]) -> Result<Evaluation, SimulationError> {
+        let mut system = RateEquations::new(self.network)?;
+        let values = self.values(theta);
+        for (f, &v) in self.free.iter().zip(&values) {
+            system.set_parameter(f.parameter, v);
+        }
+        let parameters: Vec<usize> = self.free.iter().map(|f| f.parameter).collect();
+        let result = sensitivity::integrate(
+            &system,
+            self.network.initial_conditions(),
+            &parameters,
+            &self.times,
+            self.ode,
+        )?;
+
+        let measurements = &self.data.measurements;
+        let mut predictions = Vec::with_capacity(measurements.len());
+        let mut residuals = Vec::with_capacity(measurements.len());
+        let mut jacobian = Matrix::zeros(measurements.len(), self.free.len());
+        for (row, m) in measurements.iter().enumerate() {
+            let point = result
+                .points
+                .iter()
+                .find(|p| p.time == m.time)
+                .expect("every measurement time is sampled");
+            let sd = m.sd.unwrap_or(1.0);
+            let predicted = point.concentrations[m.species];
+            predictions.push(predicted);
+            residuals.push((predicted - m.value) / sd);
+            for (c, f) in self.free.iter().enumerate() {
+                jacobian[(row, c)] =
+                    point.species_sensitivities[(m.species, c)] * f.scale(values[c]) / sd;
+            }
+        }
+        Ok((predictions, residuals, jacobian))
+    }
+}
+
+fn sum_of_squares(r: &[f64]) -> f64 {
+    r.iter().map(|x| x * x).sum()
+}
+
+// J^T J and J^T r
+fn normal_equations(j: &Matrix, r: &[f64]) -> (Matrix, Vec<f64>) {
+    let jt = j.transpose();
+    let g = jt.mul_vec(r);
+    (jt.mul(j), g)
+}
+
+// two-sided 95% quantile of Student's t distribution, by the Cornish-Fisher
+// expansion about the normal quantile
+fn t_quantile(dof: usize) -> f64 {
+    let z: f64 = 1.959_963_984_540_054;
+    if dof == 0 {
+        return f64::INFINITY;
+    }
+    let v = dof as f64;
+    let (z3, z5, z7) = (z.powi(3), z.powi(5), z.powi(7));
+    z + (z3 + z) / (4.0 * v)
+        + (5.0 * z5 + 16.0 * z3 + 3.0 * z) / (96.0 * v * v)
+        + (3.0 * z7 + 19.0 * z5 + 17.0 * z3 - 15.0 * z) / (384.0 * v.powi(3))
+}
+
+// Levenberg-Marquardt step from theta, clamped to the bounds; parameters at
+// a bound that the gradient pushes against are held fixed
+fn damped_step(
+    a: &Matrix,
+    g: &[f64],
+    lambda: f64,
+    theta: &[f64],
+    free: &[Free],
+) -> Option<Vec<f64>> {
+    let moving: Vec<usize> = (0..theta.len())
+        .filter(|&c| {
+            let f = &free[c];
+            let at_lower = theta[c] <= f.to_internal(f.lower) && g[c] > 0.0;
+            let at_upper = theta[c] >= f.to_internal(f.upper) && g[c] < 0.0;
+            !(at_lower || at_upper)
+        })
+        .collect();
+    let mut damped = Matrix::zeros(moving.len(), moving.len());
+    for (r, &c) in moving.iter().enumerate() {
+        for (s, &d) in moving.iter().enumerate() {
+            damped[(r, s)] = a[(c, d)];
+        }
+        damped[(r, r)] += lambda * a[(c, c)].max(1e-12);
+    }
+    let rhs: Vec<f64> = moving.iter().map(|&c| g[c]).collect();
+    let step = Lu::factor(damped)?.solve(&rhs);
+    // a step changes no parameter by more than a factor e, or by more than
+    // its magnitude on the linear scale, to stay clear of flat regions
+    let shrink = moving
+        .iter()
+        .zip(&step)
+        .map(|(&c, s)| s.abs() / free[c].max_step(theta[c]))
+        .fold(1.0, f64::max);
+    let mut trial = theta.to_vec();
+    for (&c, s) in moving.iter().zip(step) {
+        trial[c] = free[c].clamp(theta[c] - s / shrink);
+    }
+    Some(trial)
+}
+
+// fit the given parameters of the network to the data
+pub fn fit(
+    network: &Network,
+    data: &Data,
+    parameters: &[usize],
+    options: &EstimationOptions,
+) -> Result<Fit, EstimationError> {
+    if !network.events().is_empty() {
+        let e = SimulationError::UnsupportedEvents("parameter estimation");
+        return Err(EstimationError::Simulation(e));
+    }
+    let n = data.measurements.len();
+    if parameters.len() > n {
+        return Err(EstimationError::Underdetermined(parameters.len(), n));
+    }
+    let initial = network.parameter_values();
+    let free: Vec<Free> = parameters
+        .iter()
+        .map(|&k| {
+            let attr = network.parameter_attributes(k);
+            let lower = attr.lower.unwrap_or(f64::NEG_INFINITY);
+            let upper = attr.upper.unwrap_or(f64::INFINITY);
+            // the log scale needs a positive parameter that stays positive
+            let log = options.scale == Scale::Log && initial[k] > 0.0 && lower >= 0.0;
+            Free {
+                parameter: k,
+                lower,
+                upper,
+                log,
+            }
+        })
+        .collect();
+    let problem = Problem {
+        network,
+        data,
+        free,
+        times: data.times(),
+        ode: options.ode,
+    };
+
+    let mut theta: Vec<f64> = problem
+        .free
+        .iter()
+        .map(|f| f.clamp(f.to_internal(initial[f.parameter])))
+        .collect();
+    let (mut predictions, mut residuals, mut jacobian) = problem.evaluate(&theta)?;
+    let mut cost = sum_of_squares(&residuals);
+    let mut lambda = 1e-3;
+    let mut iterations = 0;
+    while iterations < options.max_iterations {
+        iterations += 1;
+        let (a, g) = normal_equations(&jacobian, &residuals);
+        let trial = damped_step(&a, &g, lambda, &theta, &problem.free);
+        let accepted = match trial.as_ref().map(|t| (t, problem.evaluate(t))) {
+            Some((t, Ok((p, r, j)))) if sum_of_squares(&r) < cost => {
+                let new_cost = sum_of_squares(&r);
+                let decrease = (cost - new_cost) / cost.max(f64::MIN_POSITIVE);
+                theta = t.clone();
+                predictions = p;
+                residuals = r;
+                jacobian = j;
+                cost = new_cost;
+                lambda = (lambda / 10.0).max(1e-12);
+                Some(decrease)
+            }
+            _ => {
+                lambda *= 10.0;
+                None
+            }
+        };
+        match accepted {
+            Some(decrease) if decrease < options.tolerance => break,
+            None if lambda > 1e12 => break,
+            _ => {}
+        }
+        if cost == 0.0 {
+            break;
+        }
+    }
+
+    // covariance of the internal parameters
+    let p = problem.free.len();
+    let weighted = data.measurements.iter().any(|m| m.sd.is_some());
+    let dof = n - p;
+    let variance = if weighted || dof == 0 {
+        1.0
+    } else {
+        cost / dof as f64
+    };
+    let (a, _) = normal_equations(&jacobian, &residuals);
+    let covariance = Lu::factor(a).map(|lu| {
+        (0..p)
+            .map(|c| {
+                let mut e = vec![0.0; p];
+                e[c] = 1.0;
+                lu.solve(&e)[c] * variance
+            })
+            .collect::<Vec<f64>>()
+    });
+    let t = t_quantile(dof);
+    let values = problem.values(&theta);
+    let estimates = problem
+        .free
+        .iter()
+        .enumerate()
+        .map(|(c, f)| {
+            let sigma = covariance
+                .as_ref()
+                .map_or(f64::INFINITY, |v| v[c].max(0.0).sqrt());
+            let value = values[c];
+            ParameterEstimate {
+                parameter: f.parameter,
+                value,
+                std_error: sigma * f.scale(value),
+                lower: f.to_value(theta[c] - t * sigma),
+                upper: f.to_value(theta[c] + t * sigma),
+                log_scale: f.log,
+            }
+        })
+        .collect();
+    Ok(Fit {
+        estimates,
+        objective: cost,
+        iterations,
+        predictions,
+        residuals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network() -> Network {
+        let text = "species {\n  A = 1\n}\nparam k_decay = 1\ndecay : A -> 0\n";
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    #[test]
+    fn parses_measurements_with_missing_values() {
+        let data = Data::parse("time,A,A_sd\n0,1,0.1\n1,,\n2,0.5,\n", &network()).unwrap();
+        assert_eq!(data.measurements.len(), 2);
+        assert_eq!(data.measurements[1].time, 2.0);
+        assert_eq!(data.measurements[1].sd, None);
+        assert_eq!(data.times(), vec![0.0, 2.0]);
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_times() {
+        for time in ["-1", "nan", "inf"] {
+            let text = format!("time,A\n0,1\n{time},0.5\n");
+            assert!(matches!(
+                Data::parse(&text, &network()),
+                Err(EstimationError::Data(3, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn predictions_match_measurement_times() {
+        // measurements out of order and without t = 0
+        let data = Data::parse("time,A\n2,0.1353\n1,0.3679\n", &network()).unwrap();
+        let network = network();
+        let k = network.find_parameter("k_decay").unwrap();
+        let result = fit(&network, &data, &[k], &EstimationOptions::default()).unwrap();
+        assert!((result.estimates[0].value - 1.0).abs() < 1e-3);
+        assert!((result.predictions[0] - 0.1353).abs() < 1e-3);
+        assert!((result.predictions[1] - 0.3679).abs() < 1e-3);
+    }
+}
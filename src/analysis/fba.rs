@@ -0,0 +1,241 @@
+// flux balance analysis
+//
+// fluxes v satisfy the steady state N v = 0 within per-reaction bounds,
+// while an objective reaction is maximized or minimized. Constant and
+// boundary species are exchanged with the surroundings and left out of N.
+use std::error::Error;
+use std::fmt;
+
+use crate::data::Matrix;
+use crate::network::{Network, Sense};
+use crate::numeric::{LinearProgram, LpError, LpSolution, Relation};
+
+#[derive(Debug)]
+pub enum FbaError {
+    NoObjective,
+    UnknownReaction(String),
+    Lp(LpError),
+}
+
+impl fmt::Display for FbaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FbaError::NoObjective => write!(
+                f,
+                "No objective declared; add `maximize <reaction>` or `minimize <reaction>`"
+            ),
+            FbaError::UnknownReaction(r) => write!(f, "Unknown reaction '{r}'"),
+            FbaError::Lp(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for FbaError {}
+
+impl From<LpError> for FbaError {
+    fn from(e: LpError) -> Self {
+        FbaError::Lp(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FluxDistribution {
+    pub objective: f64,
+    pub fluxes: Vec<f64>,
+}
+
+// flux range of a reaction over all (near) optimal distributions
+#[derive(Debug, Clone, Copy)]
+pub struct FluxRange {
+    pub minimum: f64,
+    pub maximum: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Knockout {
+    pub reaction: usize,
+    // None if the knockout makes the model infeasible
+    pub objective: Option<f64>,
+}
+
+pub struct FluxBalance {
+    stoichiometry: Matrix,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    objective: usize,
+    sense: Sense,
+}
+
+impl FluxBalance {
+    pub fn new(network: &Network) -> Result<Self, FbaError> {
+        let n = network.num_reactions();
+
+        // irreversible reactions only carry forward flux by default
+        let mut lower: Vec<f64> = network
+            .reactions()
+            .iter()
+            .map(|r| {
+                if r.is_reversible() {
+                    f64::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let mut upper = vec![f64::INFINITY; n];
+
+        for bound in network.flux_bounds() {
+            let j = network
+                .find_reaction(&bound.reaction)
+                .ok_or_else(|| FbaError::UnknownReaction(bound.reaction.clone()))?;
+            lower[j] = bound.lower;
+            upper[j] = bound.upper;
+        }
+
+        let (name, sense) = network.objective().ok_or(FbaError::NoObjective)?;
+        let objective = network
+            .find_reaction(name)
+            .ok_or_else(|| FbaError::UnknownReaction(name.to_string()))?;
+
+        // constant and boundary species need not balance
+        let mut stoichiometry = network.stoichiometric_matrix();
+        for i in 0..network.num_species() {
+            if network.species_attributes(i).is_fixed() {
+                stoichiometry.row_mut(i).fill(0.0);
+            }
+        }
+
+        Ok(Self {
+            stoichiometry,
+            lower,
+            upper,
+            objective,
+            sense,
+        })
+    }
+
+    pub fn bounds(&self, reaction: usize) -> (f64, f64) {
+        (self.lower[reaction], self.upper[reaction])
+    }
+
+    pub fn set_bounds(&mut self, reaction: usize, lower: f64, upper: f64) {
+        self.lower[reaction] = lower;
+        self.upper[reaction] = upper;
+    }
+
+    // steady state constraints and flux bounds, without an objective
+    fn program(&self) -> LinearProgram {
+        let n = self.lower.len();
+        let mut lp = LinearProgram::new(n);
+        for j in 0..n {
+            lp.set_bounds(j, self.lower[j], self.upper[j]);
+        }
+        for i in 0..self.stoichiometry.rows() {
+            let row = self.stoichiometry.row(i).to_vec();
+            if row.iter().any(|&a| a != 0.0) {
+                lp.add_constraint(row, Relation::Equal, 0.0);
+            }
+        }
+        lp
+    }
+
+    fn unit(&self, j: usize) -> Vec<f64> {
+        let mut c = vec![0.0; self.lower.len()];
+        c[j] = 1.0;
+        c
+    }
+
+    fn solve(&self, lp: &LinearProgram, sense: Sense) -> Result<LpSolution, LpError> {
+        match sense {
+            Sense::Maximize => lp.maximize(),
+            Sense::Minimize => lp.minimize(),
+        }
+    }
+
+    pub fn optimize(&self) -> Result<FluxDistribution, FbaError> {
+        let mut lp = self.program();
+        lp.set_objective(self.unit(self.objective));
+        let sol = self.solve(&lp, self.sense)?;
+        Ok(FluxDistribution {
+            objective: sol.objective,
+            fluxes: sol.x,
+        })
+    }
+
+    // range of each flux while the objective stays within `fraction` of its optimum
+    pub fn variability(&self, fraction: f64) -> Result<Vec<FluxRange>, FbaError> {
+        let optimum = self.optimize()?.objective;
+        let mut lp = self.program();
+        let c = self.unit(self.objective);
+        match self.sense {
+            Sense::Maximize => {
+                let target = optimum - (1.0 - fraction) * optimum.abs();
+                lp.add_constraint(c, Relation::GreaterEqual, target);
+            }
+            Sense::Minimize => {
+                let target = optimum + (1.0 - fraction) * optimum.abs();
+                lp.add_constraint(c, Relation::LessEqual, target);
+            }
+        }
+
+        let mut ranges = Vec::with_capacity(self.lower.len());
+        for j in 0..self.lower.len() {
+            lp.set_objective(self.unit(j));
+            let minimum = match lp.minimize() {
+                Ok(sol) => sol.objective,
+                Err(LpError::Unbounded) => f64::NEG_INFINITY,
+                Err(e) => return Err(e.into()),
+            };
+            let maximum = match lp.maximize() {
+                Ok(sol) => sol.objective,
+                Err(LpError::Unbounded) => f64::INFINITY,
+                Err(e) => return Err(e.into()),
+            };
+            ranges.push(FluxRange { minimum, maximum });
+        }
+        Ok(ranges)
+    }
+
+    // optimal objective with each reaction in turn blocked
+    pub fn knockouts(&self) -> Result<Vec<Knockout>, FbaError> {
+        let mut scan = Vec::with_capacity(self.lower.len());
+        for j in 0..self.lower.len() {
+            let mut lp = self.program();
+            lp.set_bounds(j, 0.0, 0.0);
+            lp.set_objective(self.unit(self.objective));
+            let objective = match self.solve(&lp, self.sense) {
+                Ok(sol) => Some(sol.objective),
+                Err(LpError::Infeasible) => None,
+                Err(e) => return Err(e.into()),
+            };
+            scan.push(Knockout {
+                reaction: j,
+                objective,
+            });
+        }
+        Ok(scan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    #[test]
+    fn boundary_species_need_not_balance() {
+        let model = "uptake : 0 -> A\ngrowth : A -> B\nflux uptake [0, 10]\nmaximize growth\n";
+        let balanced = FluxBalance::new(&network(model)).unwrap();
+        assert_eq!(balanced.optimize().unwrap().objective, 0.0);
+        let open = format!("species B [boundary]\n{model}");
+        let fba = FluxBalance::new(&network(&open)).unwrap();
+        assert_eq!(fba.optimize().unwrap().objective, 10.0);
+        let ranges = fba.variability(0.5).unwrap();
+        assert_eq!((ranges[0].minimum, ranges[0].maximum), (5.0, 10.0));
+    }
+}
@@ -0,0 +1,192 @@
+// gaps of a network: dead-end species and blocked reactions
+//
+// At steady state a species must be both produced and consumed. One that
+// only the remaining reactions produce, or only consume, or that only a
+// single reaction changes, forces the flux of those reactions to zero. Those
+// reactions are blocked, which can leave further species as dead ends, so
+// the search repeats until nothing changes. Exchange reactions such as
+// `0 -> A` count like any other, and constant or boundary species are
+// sources and sinks that never limit a reaction. Flux bounds restrict the
+// directions a reaction can run in, and close it if both are zero.
+use std::fmt;
+
+use crate::network::Network;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gap {
+    OnlyProduced,
+    OnlyConsumed,
+    // changed by a single reaction, even a reversible one
+    SingleReaction,
+}
+
+impl fmt::Display for Gap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Gap::OnlyProduced => write!(f, "only produced"),
+            Gap::OnlyConsumed => write!(f, "only consumed"),
+            Gap::SingleReaction => write!(f, "single reaction"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadEnd {
+    pub species: usize,
+    pub gap: Gap,
+    // unblocked reactions that change it, which it blocks
+    pub reactions: Vec<usize>,
+    // pass of the search that found it, from 1; later passes find dead ends
+    // caused by reactions blocked earlier
+    pub round: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Gaps {
+    pub dead_ends: Vec<DeadEnd>,
+    // each blocked reaction with the dead end that blocked it, or None if
+    // its flux bounds close it
+    pub blocked: Vec<(usize, Option<usize>)>,
+}
+
+impl Gaps {
+    pub fn is_blocked(&self, reaction: usize) -> bool {
+        self.blocked.iter().any(|&(j, _)| j == reaction)
+    }
+}
+
+// whether each reaction can run forward and backward
+fn directions(network: &Network) -> Vec<(bool, bool)> {
+    let mut directions: Vec<(bool, bool)> = network
+        .reactions()
+        .iter()
+        .map(|r| (true, r.is_reversible()))
+        .collect();
+    for bound in network.flux_bounds() {
+        if let Some(j) = network.find_reaction(&bound.reaction) {
+            directions[j] = (bound.upper > 0.0, bound.lower < 0.0);
+        }
+    }
+    directions
+}
+
+pub fn gaps(network: &Network) -> Gaps {
+    let n = network.stoichiometric_matrix();
+    let directions = directions(network);
+    let mut out = Gaps::default();
+    let mut blocked: Vec<bool> = directions.iter().map(|&(f, b)| !f && !b).collect();
+    for (j, _) in blocked.iter().enumerate().filter(|(_, b)| **b) {
+        out.blocked.push((j, None));
+    }
+    let mut round = 0;
+    loop {
+        round += 1;
+        let mut found = Vec::new();
+        for i in 0..network.num_species() {
+            if network.species_attributes(i).is_fixed() {
+                continue;
+            }
+            let reactions: Vec<usize> = (0..network.num_reactions())
+                .filter(|&j| !blocked[j] && n[(i, j)] != 0.0)
+                .collect();
+            if reactions.is_empty() {
+                continue;
+            }
+            let mut produced = false;
+            let mut consumed = false;
+            for &j in &reactions {
+                let (forward, backward) = directions[j];
+                let made = n[(i, j)] > 0.0;
+                produced |= forward && made || backward && !made;
+                consumed |= forward && !made || backward && made;
+            }
+            let gap = if !consumed {
+                Gap::OnlyProduced
+            } else if !produced {
+                Gap::OnlyConsumed
+            } else if reactions.len() == 1 {
+                Gap::SingleReaction
+            } else {
+                continue;
+            };
+            found.push((i, gap, reactions));
+        }
+        if found.is_empty() {
+            break;
+        }
+        // reactions are credited to the first dead end of the pass that
+        // blocks them
+        for (species, gap, reactions) in found {
+            for &j in &reactions {
+                if !blocked[j] {
+                    blocked[j] = true;
+                    out.blocked.push((j, Some(species)));
+                }
+            }
+            out.dead_ends.push(DeadEnd {
+                species,
+                gap,
+                reactions,
+                round,
+            });
+        }
+    }
+    out.blocked.sort_unstable();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    #[test]
+    fn blocking_propagates_through_rounds() {
+        let network = network(
+            "uptake : 0 -> A\nv1 : A -> B\nv2 : B -> C\nv3 : B -> 0\n\
+             r1 : D <-> E\nr2 : E <-> 0\nv4 : A -> F\nv5 : F -> 0\nflux v4 [0, 0]\n",
+        );
+        let gaps = gaps(&network);
+        let species = |name| network.find_species(name).unwrap();
+        let reaction = |name| network.find_reaction(name).unwrap();
+        let found: Vec<(usize, Gap, Vec<usize>, usize)> = gaps
+            .dead_ends
+            .iter()
+            .map(|d| (d.species, d.gap, d.reactions.clone(), d.round))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (species("C"), Gap::OnlyProduced, vec![reaction("v2")], 1),
+                (species("D"), Gap::SingleReaction, vec![reaction("r1")], 1),
+                (species("F"), Gap::OnlyConsumed, vec![reaction("v5")], 1),
+                (species("E"), Gap::SingleReaction, vec![reaction("r2")], 2),
+            ]
+        );
+        assert_eq!(
+            gaps.blocked,
+            vec![
+                (reaction("v2"), Some(species("C"))),
+                (reaction("r1"), Some(species("D"))),
+                (reaction("r2"), Some(species("E"))),
+                (reaction("v4"), None),
+                (reaction("v5"), Some(species("F"))),
+            ]
+        );
+        for name in ["uptake", "v1", "v3"] {
+            assert!(!gaps.is_blocked(reaction(name)));
+        }
+    }
+
+    #[test]
+    fn fixed_species_never_limit_a_reaction() {
+        let network = network("species S [constant]\nS -> A\nA -> P\nspecies P [boundary]\n");
+        let gaps = gaps(&network);
+        assert!(gaps.dead_ends.is_empty() && gaps.blocked.is_empty());
+    }
+}
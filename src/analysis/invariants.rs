@@ -0,0 +1,95 @@
+// P- and T-invariants of the network as a Petri net
+//
+// Species are places and reactions transitions, with reactants as input arcs
+// and products as output arcs weighted by their coefficients. A reversible
+// reaction is two transitions, the second named `<reaction>_rev`. Constant
+// and boundary species do not change, so their arcs cancel.
+//
+// A P-invariant y >= 0 weights places so that no transition changes the
+// weighted token count: a nonnegative conservation law. A T-invariant x >= 0
+// counts firings of each transition that together restore every marking: a
+// cycle the network can run at steady state. Only invariants of minimal
+// support are reported; every other one is a nonnegative combination of
+// them.
+use crate::data::{Matrix, Overflow, Rational};
+use crate::network::{Complex, Network};
+use crate::numeric::exact;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub reaction: usize,
+    // the backward direction of a reversible reaction
+    pub reversed: bool,
+}
+
+impl Transition {
+    pub fn name(&self, network: &Network) -> String {
+        let name = network.reaction_name(self.reaction);
+        if self.reversed {
+            format!("{name}_rev")
+        } else {
+            name
+        }
+    }
+
+    pub fn inputs<'a>(&self, network: &'a Network) -> &'a Complex {
+        let rxn = &network.reactions()[self.reaction];
+        if self.reversed {
+            rxn.get_products()
+        } else {
+            rxn.get_reactants()
+        }
+    }
+
+    pub fn outputs<'a>(&self, network: &'a Network) -> &'a Complex {
+        let rxn = &network.reactions()[self.reaction];
+        if self.reversed {
+            rxn.get_reactants()
+        } else {
+            rxn.get_products()
+        }
+    }
+}
+
+pub fn transitions(network: &Network) -> Vec<Transition> {
+    let mut out = Vec::new();
+    for (j, rxn) in network.reactions().iter().enumerate() {
+        out.push(Transition {
+            reaction: j,
+            reversed: false,
+        });
+        if rxn.is_reversible() {
+            out.push(Transition {
+                reaction: j,
+                reversed: true,
+            });
+        }
+    }
+    out
+}
+
+// change of each species by one firing of each transition
+pub fn incidence(network: &Network, transitions: &[Transition]) -> Matrix<Rational> {
+    let n = network.exact_stoichiometric_matrix();
+    let mut c = Matrix::zeros(network.num_species(), transitions.len());
+    for (k, t) in transitions.iter().enumerate() {
+        for i in 0..network.num_species() {
+            if network.species_attributes(i).is_fixed() {
+                continue;
+            }
+            let x = n[(i, t.reaction)];
+            c[(i, k)] = if t.reversed { -x } else { x };
+        }
+    }
+    c
+}
+
+// minimal P-invariants, as integer weights of each species
+pub fn p_invariants(network: &Network) -> Result<Vec<Vec<Rational>>, Overflow> {
+    exact::semiflows(&incidence(network, &transitions(network)))
+}
+
+// minimal T-invariants, as integer firing counts of each of `transitions`
+pub fn t_invariants(network: &Network) -> Result<Vec<Vec<Rational>>, Overflow> {
+    exact::semiflows(&incidence(network, &transitions(network)).transpose())
+}
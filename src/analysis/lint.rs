@@ -0,0 +1,328 @@
+// semantic lints over a parsed network
+//
+// Each lint has a code that a model can `allow` or `deny`, e.g.
+// `allow no_products, dead_end`, and that `check --allow` and `--deny`
+// override. Lints warn by default. Most catch typos that the language cannot:
+// a misspelled species is silently a new species, which then appears in a
+// single reaction and is only produced or only consumed.
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::network::{Complex, LintLevel, Network, ParameterWarning, SpeciesId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lint {
+    DuplicateReaction,
+    TrivialReaction,
+    SingleUse,
+    DeadEnd,
+    NoProducts,
+    SimilarNames,
+    UnusedParameter,
+    UndeclaredParameter,
+    Unbalanced,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 9] = [
+        Lint::DuplicateReaction,
+        Lint::TrivialReaction,
+        Lint::SingleUse,
+        Lint::DeadEnd,
+        Lint::NoProducts,
+        Lint::SimilarNames,
+        Lint::UnusedParameter,
+        Lint::UndeclaredParameter,
+        Lint::Unbalanced,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::DuplicateReaction => "duplicate_reaction",
+            Lint::TrivialReaction => "trivial_reaction",
+            Lint::SingleUse => "single_use",
+            Lint::DeadEnd => "dead_end",
+            Lint::NoProducts => "no_products",
+            Lint::SimilarNames => "similar_names",
+            Lint::UnusedParameter => "unused_parameter",
+            Lint::UndeclaredParameter => "undeclared_parameter",
+            Lint::Unbalanced => "unbalanced",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Lint::ALL.into_iter().find(|l| l.code() == code)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub message: String,
+}
+
+// level of each lint: warn, then the model's settings, then `overrides`
+// (e.g. from the command line); unknown codes are returned
+pub fn levels(
+    network: &Network,
+    overrides: &[(String, LintLevel)],
+) -> (Vec<(Lint, LintLevel)>, Vec<String>) {
+    let mut levels: Vec<(Lint, LintLevel)> =
+        Lint::ALL.iter().map(|&l| (l, LintLevel::Warn)).collect();
+    let mut unknown = Vec::new();
+    for (code, level) in network.lint_levels().iter().chain(overrides) {
+        match Lint::from_code(code) {
+            Some(lint) => {
+                let entry = levels
+                    .iter_mut()
+                    .find(|(l, _)| *l == lint)
+                    .expect("all lints have a level");
+                entry.1 = *level;
+            }
+            None if !unknown.contains(code) => unknown.push(code.clone()),
+            None => {}
+        }
+    }
+    (levels, unknown)
+}
+
+// whether the model allows a lint, for warnings printed outside `check`
+pub fn allowed(network: &Network, lint: Lint) -> bool {
+    let (levels, _) = levels(network, &[]);
+    levels.contains(&(lint, LintLevel::Allow))
+}
+
+// edit distance by insertions, deletions and substitutions
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+fn same(a: &Complex, b: &Complex) -> bool {
+    a.terms() == b.terms()
+}
+
+// every lint that fires, whatever its level
+pub fn check(network: &Network) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    let mut report = |lint, message| out.push(Diagnostic { lint, message });
+    let reactions = network.reactions();
+
+    for (j, rxn) in reactions.iter().enumerate() {
+        if same(rxn.get_reactants(), rxn.get_products()) {
+            report(
+                Lint::TrivialReaction,
+                format!(
+                    "reaction '{}' has identical reactants and products",
+                    network.reaction_name(j)
+                ),
+            );
+        }
+        if rxn.get_products().is_empty() && !rxn.is_reversible() {
+            report(
+                Lint::NoProducts,
+                format!(
+                    "reaction '{}' produces nothing; allow no_products if it is an outflow",
+                    network.reaction_name(j)
+                ),
+            );
+        }
+        for (k, other) in reactions.iter().enumerate().skip(j + 1) {
+            let forward = same(rxn.get_reactants(), other.get_reactants())
+                && same(rxn.get_products(), other.get_products());
+            let reversed = (rxn.is_reversible() || other.is_reversible())
+                && same(rxn.get_reactants(), other.get_products())
+                && same(rxn.get_products(), other.get_reactants());
+            if forward || reversed {
+                report(
+                    Lint::DuplicateReaction,
+                    format!(
+                        "reactions '{}' and '{}' convert the same species",
+                        network.reaction_name(j),
+                        network.reaction_name(k)
+                    ),
+                );
+            }
+        }
+    }
+
+    // reactions each species takes part in, and whether it can be made or
+    // used up
+    let n = network.num_species();
+    let mut occurrences: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n];
+    let mut produced = vec![false; n];
+    let mut consumed = vec![false; n];
+    for (j, rxn) in reactions.iter().enumerate() {
+        for (i, _) in rxn.get_reactants().terms() {
+            occurrences[i].insert(j);
+            consumed[i] = true;
+            produced[i] |= rxn.is_reversible();
+        }
+        for (i, _) in rxn.get_products().terms() {
+            occurrences[i].insert(j);
+            produced[i] = true;
+            consumed[i] |= rxn.is_reversible();
+        }
+    }
+    // species also referred to outside the reactions they take part in
+    let mut referenced: BTreeSet<String> = BTreeSet::new();
+    for (j, rxn) in reactions.iter().enumerate() {
+        if let Some(rate) = rxn.get_kinetics() {
+            referenced.extend(rate.symbols().into_iter().filter(|s| {
+                network
+                    .find_species(s)
+                    .is_some_and(|i| !occurrences[i].contains(&j))
+            }));
+        }
+    }
+    for d in network.definitions() {
+        referenced.extend(d.value.symbols());
+    }
+    for event in network.events() {
+        referenced.extend(event.symbols());
+    }
+
+    let single_use =
+        |i: SpeciesId| occurrences[i].len() == 1 && !referenced.contains(network.species_name(i));
+    for i in 0..n {
+        let name = network.species_name(i);
+        if network.species_attributes(i).is_fixed() {
+            continue;
+        }
+        if single_use(i) {
+            let j = *occurrences[i].first().expect("one reaction");
+            report(
+                Lint::SingleUse,
+                format!(
+                    "species '{name}' appears only in reaction '{}'",
+                    network.reaction_name(j)
+                ),
+            );
+        }
+        if produced[i] != consumed[i] {
+            let how = if produced[i] { "produced" } else { "consumed" };
+            report(Lint::DeadEnd, format!("species '{name}' is only {how}"));
+        }
+    }
+
+    // a name one edit away from another is suspect when one of the two
+    // barely occurs; ATP and ADP are both common, GLY and GLYc less so
+    let base = |i: SpeciesId| {
+        let name = network.species_name(i);
+        name.split('@').next().unwrap_or(name).to_string()
+    };
+    for i in 0..n {
+        for k in i + 1..n {
+            let (a, b) = (base(i), base(k));
+            if a == b {
+                continue;
+            }
+            let similar = a.to_lowercase() == b.to_lowercase()
+                || (a.chars().count() >= 3
+                    && b.chars().count() >= 3
+                    && levenshtein(&a, &b) == 1
+                    && (single_use(i) || single_use(k)));
+            if similar {
+                report(
+                    Lint::SimilarNames,
+                    format!(
+                        "species '{}' and '{}' have similar names",
+                        network.species_name(i),
+                        network.species_name(k)
+                    ),
+                );
+            }
+        }
+    }
+
+    for w in network.parameter_warnings() {
+        let lint = match w {
+            ParameterWarning::Undeclared(_) => Lint::UndeclaredParameter,
+            ParameterWarning::Unused(_) => Lint::UnusedParameter,
+        };
+        report(lint, w.to_string());
+    }
+    for w in network.balance_warnings() {
+        report(Lint::Unbalanced, w.to_string());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    fn codes(network: &Network) -> Vec<&'static str> {
+        check(network).iter().map(|d| d.lint.code()).collect()
+    }
+
+    #[test]
+    fn finds_structural_mistakes() {
+        let network = network("A + B <-> C\nC -> A + B\nD -> D\nC -> 0\n");
+        let codes = codes(&network);
+        assert!(codes.contains(&"duplicate_reaction"));
+        assert!(codes.contains(&"trivial_reaction"));
+        assert!(codes.contains(&"no_products"));
+        assert!(!codes.contains(&"dead_end"));
+    }
+
+    #[test]
+    fn finds_likely_typos() {
+        let network = network("GLY <-> SER\nSER -> GLY\nGLYc -> 0\n");
+        let messages: Vec<String> = check(&network).into_iter().map(|d| d.message).collect();
+        assert!(messages.contains(&"species 'GLYc' appears only in reaction 'R3'".to_string()));
+        assert!(messages.contains(&"species 'GLYc' is only consumed".to_string()));
+        assert!(messages.contains(&"species 'GLY' and 'GLYc' have similar names".to_string()));
+    }
+
+    #[test]
+    fn fixed_and_referenced_species_are_exempt() {
+        let network = network(
+            "species X [boundary]\nX -> A\nA <-> B : k * A * Y - B\nY <-> B\nobservable t = A + B\n",
+        );
+        let codes = codes(&network);
+        assert!(!codes.contains(&"single_use"));
+        assert!(!codes.contains(&"dead_end"));
+    }
+
+    #[test]
+    fn levels_apply_the_model_then_overrides() {
+        let network = network("allow no_products, dead_end\ndeny single_use\nA -> 0\n");
+        let overrides = [
+            ("dead_end".to_string(), LintLevel::Warn),
+            ("no_such_lint".to_string(), LintLevel::Deny),
+        ];
+        let (levels, unknown) = levels(&network, &overrides);
+        let level = |lint| levels.iter().find(|(l, _)| *l == lint).unwrap().1;
+        assert_eq!(level(Lint::NoProducts), LintLevel::Allow);
+        assert_eq!(level(Lint::DeadEnd), LintLevel::Warn);
+        assert_eq!(level(Lint::SingleUse), LintLevel::Deny);
+        assert_eq!(level(Lint::Unbalanced), LintLevel::Warn);
+        assert_eq!(unknown, ["no_such_lint"]);
+        assert!(allowed(&network, Lint::DeadEnd));
+    }
+}
@@ -0,0 +1,717 @@
+// model reduction by quasi-steady-state and rapid-equilibrium approximations
+//
+// Eliminated species are written in terms of the remaining ones by solving
+// linear equations: their balances set to zero for the quasi-steady-state
+// approximation (QSSA), or the rate laws of the fast reactions set to zero
+// for rapid equilibrium. Conservation laws among the eliminated species, such
+// as total enzyme, replace dependent equations and their totals become new
+// parameters. The equations must be linear in the eliminated species, as in
+// enzyme mechanisms where each step involves one enzyme form; they are solved
+// symbolically by Cramer's rule.
+//
+// With r the amount fluxes and N_F the stoichiometry of the eliminated
+// species, the quasi-steady state has N_F r = 0, so r is a combination of a
+// basis of the nullspace of N_F. Each basis vector is an overall reaction
+// whose rate is the flux of the one reaction only it contains, e.g. the
+// catalytic step of a Michaelis-Menten mechanism.
+use std::error::Error;
+use std::fmt;
+
+use super::{conservation, invariants};
+use crate::data::{Matrix, Overflow, Rational};
+use crate::network::{
+    BinaryOp, Complex, Declaration, Definition, DefinitionError, Expr, Network,
+    ParameterAttributes, Reaction,
+};
+use crate::numeric::exact;
+
+// eliminated species solved for at once; Cramer's rule grows factorially
+pub const MAX_ELIMINATED: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approximation {
+    QuasiSteadyState,
+    // fast reactions, at equilibrium
+    RapidEquilibrium(Vec<usize>),
+}
+
+#[derive(Debug)]
+pub enum ReductionError {
+    NothingToEliminate,
+    TooManySpecies(usize),
+    FixedSpecies(String),
+    // reaction whose rate law is not linear in the eliminated species
+    Nonlinear(String),
+    // equations and unknowns of a rapid-equilibrium reduction
+    Equations(usize, usize),
+    Singular,
+    // overall reaction running only through fast reactions
+    Undetermined(String),
+    // event that refers to an eliminated species
+    Event(String),
+    Overflow(Overflow),
+    Definition(DefinitionError),
+}
+
+impl fmt::Display for ReductionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReductionError::NothingToEliminate => write!(f, "No species to eliminate"),
+            ReductionError::TooManySpecies(n) => write!(
+                f,
+                "Cannot eliminate {n} species at once; at most {MAX_ELIMINATED} are supported"
+            ),
+            ReductionError::FixedSpecies(s) => {
+                write!(
+                    f,
+                    "Species '{s}' is constant or boundary and cannot be eliminated"
+                )
+            }
+            ReductionError::Nonlinear(r) => write!(
+                f,
+                "Rate law of '{r}' is not linear in the eliminated species"
+            ),
+            ReductionError::Equations(equations, unknowns) => write!(
+                f,
+                "Fast reactions and conservation laws give {equations} equations for {unknowns} eliminated species"
+            ),
+            ReductionError::Singular => write!(
+                f,
+                "The equations do not determine the eliminated species uniquely"
+            ),
+            ReductionError::Undetermined(r) => write!(
+                f,
+                "Rate of overall reaction '{r}' is undetermined: it runs only through fast reactions"
+            ),
+            ReductionError::Event(e) => write!(f, "Event '{e}' refers to an eliminated species"),
+            ReductionError::Overflow(e) => write!(f, "{e}"),
+            ReductionError::Definition(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for ReductionError {}
+
+impl From<Overflow> for ReductionError {
+    fn from(e: Overflow) -> Self {
+        ReductionError::Overflow(e)
+    }
+}
+
+impl From<DefinitionError> for ReductionError {
+    fn from(e: DefinitionError) -> Self {
+        ReductionError::Definition(e)
+    }
+}
+
+#[derive(Debug)]
+pub struct Reduction {
+    pub network: Network,
+    // each eliminated species and its value in terms of the others
+    pub eliminated: Vec<(String, Expr)>,
+    // conserved moieties split by the reduction: the species left in the
+    // network no longer change, so the reduced rate laws read them as
+    // constants rather than as the total less the eliminated species.
+    // Each moiety's formula and the species left
+    pub split: Vec<(String, Vec<String>)>,
+}
+
+fn is_zero(e: &Expr) -> bool {
+    *e == Expr::Number(0.0)
+}
+
+fn add(a: Expr, b: Expr) -> Expr {
+    Expr::binary(BinaryOp::Add, a, b)
+}
+
+fn mul(a: Expr, b: Expr) -> Expr {
+    Expr::binary(BinaryOp::Mul, a, b)
+}
+
+// coefficient of each eliminated species and the remainder of a rate law
+// linear in them
+fn linear(law: &Expr, eliminated: &[String]) -> Option<(Vec<Expr>, Expr)> {
+    let mut coefficients = Vec::with_capacity(eliminated.len());
+    for s in eliminated {
+        let c = law.derivative(s).simplify();
+        if c.symbols().iter().any(|t| eliminated.contains(t)) {
+            return None;
+        }
+        coefficients.push(c);
+    }
+    let zero = Expr::Number(0.0);
+    let rest = eliminated
+        .iter()
+        .fold(law.clone(), |e, s| e.substitute(s, &zero))
+        .simplify();
+    Some((coefficients, rest))
+}
+
+// determinant by expansion along the first row, skipping zero entries
+fn determinant(a: &[Vec<Expr>], rows: &[usize], cols: &[usize]) -> Expr {
+    let Some((&row, rest)) = rows.split_first() else {
+        return Expr::Number(1.0);
+    };
+    let mut det: Option<Expr> = None;
+    for (k, &c) in cols.iter().enumerate() {
+        if is_zero(&a[row][c]) {
+            continue;
+        }
+        let minor: Vec<usize> = cols.iter().copied().filter(|&d| d != c).collect();
+        let term = mul(a[row][c].clone(), determinant(a, rest, &minor));
+        det = Some(match (det, k % 2 == 1) {
+            (None, false) => term,
+            (None, true) => Expr::Neg(Box::new(term)),
+            (Some(d), false) => add(d, term),
+            (Some(d), true) => Expr::binary(BinaryOp::Sub, d, term),
+        });
+    }
+    det.unwrap_or(Expr::Number(0.0))
+}
+
+// signed terms of a sum, with products distributed over sums, so that
+// determinants of polynomial entries collect into one polynomial
+fn expanded_terms(e: &Expr) -> Vec<(bool, Expr)> {
+    match e {
+        Expr::Neg(a) => expanded_terms(a)
+            .into_iter()
+            .map(|(negative, t)| (!negative, t))
+            .collect(),
+        Expr::Binary(op @ (BinaryOp::Add | BinaryOp::Sub), a, b) => {
+            let mut terms = expanded_terms(a);
+            terms.extend(
+                expanded_terms(b)
+                    .into_iter()
+                    .map(|(negative, t)| (negative != (*op == BinaryOp::Sub), t)),
+            );
+            terms
+        }
+        Expr::Binary(BinaryOp::Mul, a, b) => {
+            let right = expanded_terms(b);
+            expanded_terms(a)
+                .into_iter()
+                .flat_map(|(n, s)| {
+                    right
+                        .iter()
+                        .map(move |(m, t)| (n != *m, mul(s.clone(), t.clone())))
+                })
+                .collect()
+        }
+        _ => vec![(false, e.clone())],
+    }
+}
+
+fn expand(e: &Expr) -> Expr {
+    expanded_terms(e)
+        .into_iter()
+        .fold(Expr::Number(0.0), |sum, (negative, t)| {
+            let op = if negative {
+                BinaryOp::Sub
+            } else {
+                BinaryOp::Add
+            };
+            Expr::binary(op, sum, t)
+        })
+        .simplify()
+}
+
+// whether an expression is written with a leading minus sign
+fn leads_negative(e: &Expr) -> bool {
+    match e {
+        Expr::Number(x) => *x < 0.0,
+        Expr::Neg(_) => true,
+        Expr::Binary(_, a, _) => leads_negative(a),
+        Expr::Symbol(_) | Expr::Call(..) => false,
+    }
+}
+
+// solution of a x = b by Cramer's rule
+fn solve(a: &[Vec<Expr>], b: &[Expr]) -> Result<Vec<Expr>, ReductionError> {
+    let n = b.len();
+    let indices: Vec<usize> = (0..n).collect();
+    let mut det = expand(&determinant(a, &indices, &indices));
+    if is_zero(&det) {
+        return Err(ReductionError::Singular);
+    }
+    // a denominator such as `kf * S + kr + k` rather than its negative
+    let flip = leads_negative(&det);
+    if flip {
+        det = Expr::Neg(Box::new(det)).simplify();
+    }
+    Ok((0..n)
+        .map(|f| {
+            let replaced: Vec<Vec<Expr>> = a
+                .iter()
+                .zip(b)
+                .map(|(row, bi)| {
+                    let mut row = row.clone();
+                    row[f] = bi.clone();
+                    row
+                })
+                .collect();
+            let mut numerator = determinant(&replaced, &indices, &indices);
+            if flip {
+                numerator = Expr::Neg(Box::new(numerator));
+            }
+            let numerator = expand(&numerator);
+            Expr::binary(BinaryOp::Div, numerator, det.clone()).simplify()
+        })
+        .collect())
+}
+
+// a name for a new parameter or observable that clashes with nothing in
+// the reduced network; the names of eliminated species are free
+fn fresh_name(network: &Network, eliminated: &[String], taken: &[String], base: &str) -> String {
+    let used = |name: &str| {
+        network.find_species(name).is_some() && !eliminated.iter().any(|e| e == name)
+            || network.find_parameter(name).is_some()
+            || network.find_compartment(name).is_some()
+            || network.find_definition(name).is_some()
+            || taken.iter().any(|t| t == name)
+    };
+    let mut name = base.to_string();
+    let mut n = 1;
+    while used(&name) {
+        n += 1;
+        name = format!("{base}_{n}");
+    }
+    name
+}
+
+// species eliminated by default with fast reactions at equilibrium: those in
+// conservation laws among the species of the fast reactions, such as the
+// forms of an enzyme
+pub fn equilibrium_species(network: &Network, fast: &[usize]) -> Result<Vec<usize>, Overflow> {
+    let stoichiometry = network.exact_stoichiometric_matrix();
+    let involved: Vec<usize> = (0..network.num_species())
+        .filter(|&i| fast.iter().any(|&j| !stoichiometry[(i, j)].is_zero()))
+        .collect();
+    let mut rows = Matrix::zeros(involved.len(), network.num_reactions());
+    for (f, &i) in involved.iter().enumerate() {
+        rows.row_mut(f).copy_from_slice(stoichiometry.row(i));
+    }
+    let laws = exact::left_nullspace(&rows)?;
+    Ok(involved
+        .into_iter()
+        .enumerate()
+        .filter(|&(f, i)| {
+            !network.species_attributes(i).is_fixed() && laws.iter().any(|y| !y[f].is_zero())
+        })
+        .map(|(_, i)| i)
+        .collect())
+}
+
+// the network with the given species eliminated by the approximation
+pub fn reduce(
+    network: &Network,
+    eliminate: &[usize],
+    approximation: &Approximation,
+) -> Result<Reduction, ReductionError> {
+    if eliminate.is_empty() {
+        return Err(ReductionError::NothingToEliminate);
+    }
+    if eliminate.len() > MAX_ELIMINATED {
+        return Err(ReductionError::TooManySpecies(eliminate.len()));
+    }
+    if let Some(&i) = eliminate
+        .iter()
+        .find(|&&i| network.species_attributes(i).is_fixed())
+    {
+        return Err(ReductionError::FixedSpecies(
+            network.species_name(i).to_string(),
+        ));
+    }
+    let names: Vec<String> = eliminate
+        .iter()
+        .map(|&i| network.species_name(i).to_string())
+        .collect();
+    // eliminated species become observables, named after them where the
+    // language allows
+    let mut observables: Vec<String> = Vec::with_capacity(names.len());
+    for name in &names {
+        let observable = fresh_name(network, &names, &observables, &name.replace('@', "_"));
+        observables.push(observable);
+    }
+    let laws: Vec<Expr> = (0..network.num_reactions())
+        .map(|j| network.rate_law(j))
+        .collect();
+    let mut terms = Vec::with_capacity(laws.len());
+    for (j, law) in laws.iter().enumerate() {
+        terms.push(linear(law, &names).ok_or(ReductionError::Nonlinear(network.reaction_name(j)))?);
+    }
+
+    // amount stoichiometry of the eliminated species
+    let stoichiometry = network.exact_stoichiometric_matrix();
+    let mut fast = Matrix::zeros(eliminate.len(), network.num_reactions());
+    for (f, &i) in eliminate.iter().enumerate() {
+        fast.row_mut(f).copy_from_slice(stoichiometry.row(i));
+    }
+
+    // conservation laws among the eliminated species; they replace the
+    // balances that depend on the others
+    let (_, pivots) = exact::rref(&fast.transpose())?;
+    let dependent: Vec<usize> = (0..eliminate.len())
+        .filter(|f| !pivots.contains(f))
+        .collect();
+    let moieties: Vec<Vec<Rational>> = exact::left_nullspace(&fast)?;
+
+    let mut a: Vec<Vec<Expr>> = Vec::new();
+    let mut b: Vec<Expr> = Vec::new();
+    match approximation {
+        Approximation::QuasiSteadyState => {
+            // sum_j N_fj V_j r_j = 0 for each independent balance
+            for f in (0..eliminate.len()).filter(|f| !dependent.contains(f)) {
+                let mut row = vec![Expr::Number(0.0); eliminate.len()];
+                let mut rest = Expr::Number(0.0);
+                for (j, (coefficients, remainder)) in terms.iter().enumerate() {
+                    let c = fast[(f, j)];
+                    if c.is_zero() {
+                        continue;
+                    }
+                    let scale = Expr::Number(c.to_f64() * network.reaction_volume(j));
+                    for (entry, coefficient) in row.iter_mut().zip(coefficients) {
+                        *entry = add(entry.clone(), mul(scale.clone(), coefficient.clone()));
+                    }
+                    rest = add(rest, mul(scale, remainder.clone()));
+                }
+                a.push(row.into_iter().map(|e| e.simplify()).collect());
+                b.push(Expr::Neg(Box::new(rest)).simplify());
+            }
+        }
+        Approximation::RapidEquilibrium(reactions) => {
+            let equations = reactions.len() + moieties.len();
+            if equations != eliminate.len() {
+                return Err(ReductionError::Equations(equations, eliminate.len()));
+            }
+            for &j in reactions {
+                let (coefficients, remainder) = &terms[j];
+                a.push(coefficients.clone());
+                b.push(Expr::Neg(Box::new(remainder.clone())).simplify());
+            }
+        }
+    }
+
+    // totals of the conserved moieties become parameters
+    let mut totals: Vec<(String, f64, String)> = Vec::new();
+    let x0 = network.initial_conditions();
+    for y in &moieties {
+        // named after the first species of the law, e.g. `E_total`
+        let first = y
+            .iter()
+            .position(|c| !c.is_zero())
+            .expect("laws are nonzero");
+        let mut taken: Vec<String> = totals.iter().map(|t| t.0.clone()).collect();
+        taken.extend(observables.iter().cloned());
+        let base = format!("{}_total", observables[first]);
+        let name = fresh_name(network, &names, &taken, &base);
+        let mut row = vec![Expr::Number(0.0); eliminate.len()];
+        let mut total = 0.0;
+        let mut weights = vec![Rational::ZERO; network.num_species()];
+        for (f, &c) in y.iter().enumerate() {
+            let i = eliminate[f];
+            let weight = c.to_f64() * network.species_volume(i);
+            row[f] = Expr::Number(weight);
+            total += weight * x0[i];
+            weights[i] = c;
+        }
+        let law = conservation::ConservationLaw {
+            coefficients: weights,
+            dependent: eliminate[first],
+        };
+        a.push(row);
+        b.push(Expr::symbol(name.clone()));
+        totals.push((name, total, law.formula(network)));
+    }
+
+    let solution = solve(&a, &b)?;
+    let substitute = |e: &Expr| {
+        names
+            .iter()
+            .zip(&solution)
+            .fold(network.expand(e), |e, (s, x)| e.substitute(s, x))
+            .simplify()
+    };
+    let reduced_laws: Vec<Expr> = laws.iter().map(substitute).collect();
+
+    // overall reactions: a nullspace basis of N_F with the simplest slow
+    // reactions as free columns, which rref places last
+    let fast_reactions = match approximation {
+        Approximation::QuasiSteadyState => Vec::new(),
+        Approximation::RapidEquilibrium(reactions) => reactions.clone(),
+    };
+    let mut order: Vec<usize> = (0..network.num_reactions()).collect();
+    order.sort_by_key(|&j| {
+        (
+            !fast_reactions.contains(&j),
+            std::cmp::Reverse(reduced_laws[j].to_string().len()),
+        )
+    });
+    let mut permuted = Matrix::zeros(eliminate.len(), order.len());
+    for (k, &j) in order.iter().enumerate() {
+        for f in 0..eliminate.len() {
+            permuted[(f, k)] = fast[(f, j)];
+        }
+    }
+    let (_, pivots) = exact::rref(&permuted)?;
+    let free: Vec<usize> = (0..order.len()).filter(|k| !pivots.contains(k)).collect();
+    let modes = exact::nullspace(&permuted)?;
+
+    let mut reduced = Network::new();
+    for c in 0..network.num_compartments() {
+        let id = reduced.register_compartment(network.compartment_name(c).to_string());
+        *reduced.compartment_attributes_mut(id) = network.compartment_attributes(c).clone();
+    }
+    let mut index = vec![None; network.num_species()];
+    for i in (0..network.num_species()).filter(|i| !eliminate.contains(i)) {
+        let id = reduced.register_species(network.species_name(i).to_string());
+        *reduced.species_attributes_mut(id) = network.species_attributes(i).clone();
+        index[i] = Some(id);
+    }
+
+    // overall reactions, in the order of their first member
+    let mut overall = Vec::new();
+    for (mode, &k) in modes.iter().zip(&free) {
+        let pivot = order[k];
+        let mut members: Vec<usize> = (0..order.len())
+            .filter(|&l| !mode[l].is_zero())
+            .map(|l| order[l])
+            .collect();
+        members.sort_unstable();
+        let mut reactants = Complex::new();
+        let mut products = Complex::new();
+        for i in 0..network.num_species() {
+            let Some(id) = index[i] else { continue };
+            let c = (0..order.len())
+                .try_fold(Rational::ZERO, |s, l| {
+                    s.checked_add(stoichiometry[(i, order[l])].checked_mul(mode[l])?)
+                })
+                .ok_or(Overflow)?;
+            if c > Rational::ZERO {
+                products.add_term(id, c);
+            } else if c < Rational::ZERO {
+                reactants.add_term(id, -c);
+            }
+        }
+        if reactants.is_empty() && products.is_empty() {
+            continue;
+        }
+        let reversible = members
+            .iter()
+            .all(|&j| network.reactions()[j].is_reversible());
+        // a reaction on its own keeps its name, so that default rate
+        // constants such as `k_r3` keep referring to it
+        let name = match members.as_slice() {
+            [j] => network.reaction_name(*j),
+            _ => {
+                let joined: Vec<String> =
+                    members.iter().map(|&j| network.reaction_name(j)).collect();
+                joined.join("_")
+            }
+        };
+        if fast_reactions.contains(&pivot) {
+            return Err(ReductionError::Undetermined(name));
+        }
+        // amount flux of the pivot over the volume of the new reaction
+        let volume = reactants
+            .terms()
+            .into_iter()
+            .chain(products.terms())
+            .find_map(|(id, _)| reduced.species_attributes(id).compartment)
+            .map_or(1.0, |c| reduced.compartment_attributes(c).volume());
+        let mut rxn = if reversible {
+            Reaction::named_reversible(name, reactants, products)
+        } else {
+            Reaction::named_forward(name, reactants, products)
+        };
+        let untouched =
+            members.len() == 1 && laws[pivot].symbols().iter().all(|s| !names.contains(s));
+        if untouched {
+            if let Some(rate) = network.reactions()[pivot].get_kinetics() {
+                rxn.set_kinetics(rate.clone());
+            }
+        } else {
+            let ratio = network.reaction_volume(pivot) / volume;
+            rxn.set_kinetics(if ratio == 1.0 {
+                reduced_laws[pivot].clone()
+            } else {
+                mul(Expr::Number(ratio), reduced_laws[pivot].clone()).simplify()
+            });
+        }
+        overall.push((members[0], rxn));
+    }
+    overall.sort_by_key(|(first, _)| *first);
+    for (_, rxn) in overall {
+        reduced.add_reaction(rxn);
+    }
+
+    // definitions keep their meaning, and eliminated species become
+    // observables so they can still be followed
+    for d in network.definitions() {
+        let value = names
+            .iter()
+            .zip(&solution)
+            .fold(d.value.clone(), |e, (s, x)| e.substitute(s, x));
+        reduced.add_definition(Definition {
+            name: d.name.clone(),
+            value,
+            observable: d.observable,
+        });
+    }
+    let mut eliminated = Vec::with_capacity(names.len());
+    for ((name, observable), x) in names.iter().zip(&observables).zip(&solution) {
+        reduced.add_definition(Definition {
+            name: observable.clone(),
+            value: x.clone(),
+            observable: true,
+        });
+        eliminated.push((name.clone(), x.clone()));
+    }
+    for event in network.events() {
+        if event.symbols().iter().any(|s| names.contains(s)) {
+            return Err(ReductionError::Event(event.to_string()));
+        }
+        reduced.add_event(event.clone());
+    }
+
+    // parameters still in use, then the totals; rate constants of default
+    // mass-action laws stay implicit, while those now in explicit rate laws
+    // are declared
+    let mut used = std::collections::BTreeSet::new();
+    let mut implicit = std::collections::BTreeSet::new();
+    for (j, rxn) in reduced.reactions().iter().enumerate() {
+        match rxn.get_kinetics() {
+            Some(rate) => used.extend(rate.symbols()),
+            None => implicit.extend(reduced.mass_action(j).symbols()),
+        }
+    }
+    for d in reduced.definitions() {
+        used.extend(d.value.symbols());
+    }
+    for event in reduced.events() {
+        used.extend(event.symbols());
+    }
+    for k in 0..network.num_parameters() {
+        let name = network.parameter_name(k);
+        let attributes = network.parameter_attributes(k);
+        if used.contains(name)
+            || implicit.contains(name) && attributes.declaration == Declaration::Explicit
+        {
+            reduced.declare_parameter(name.to_string(), attributes.clone());
+        }
+    }
+    for (name, value, formula) in totals {
+        let attributes = ParameterAttributes {
+            value: Some(value),
+            description: Some(format!("total {formula}")),
+            ..Default::default()
+        };
+        reduced.declare_parameter(name, attributes);
+    }
+    reduced.order_definitions()?;
+    reduced.resolve_parameters();
+
+    let changing = reduced.exact_stoichiometric_matrix();
+    let changes = |i: usize| {
+        reduced
+            .find_species(network.species_name(i))
+            .is_some_and(|r| changing.row(r).iter().any(|x| !x.is_zero()))
+    };
+    let mut split = Vec::new();
+    for y in invariants::p_invariants(network)? {
+        let support: Vec<usize> = (0..y.len()).filter(|&i| !y[i].is_zero()).collect();
+        let (gone, left): (Vec<usize>, Vec<usize>) =
+            support.iter().partition(|i| eliminate.contains(i));
+        if gone.is_empty() || left.is_empty() || left.iter().any(|&i| changes(i)) {
+            continue;
+        }
+        let law = conservation::ConservationLaw {
+            coefficients: y,
+            dependent: gone[0],
+        };
+        let left = left
+            .iter()
+            .map(|&i| network.species_name(i).to_string())
+            .collect();
+        split.push((law.formula(network), left));
+    }
+    Ok(Reduction {
+        network: reduced,
+        eliminated,
+        split,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+    use crate::language::writer;
+    use crate::simulation::kinetics::Formula;
+
+    const MICHAELIS_MENTEN: &str = "species {\n  S = 10\n  E = 0.1\n}\n\
+        param {\n  kf = 10\n  kr = 1\n  kc = 1\n}\n\
+        bind : E + S <-> ES : kf * E * S - kr * ES\n\
+        cat : ES -> E + P : kc * ES\n";
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    #[test]
+    fn observable_names_avoid_parameters() {
+        let network = network("param A_c = 2\n0 -> A@c : A_c\nA@c -> B\n");
+        let a = network.find_species("A@c").unwrap();
+        let reduction = reduce(&network, &[a], &Approximation::QuasiSteadyState).unwrap();
+        assert_eq!(reduction.network.definitions()[0].name, "A_c_2");
+        assert!(reduction.network.find_parameter("A_c").is_some());
+    }
+
+    #[test]
+    fn written_reduction_reads_back() {
+        // E has no attributes, and is left only in the reduced rate law
+        let network = network(&MICHAELIS_MENTEN.replace("  E = 0.1\n", ""));
+        let es = network.find_species("ES").unwrap();
+        let reduction = reduce(&network, &[es], &Approximation::QuasiSteadyState).unwrap();
+        let reread = self::network(&writer::write(&reduction.network));
+        assert!(reread.find_species("E").is_some());
+        assert!(reread.find_parameter("E").is_none());
+        assert!(reread.parameter_warnings().is_empty());
+        assert_eq!(reread.num_reactions(), reduction.network.num_reactions());
+    }
+
+    #[test]
+    fn enzyme_and_complex_give_michaelis_menten() {
+        let network = network(MICHAELIS_MENTEN);
+        let eliminate = [
+            network.find_species("E").unwrap(),
+            network.find_species("ES").unwrap(),
+        ];
+        let reduction = reduce(&network, &eliminate, &Approximation::QuasiSteadyState).unwrap();
+        assert!(reduction.split.is_empty());
+        let reduced = &reduction.network;
+        assert_eq!(reduced.num_reactions(), 1);
+        let rate = Formula::new(&reduced.rate_law(0), reduced).unwrap();
+        let p = reduced.parameter_values();
+        let s = reduced.find_species("S").unwrap();
+        // Vmax = kc E_total, Km = (kr + kc) / kf
+        let (vmax, km) = (1.0 * 0.1, (1.0 + 1.0) / 10.0);
+        for substrate in [0.01, 0.2, 5.0, 100.0] {
+            let mut x = vec![0.0; reduced.num_species()];
+            x[s] = substrate;
+            let expected = vmax * substrate / (km + substrate);
+            assert!((rate.eval(&x, &p) - expected).abs() < 1e-12 * expected);
+        }
+    }
+
+    #[test]
+    fn complex_alone_splits_enzyme_moiety() {
+        let network = network(MICHAELIS_MENTEN);
+        let es = network.find_species("ES").unwrap();
+        let reduction = reduce(&network, &[es], &Approximation::QuasiSteadyState).unwrap();
+        assert_eq!(
+            reduction.split,
+            vec![("E + ES".to_string(), vec!["E".to_string()])]
+        );
+    }
+}
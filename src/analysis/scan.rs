@@ -0,0 +1,371 @@
+// parameter scans: simulations or steady states over a grid of parameter
+// values or initial conditions, or over a space-filling sample of them
+//
+// Each axis ranges over an interval on a linear or log scale. A grid takes
+// evenly spaced values on every axis and all their combinations; Latin
+// hypercube and Sobol designs spread a given number of points over the
+// whole box. Samples are independent and are divided among threads.
+use std::error::Error;
+use std::fmt;
+use std::thread;
+
+use super::conservation;
+use super::steady_state::{self, SteadyStateError, SteadyStateOptions};
+use crate::data::Overflow;
+use crate::network::Network;
+use crate::numeric::ode::OdeOptions;
+use crate::numeric::{Rng, Sobol, latin_hypercube};
+use crate::simulation::{Observables, RateEquations, SimulationError, deterministic};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Parameter(usize),
+    // initial concentration of a species
+    Initial(usize),
+}
+
+impl Variable {
+    // parameters take precedence over species of the same name
+    pub fn find(network: &Network, name: &str) -> Option<Self> {
+        network
+            .find_parameter(name)
+            .map(Variable::Parameter)
+            .or_else(|| network.find_species(name).map(Variable::Initial))
+    }
+
+    pub fn name<'a>(&self, network: &'a Network) -> &'a str {
+        match *self {
+            Variable::Parameter(k) => network.parameter_name(k),
+            Variable::Initial(i) => network.species_name(i),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Axis {
+    pub variable: Variable,
+    pub lower: f64,
+    pub upper: f64,
+    pub log: bool,
+}
+
+impl Axis {
+    // value at fraction u of the way from lower to upper
+    pub fn at(&self, u: f64) -> f64 {
+        if self.log {
+            self.lower * (self.upper / self.lower).powf(u)
+        } else {
+            self.lower + u * (self.upper - self.lower)
+        }
+    }
+
+    // evenly spaced values including both ends
+    pub fn grid(&self, points: usize) -> Vec<f64> {
+        match points {
+            0 => Vec::new(),
+            1 => vec![self.lower],
+            n => (0..n).map(|k| self.at(k as f64 / (n - 1) as f64)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Design {
+    // points per axis
+    Grid(usize),
+    LatinHypercube { samples: usize, seed: u64 },
+    Sobol { samples: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    // states at the given times
+    TimeCourse(Vec<f64>),
+    SteadyState,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub design: Design,
+    pub mode: Mode,
+    pub ode: OdeOptions,
+    pub steady: SteadyStateOptions,
+    pub threads: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            design: Design::Grid(11),
+            mode: Mode::SteadyState,
+            ode: OdeOptions::default(),
+            steady: SteadyStateOptions::default(),
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScanError {
+    // log axes need positive ends
+    NonPositiveLogRange(String),
+    TooManyDimensions(usize),
+    Simulation(SimulationError),
+    Overflow(Overflow),
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScanError::NonPositiveLogRange(name) => {
+                write!(f, "Log range of '{name}' must be positive")
+            }
+            ScanError::TooManyDimensions(n) => write!(
+                f,
+                "Sobol sampling supports at most {} dimensions, not {n}",
+                Sobol::MAX_DIMENSIONS
+            ),
+            ScanError::Simulation(e) => write!(f, "{e}"),
+            ScanError::Overflow(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for ScanError {}
+
+impl From<SimulationError> for ScanError {
+    fn from(e: SimulationError) -> Self {
+        ScanError::Simulation(e)
+    }
+}
+
+impl From<Overflow> for ScanError {
+    fn from(e: Overflow) -> Self {
+        ScanError::Overflow(e)
+    }
+}
+
+// state of the network at one time; infinite at a steady state
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub time: f64,
+    pub concentrations: Vec<f64>,
+    pub fluxes: Vec<f64>,
+    // values of the network's observables
+    pub observed: Vec<f64>,
+}
+
+#[derive(Debug)]
+pub struct Sample {
+    // value of each axis
+    pub values: Vec<f64>,
+    // failed simulations or steady-state solves are kept as errors
+    pub outcome: Result<Vec<Snapshot>, SteadyStateError>,
+}
+
+// points of the design, as values of each axis
+pub fn design_points(axes: &[Axis], design: Design) -> Result<Vec<Vec<f64>>, ScanError> {
+    let unit = match design {
+        Design::Grid(points) => {
+            // all combinations, with the last axis varying fastest
+            let mut product = vec![Vec::new()];
+            for axis in axes {
+                let values = axis.grid(points);
+                product = product
+                    .into_iter()
+                    .flat_map(|p| {
+                        values.iter().map(move |&v| {
+                            let mut q = p.clone();
+                            q.push(v);
+                            q
+                        })
+                    })
+                    .collect();
+            }
+            return Ok(product);
+        }
+        Design::LatinHypercube { samples, seed } => {
+            latin_hypercube(samples, axes.len(), &mut Rng::new(seed))
+        }
+        Design::Sobol { samples } => Sobol::new(axes.len())
+            .ok_or(ScanError::TooManyDimensions(axes.len()))?
+            .take(samples)
+            .collect(),
+    };
+    Ok(unit
+        .into_iter()
+        .map(|u| axes.iter().zip(u).map(|(a, u)| a.at(u)).collect())
+        .collect())
+}
+
+fn run(
+    network: &Network,
+    base: &RateEquations,
+    observables: &Observables,
+    laws: &[conservation::ConservationLaw],
+    axes: &[Axis],
+    values: &[f64],
+    options: &ScanOptions,
+) -> Result<Vec<Snapshot>, SteadyStateError> {
+    let mut system = base.clone();
+    let mut x0 = network.initial_conditions();
+    for (axis, &v) in axes.iter().zip(values) {
+        match axis.variable {
+            Variable::Parameter(k) => system.set_parameter(k, v),
+            Variable::Initial(i) => x0[i] = v,
+        }
+    }
+    match &options.mode {
+        Mode::TimeCourse(times) => {
+            let states = deterministic::integrate(&system, x0, times, options.ode)?;
+            Ok(times
+                .iter()
+                .zip(states)
+                .map(|(&time, x)| Snapshot {
+                    time,
+                    fluxes: system.rates(&x),
+                    observed: observables.eval(&x, system.parameters()),
+                    concentrations: x,
+                })
+                .collect())
+        }
+        Mode::SteadyState => {
+            let state =
+                steady_state::steady_state_from(network, &system, laws, x0, &options.steady)?;
+            Ok(vec![Snapshot {
+                time: f64::INFINITY,
+                observed: observables.eval(&state.concentrations, system.parameters()),
+                concentrations: state.concentrations,
+                fluxes: state.fluxes,
+            }])
+        }
+    }
+}
+
+// simulate or solve the network at every point of the design
+pub fn scan(
+    network: &Network,
+    axes: &[Axis],
+    options: &ScanOptions,
+) -> Result<Vec<Sample>, ScanError> {
+    if let Some(axis) = axes
+        .iter()
+        .find(|a| a.log && (a.lower <= 0.0 || a.upper <= 0.0))
+    {
+        return Err(ScanError::NonPositiveLogRange(
+            axis.variable.name(network).to_string(),
+        ));
+    }
+    let points = design_points(axes, options.design)?;
+    let base = RateEquations::new(network)?;
+    let observables = Observables::new(network)?;
+    let laws = conservation::conservation_laws(network)?;
+
+    let n = points.len();
+    let chunk = n.div_ceil(options.threads.clamp(1, n.max(1))).max(1);
+    let mut outcomes: Vec<Option<Result<Vec<Snapshot>, SteadyStateError>>> =
+        (0..n).map(|_| None).collect();
+    thread::scope(|scope| {
+        for (slots, values) in outcomes.chunks_mut(chunk).zip(points.chunks(chunk)) {
+            let (base, observables, laws) = (&base, &observables, &laws);
+            scope.spawn(move || {
+                for (slot, v) in slots.iter_mut().zip(values) {
+                    *slot = Some(run(network, base, observables, laws, axes, v, options));
+                }
+            });
+        }
+    });
+    Ok(points
+        .into_iter()
+        .zip(outcomes)
+        .map(|(values, outcome)| Sample {
+            values,
+            outcome: outcome.expect("every sample is assigned to a thread"),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    fn axis(variable: Variable, lower: f64, upper: f64, log: bool) -> Axis {
+        Axis {
+            variable,
+            lower,
+            upper,
+            log,
+        }
+    }
+
+    #[test]
+    fn grids_combine_axes() {
+        let log = axis(Variable::Parameter(0), 1.0, 100.0, true);
+        let values = log.grid(3);
+        assert!((values[1] - 10.0).abs() < 1e-12);
+        let linear = axis(Variable::Initial(0), 0.0, 1.0, false);
+        let points = design_points(&[log, linear], Design::Grid(2)).unwrap();
+        assert_eq!(
+            points,
+            vec![
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+                vec![100.0, 0.0],
+                vec![100.0, 1.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn latin_hypercube_fills_every_stratum() {
+        let axes = [
+            axis(Variable::Parameter(0), 0.0, 1.0, false),
+            axis(Variable::Parameter(1), 0.0, 1.0, false),
+        ];
+        let design = Design::LatinHypercube {
+            samples: 5,
+            seed: 7,
+        };
+        let points = design_points(&axes, design).unwrap();
+        for d in 0..2 {
+            let mut strata: Vec<usize> = points.iter().map(|p| (p[d] * 5.0) as usize).collect();
+            strata.sort();
+            assert_eq!(strata, vec![0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn steady_states_follow_the_scanned_parameter() {
+        let network = network("param k = 1\nfeed : 0 -> A : k\ndrain : A -> 0 : 2 * A\n");
+        let k = Variable::find(&network, "k").unwrap();
+        let options = ScanOptions {
+            design: Design::Grid(4),
+            threads: 2,
+            ..Default::default()
+        };
+        let samples = scan(&network, &[axis(k, 2.0, 8.0, false)], &options).unwrap();
+        assert_eq!(samples.len(), 4);
+        for sample in &samples {
+            let state = &sample.outcome.as_ref().unwrap()[0];
+            assert!((state.concentrations[0] - sample.values[0] / 2.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn log_axes_need_positive_ranges() {
+        let network = network("param k = 1\n0 -> A : k\n");
+        let k = Variable::find(&network, "k").unwrap();
+        let error = scan(
+            &network,
+            &[axis(k, 0.0, 1.0, true)],
+            &ScanOptions::default(),
+        );
+        assert!(matches!(error, Err(ScanError::NonPositiveLogRange(name)) if name == "k"));
+    }
+}
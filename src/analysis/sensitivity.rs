@@ -0,0 +1,266 @@
+// forward sensitivities of concentrations and fluxes to parameters
+//
+// Along a time course, S = dx/dp obeys dS/dt = J S + df/dp with S(0) = 0,
+// integrated together with the rate equations. At a steady state the same
+// derivative solves the linear system of the Newton iteration, in which the
+// conserved totals do not depend on the parameters. Normalized (log)
+// coefficients scale d y / d p by p / y.
+use super::conservation;
+use super::steady_state::{self, SteadyStateError, SteadyStateOptions, SteadyStateSystem};
+use crate::data::Matrix;
+use crate::network::Network;
+use crate::numeric::Lu;
+use crate::numeric::ode::{self, OdeOptions, OdeSystem};
+use crate::simulation::{RateEquations, SimulationError};
+
+// the rate equations extended by the sensitivities to chosen parameters;
+// the state is x followed by dx/dp_k for each parameter in turn
+pub struct SensitivityEquations<'a> {
+    system: &'a RateEquations,
+    parameters: &'a [usize],
+}
+
+impl<'a> SensitivityEquations<'a> {
+    pub fn new(system: &'a RateEquations, parameters: &'a [usize]) -> Self {
+        Self { system, parameters }
+    }
+}
+
+impl OdeSystem for SensitivityEquations<'_> {
+    fn dim(&self) -> usize {
+        self.system.dim() * (1 + self.parameters.len())
+    }
+
+    fn rhs(&self, t: f64, y: &[f64], dy: &mut [f64]) {
+        let n = self.system.dim();
+        let x = &y[..n];
+        self.system.rhs(t, x, &mut dy[..n]);
+        let j = self.system.jacobian(t, x);
+        let fp = self.system.parameter_jacobian(x);
+        for (c, &k) in self.parameters.iter().enumerate() {
+            let s = &y[n * (c + 1)..n * (c + 2)];
+            let ds = j.mul_vec(s);
+            for i in 0..n {
+                dy[n * (c + 1) + i] = ds[i] + fp[(i, k)];
+            }
+        }
+    }
+
+    // block diagonal in J; the dependence of J S on x is left out, which the
+    // stiff integrator tolerates at the cost of smaller steps
+    fn jacobian(&self, t: f64, y: &[f64]) -> Matrix {
+        let n = self.system.dim();
+        let j = self.system.jacobian(t, &y[..n]);
+        let dim = self.dim();
+        let mut out = Matrix::zeros(dim, dim);
+        for block in 0..=self.parameters.len() {
+            for a in 0..n {
+                for b in 0..n {
+                    out[(block * n + a, block * n + b)] = j[(a, b)];
+                }
+            }
+        }
+        out
+    }
+}
+
+// sensitivities at one time, or at the steady state
+#[derive(Debug, Clone)]
+pub struct SensitivityPoint {
+    // infinite at the steady state
+    pub time: f64,
+    pub concentrations: Vec<f64>,
+    pub fluxes: Vec<f64>,
+    // species by chosen parameters, d x_i / d p_k
+    pub species_sensitivities: Matrix,
+    // reactions by chosen parameters, d r_j / d p_k
+    pub flux_sensitivities: Matrix,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sensitivities {
+    // chosen parameters, as indices into the network's parameters
+    pub parameters: Vec<usize>,
+    pub values: Vec<f64>,
+    pub points: Vec<SensitivityPoint>,
+}
+
+// d ln y / d ln p from d y / d p; undefined where y vanishes
+pub fn normalized(value: f64, sensitivity: f64, parameter: f64) -> f64 {
+    if value == 0.0 {
+        f64::NAN
+    } else {
+        sensitivity * parameter / value
+    }
+}
+
+impl Sensitivities {
+    // normalized sensitivity of species i to the c-th chosen parameter
+    pub fn species_coefficient(&self, point: &SensitivityPoint, i: usize, c: usize) -> f64 {
+        normalized(
+            point.concentrations[i],
+            point.species_sensitivities[(i, c)],
+            self.values[c],
+        )
+    }
+
+    // normalized sensitivity of reaction j's flux to the c-th chosen parameter
+    pub fn flux_coefficient(&self, point: &SensitivityPoint, j: usize, c: usize) -> f64 {
+        normalized(
+            point.fluxes[j],
+            point.flux_sensitivities[(j, c)],
+            self.values[c],
+        )
+    }
+}
+
+// total derivative of the fluxes, dr/dp = dr/dx dx/dp + ∂r/∂p
+fn flux_sensitivity(
+    system: &RateEquations,
+    x: &[f64],
+    species: &Matrix,
+    parameters: &[usize],
+) -> Matrix {
+    let mut out = system.rate_jacobian(x).mul(species);
+    let rp = system.rate_parameter_jacobian(x);
+    for j in 0..out.rows() {
+        for (c, &k) in parameters.iter().enumerate() {
+            out[(j, c)] += rp[(j, k)];
+        }
+    }
+    out
+}
+
+fn point(
+    system: &RateEquations,
+    time: f64,
+    x: Vec<f64>,
+    species: Matrix,
+    parameters: &[usize],
+) -> SensitivityPoint {
+    let fluxes = system.rates(&x);
+    let flux_sensitivities = flux_sensitivity(system, &x, &species, parameters);
+    SensitivityPoint {
+        time,
+        concentrations: x,
+        fluxes,
+        species_sensitivities: species,
+        flux_sensitivities,
+    }
+}
+
+fn values(system: &RateEquations, parameters: &[usize]) -> Vec<f64> {
+    parameters.iter().map(|&k| system.parameters()[k]).collect()
+}
+
+// sensitivities along the time course from the initial conditions
+pub fn time_course(
+    network: &Network,
+    parameters: &[usize],
+    times: &[f64],
+    options: OdeOptions,
+) -> Result<Sensitivities, SimulationError> {
+    let system = RateEquations::new(network)?;
+    integrate(
+        &system,
+        network.initial_conditions(),
+        parameters,
+        times,
+        options,
+    )
+}
+
+// sensitivities along the time course of `system` from x0
+pub fn integrate(
+    system: &RateEquations,
+    x0: Vec<f64>,
+    parameters: &[usize],
+    times: &[f64],
+    options: OdeOptions,
+) -> Result<Sensitivities, SimulationError> {
+    if !system.events().is_empty() {
+        return Err(SimulationError::UnsupportedEvents("sensitivity analysis"));
+    }
+    let equations = SensitivityEquations::new(system, parameters);
+    let n = system.dim();
+    let mut y0 = x0;
+    y0.resize(equations.dim(), 0.0);
+
+    if let Some(t) = times.iter().find(|t| !(t.is_finite() && **t >= 0.0)) {
+        return Err(SimulationError::InvalidOption(format!(
+            "Sensitivity times must be finite and nonnegative, not {t}"
+        )));
+    }
+    if times.windows(2).any(|w| w[1] < w[0]) {
+        return Err(SimulationError::InvalidOption(
+            "Sensitivity times must be in increasing order".to_string(),
+        ));
+    }
+    // integration starts at 0 whether or not it is requested
+    let start = times.first() != Some(&0.0);
+    let mut samples = if start { vec![0.0] } else { Vec::new() };
+    samples.extend(times);
+    let mut states = ode::integrate(&equations, y0, &samples, options)?;
+    if start {
+        states.remove(0);
+    }
+
+    let points = samples
+        .iter()
+        .skip(samples.len() - states.len())
+        .zip(states)
+        .map(|(&t, y)| {
+            let mut species = Matrix::zeros(n, parameters.len());
+            for c in 0..parameters.len() {
+                for i in 0..n {
+                    species[(i, c)] = y[n * (c + 1) + i];
+                }
+            }
+            point(system, t, y[..n].to_vec(), species, parameters)
+        })
+        .collect();
+    Ok(Sensitivities {
+        parameters: parameters.to_vec(),
+        values: values(system, parameters),
+        points,
+    })
+}
+
+// sensitivities of the steady state reached from the initial conditions
+pub fn at_steady_state(
+    network: &Network,
+    parameters: &[usize],
+    options: &SteadyStateOptions,
+) -> Result<Sensitivities, SteadyStateError> {
+    let state = steady_state::steady_state(network, options)?;
+    let system = RateEquations::new(network)?;
+    let laws = conservation::conservation_laws(network)?;
+    let x = state.concentrations;
+    let problem = SteadyStateSystem::new(network, &system, &laws, &x);
+
+    // d/dp of f(x(p), p) = 0 with the conserved totals held fixed
+    let lu = Lu::factor(problem.jacobian(&x)).ok_or(SteadyStateError::SingularJacobian)?;
+    let dependents: Vec<usize> = problem.dependents().collect();
+    let fp = system.parameter_jacobian(&x);
+    let n = x.len();
+    let mut species = Matrix::zeros(n, parameters.len());
+    for (c, &k) in parameters.iter().enumerate() {
+        let b: Vec<f64> = (0..n)
+            .map(|i| {
+                if dependents.contains(&i) {
+                    0.0
+                } else {
+                    -fp[(i, k)]
+                }
+            })
+            .collect();
+        for (i, dx) in lu.solve(&b).into_iter().enumerate() {
+            species[(i, c)] = dx;
+        }
+    }
+    Ok(Sensitivities {
+        parameters: parameters.to_vec(),
+        values: values(&system, parameters),
+        points: vec![point(&system, f64::INFINITY, x, species, parameters)],
+    })
+}
@@ -0,0 +1,205 @@
+// siphons and traps of the species-reaction Petri net, and persistence
+//
+// Species are places and reactions transitions, with a reversible reaction
+// giving one transition each way. A siphon is a set of species such that
+// every reaction producing one of them consumes one of them: once all are
+// absent they stay absent. A trap is the reverse, a set that every reaction
+// consuming one of them also replenishes. Constant and boundary species are
+// never depleted and are left out of the net.
+//
+// By the criterion of Angeli, De Leenheer and Sontag, a network whose
+// trajectories are bounded is persistent, i.e. no species tends to zero from
+// a positive start, if every siphon contains the support of a nonnegative
+// conservation law. It suffices to check the minimal siphons.
+use std::collections::BTreeSet;
+
+use super::invariants;
+use crate::network::{Complex, Network};
+use crate::numeric::{LinearProgram, Relation};
+
+// places consumed and produced by one transition
+type Arcs = (BTreeSet<usize>, BTreeSet<usize>);
+
+fn arcs(network: &Network) -> Vec<Arcs> {
+    let dynamic = |complex: &Complex| -> BTreeSet<usize> {
+        complex
+            .terms()
+            .into_iter()
+            .map(|(i, _)| i)
+            .filter(|&i| !network.species_attributes(i).is_fixed())
+            .collect()
+    };
+    invariants::transitions(network)
+        .iter()
+        .map(|t| (dynamic(t.inputs(network)), dynamic(t.outputs(network))))
+        .collect()
+}
+
+// minimal nonempty sets S of the places such that every transition with an
+// output in S has an input in S
+fn minimal_siphons_of(places: &[usize], transitions: &[Arcs]) -> Vec<Vec<usize>> {
+    // every minimal siphon containing a place is reached by adding, for the
+    // first transition that violates the condition, one of its inputs
+    fn search(set: &mut BTreeSet<usize>, transitions: &[Arcs], found: &mut Vec<BTreeSet<usize>>) {
+        if found.iter().any(|f| f.is_subset(set)) {
+            return;
+        }
+        let violated = transitions
+            .iter()
+            .find(|(inputs, outputs)| !outputs.is_disjoint(set) && inputs.is_disjoint(set));
+        let Some((inputs, _)) = violated else {
+            found.retain(|f| !set.is_subset(f));
+            found.push(set.clone());
+            return;
+        };
+        for &i in inputs {
+            set.insert(i);
+            search(set, transitions, found);
+            set.remove(&i);
+        }
+    }
+
+    let mut found = Vec::new();
+    for &p in places {
+        search(&mut BTreeSet::from([p]), transitions, &mut found);
+    }
+    let mut out: Vec<Vec<usize>> = found.into_iter().map(|s| s.into_iter().collect()).collect();
+    out.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    out
+}
+
+fn places(network: &Network) -> Vec<usize> {
+    (0..network.num_species())
+        .filter(|&i| !network.species_attributes(i).is_fixed())
+        .collect()
+}
+
+pub fn minimal_siphons(network: &Network) -> Vec<Vec<usize>> {
+    minimal_siphons_of(&places(network), &arcs(network))
+}
+
+// traps are the siphons of the net with every transition reversed
+pub fn minimal_traps(network: &Network) -> Vec<Vec<usize>> {
+    let reversed: Vec<Arcs> = arcs(network)
+        .into_iter()
+        .map(|(inputs, outputs)| (outputs, inputs))
+        .collect();
+    minimal_siphons_of(&places(network), &reversed)
+}
+
+// whether some y >= 0 with y^T N = 0 and y_i >= lower for species i in
+// `species`, zero elsewhere, exists; scaled to sum to one if lower is zero
+fn semiflow(network: &Network, species: &[usize], lower: f64) -> bool {
+    let n = network.stoichiometric_matrix();
+    let mut lp = LinearProgram::new(species.len());
+    for (k, _) in species.iter().enumerate() {
+        lp.set_bounds(k, lower, f64::INFINITY);
+    }
+    for j in 0..network.num_reactions() {
+        let row: Vec<f64> = species.iter().map(|&i| n[(i, j)]).collect();
+        if row.iter().any(|&c| c != 0.0) {
+            lp.add_constraint(row, Relation::Equal, 0.0);
+        }
+    }
+    if lower == 0.0 {
+        lp.add_constraint(vec![1.0; species.len()], Relation::Equal, 1.0);
+    }
+    lp.minimize().is_ok()
+}
+
+// whether the species contain the support of a nonnegative conservation law
+pub fn contains_conservation_law(network: &Network, species: &[usize]) -> bool {
+    !species.is_empty() && semiflow(network, species, 0.0)
+}
+
+// whether a conservation law with positive weight on every species that
+// changes exists, which bounds all trajectories
+pub fn is_conservative(network: &Network) -> bool {
+    semiflow(network, &places(network), 1.0)
+}
+
+#[derive(Debug, Clone)]
+pub struct Persistence {
+    pub siphons: Vec<Vec<usize>>,
+    pub traps: Vec<Vec<usize>>,
+    // minimal siphons without a conservation law, which the criterion
+    // cannot rule out emptying
+    pub critical: Vec<Vec<usize>>,
+    pub conservative: bool,
+}
+
+impl Persistence {
+    // whether the criterion shows the network persistent; without critical
+    // siphons a network with unbounded trajectories may still lose species
+    pub fn is_persistent(&self) -> bool {
+        self.critical.is_empty() && self.conservative
+    }
+}
+
+pub fn persistence(network: &Network) -> Persistence {
+    let siphons = minimal_siphons(network);
+    let critical = siphons
+        .iter()
+        .filter(|s| !contains_conservation_law(network, s))
+        .cloned()
+        .collect();
+    Persistence {
+        siphons,
+        traps: minimal_traps(network),
+        critical,
+        conservative: is_conservative(network),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    fn sorted(mut sets: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        sets.sort();
+        sets
+    }
+
+    #[test]
+    fn enzyme_siphons_and_traps() {
+        // species E, S, ES, P in order
+        let network = network("E + S <-> ES\nES -> E + P\n");
+        let result = persistence(&network);
+        assert_eq!(sorted(result.siphons.clone()), vec![vec![0, 2], vec![1, 2]]);
+        assert_eq!(sorted(result.traps.clone()), vec![vec![0, 2], vec![3]]);
+        // substrate runs out: S + ES is not conserved without P
+        assert_eq!(result.critical, vec![vec![1, 2]]);
+        assert!(result.conservative && !result.is_persistent());
+    }
+
+    #[test]
+    fn reversible_conversion_is_persistent() {
+        let network = network("A <-> B\nB + C <-> D\n");
+        let result = persistence(&network);
+        assert!(result.critical.is_empty());
+        assert!(result.is_persistent());
+    }
+
+    #[test]
+    fn autocatalyst_can_be_lost() {
+        let network = network("A + B -> 2 B\nB -> A\n");
+        let b = network.find_species("B").unwrap();
+        let result = persistence(&network);
+        assert_eq!(result.critical, vec![vec![b]]);
+        assert!(contains_conservation_law(&network, &[0, 1]));
+        assert!(!result.is_persistent());
+    }
+
+    #[test]
+    fn fixed_species_are_left_out() {
+        let network = network("species F [constant]\nF -> A\nA -> 0\n");
+        assert!(minimal_siphons(&network).is_empty());
+        assert!(!is_conservative(&network));
+    }
+}
@@ -0,0 +1,191 @@
+// linear stability of a steady state
+//
+// The Jacobian of the rate equations is singular along every conservation
+// law, so each law's dependent species is eliminated first: with
+// x_d = (T - sum_i w_i x_i) / w_d, the reduced Jacobian over the remaining
+// species is J_II + J_Id dx_d/dx_I. Its eigenvalues decide stability, and
+// each mode's time scale is 1/|Re lambda|. The species taking part in a mode
+// are ranked by participation factors |l_i r_i|, from its left and right
+// eigenvectors.
+use std::fmt;
+
+use super::conservation::ConservationLaw;
+use crate::data::Matrix;
+use crate::network::Network;
+use crate::numeric::OdeSystem;
+use crate::numeric::eigen::{self, Complex, NoConvergence};
+use crate::simulation::RateEquations;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stability {
+    StableNode,
+    // stable with damped oscillations
+    StableFocus,
+    UnstableNode,
+    UnstableFocus,
+    // growing and decaying directions
+    Saddle,
+    // a mode with zero real part; linearization is inconclusive
+    NonHyperbolic,
+}
+
+impl Stability {
+    pub fn is_stable(&self) -> bool {
+        matches!(self, Stability::StableNode | Stability::StableFocus)
+    }
+}
+
+impl fmt::Display for Stability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Stability::StableNode => "stable node",
+            Stability::StableFocus => "stable focus",
+            Stability::UnstableNode => "unstable node",
+            Stability::UnstableFocus => "unstable focus",
+            Stability::Saddle => "saddle",
+            Stability::NonHyperbolic => "non-hyperbolic",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mode {
+    pub eigenvalue: Complex,
+    // 1/|Re lambda|; infinite for a mode with zero real part
+    pub time_scale: f64,
+    // 2 pi/|Im lambda| for oscillatory modes
+    pub period: Option<f64>,
+    // species and their participation, largest first, summing to one
+    pub participation: Vec<(usize, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StabilityAnalysis {
+    pub stability: Stability,
+    // species kept in the reduced system
+    pub species: Vec<usize>,
+    pub jacobian: Matrix,
+    // slowest modes first
+    pub modes: Vec<Mode>,
+}
+
+// Jacobian over the species that are not eliminated by a conservation law,
+// and those species
+pub fn reduced_jacobian(
+    network: &Network,
+    system: &RateEquations,
+    laws: &[ConservationLaw],
+    x: &[f64],
+) -> (Matrix, Vec<usize>) {
+    let j = system.jacobian(0.0, x);
+    let n = x.len();
+    let independent: Vec<usize> = (0..n)
+        .filter(|&i| laws.iter().all(|law| law.dependent != i))
+        .collect();
+    let mut reduced = Matrix::zeros(independent.len(), independent.len());
+    for (a, &row) in independent.iter().enumerate() {
+        for (b, &col) in independent.iter().enumerate() {
+            reduced[(a, b)] = j[(row, col)];
+        }
+    }
+    for law in laws {
+        let weights = law.weights(network);
+        let d = law.dependent;
+        for (a, &row) in independent.iter().enumerate() {
+            if j[(row, d)] == 0.0 {
+                continue;
+            }
+            for (b, &col) in independent.iter().enumerate() {
+                reduced[(a, b)] -= j[(row, d)] * weights[col] / weights[d];
+            }
+        }
+    }
+    (reduced, independent)
+}
+
+// eigenvalues with real parts this small relative to the spectrum count as zero
+const ZERO_TOLERANCE: f64 = 1e-9;
+
+// stability from the eigenvalues of a (reduced) Jacobian
+pub fn classify(eigenvalues: &[Complex]) -> Stability {
+    let scale = eigenvalues.iter().fold(1.0_f64, |m, l| m.max(l.abs()));
+    let zero = ZERO_TOLERANCE * scale;
+    let growing = eigenvalues.iter().filter(|l| l.re > zero).count();
+    let decaying = eigenvalues.iter().filter(|l| l.re < -zero).count();
+    let oscillating = eigenvalues.iter().any(|l| l.im.abs() > zero);
+    if growing + decaying < eigenvalues.len() {
+        Stability::NonHyperbolic
+    } else if growing > 0 && decaying > 0 {
+        Stability::Saddle
+    } else if growing > 0 {
+        if oscillating {
+            Stability::UnstableFocus
+        } else {
+            Stability::UnstableNode
+        }
+    } else if oscillating {
+        Stability::StableFocus
+    } else {
+        Stability::StableNode
+    }
+}
+
+// participation of each state in the mode with eigenvalue lambda
+fn participation(jacobian: &Matrix, lambda: Complex) -> Vec<f64> {
+    let right = eigen::eigenvector(jacobian, lambda);
+    // left eigenvectors of J are right eigenvectors of J^T
+    let left = eigen::eigenvector(&jacobian.transpose(), lambda);
+    let p: Vec<f64> = right
+        .iter()
+        .zip(&left)
+        .map(|(r, l)| (*r * *l).abs())
+        .collect();
+    let total: f64 = p.iter().sum();
+    if total > 0.0 && total.is_finite() {
+        p.iter().map(|pi| pi / total).collect()
+    } else {
+        // fall back to the right eigenvector alone
+        let total: f64 = right.iter().map(|r| r.abs().powi(2)).sum();
+        right.iter().map(|r| r.abs().powi(2) / total).collect()
+    }
+}
+
+// stability of the steady state x of the network
+pub fn analyze(
+    network: &Network,
+    system: &RateEquations,
+    laws: &[ConservationLaw],
+    x: &[f64],
+) -> Result<StabilityAnalysis, NoConvergence> {
+    let (jacobian, species) = reduced_jacobian(network, system, laws, x);
+    let eigenvalues = eigen::eigenvalues(&jacobian)?;
+    let stability = classify(&eigenvalues);
+
+    let mut modes: Vec<Mode> = eigenvalues
+        .iter()
+        // one mode per complex-conjugate pair
+        .filter(|l| l.im >= 0.0)
+        .map(|&lambda| {
+            let mut participation: Vec<(usize, f64)> = participation(&jacobian, lambda)
+                .into_iter()
+                .enumerate()
+                .map(|(k, p)| (species[k], p))
+                .collect();
+            participation.sort_by(|a, b| b.1.total_cmp(&a.1));
+            Mode {
+                eigenvalue: lambda,
+                time_scale: 1.0 / lambda.re.abs(),
+                period: (lambda.im != 0.0).then(|| 2.0 * std::f64::consts::PI / lambda.im),
+                participation,
+            }
+        })
+        .collect();
+    modes.sort_by(|a, b| b.time_scale.total_cmp(&a.time_scale));
+    Ok(StabilityAnalysis {
+        stability,
+        species,
+        jacobian,
+        modes,
+    })
+}
@@ -0,0 +1,284 @@
+// steady states of the rate equations, f(x) = 0
+//
+// The Jacobian of f is singular whenever the network has conservation laws,
+// so the balance of each law's dependent species is replaced by the law
+// itself, fixing the totals at their initial values. The resulting system
+// is solved by damped Newton iteration, optionally starting from a point
+// reached by integrating the rate equations when Newton fails from the
+// initial conditions.
+use std::error::Error;
+use std::fmt;
+
+use super::conservation::{self, ConservationLaw};
+use crate::data::{Matrix, Overflow};
+use crate::network::Network;
+use crate::numeric::Lu;
+use crate::numeric::ode::{Integrator, Method, OdeOptions, OdeSystem};
+use crate::simulation::{RateEquations, SimulationError};
+
+#[derive(Debug)]
+pub enum SteadyStateError {
+    Simulation(SimulationError),
+    // residual of the best point found
+    NoConvergence(f64),
+    SingularJacobian,
+    Overflow(Overflow),
+}
+
+impl fmt::Display for SteadyStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SteadyStateError::Simulation(e) => write!(f, "{e}"),
+            SteadyStateError::NoConvergence(r) => write!(
+                f,
+                "Newton iteration did not converge (residual {r}); try --integrate"
+            ),
+            SteadyStateError::SingularJacobian => {
+                write!(
+                    f,
+                    "Singular Jacobian; the steady state may not be isolated (try --integrate)"
+                )
+            }
+            SteadyStateError::Overflow(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for SteadyStateError {}
+
+impl From<SimulationError> for SteadyStateError {
+    fn from(e: SimulationError) -> Self {
+        SteadyStateError::Simulation(e)
+    }
+}
+
+impl From<Overflow> for SteadyStateError {
+    fn from(e: Overflow) -> Self {
+        SteadyStateError::Overflow(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SteadyStateOptions {
+    // largest acceptable |dx_i/dt| at the steady state
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    // fall back to integrating towards the steady state
+    pub integrate: bool,
+    // longest integration of the fallback
+    pub t_max: f64,
+}
+
+impl Default for SteadyStateOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-9,
+            max_iterations: 100,
+            integrate: false,
+            t_max: 1e9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approach {
+    Newton,
+    Integration,
+}
+
+#[derive(Debug, Clone)]
+pub struct SteadyState {
+    pub concentrations: Vec<f64>,
+    // rate of each reaction at the steady state
+    pub fluxes: Vec<f64>,
+    // largest |dx_i/dt| at the steady state
+    pub residual: f64,
+    pub iterations: usize,
+    pub approach: Approach,
+}
+
+// rate equations with conservation laws in place of redundant balances
+pub struct SteadyStateSystem<'a> {
+    pub system: &'a RateEquations,
+    laws: Vec<(usize, Vec<f64>, f64)>,
+}
+
+impl<'a> SteadyStateSystem<'a> {
+    // laws keep the totals of the concentrations x0
+    pub fn new(
+        network: &Network,
+        system: &'a RateEquations,
+        laws: &[ConservationLaw],
+        x0: &[f64],
+    ) -> Self {
+        let laws = laws
+            .iter()
+            .map(|law| (law.dependent, law.weights(network), law.total(network, x0)))
+            .collect();
+        Self { system, laws }
+    }
+
+    // the same conserved totals for another system, e.g. with a parameter
+    // changed
+    pub fn with_system<'b>(&self, system: &'b RateEquations) -> SteadyStateSystem<'b> {
+        SteadyStateSystem {
+            system,
+            laws: self.laws.clone(),
+        }
+    }
+
+    // species whose balance is replaced by a conservation law
+    pub fn dependents(&self) -> impl Iterator<Item = usize> + '_ {
+        self.laws.iter().map(|(dependent, _, _)| *dependent)
+    }
+
+    pub fn residual(&self, x: &[f64]) -> Vec<f64> {
+        let mut f = vec![0.0; x.len()];
+        self.system.rhs(0.0, x, &mut f);
+        for (dependent, weights, total) in &self.laws {
+            f[*dependent] = dot(weights, x) - total;
+        }
+        f
+    }
+
+    pub fn jacobian(&self, x: &[f64]) -> Matrix {
+        let mut j = self.system.jacobian(0.0, x);
+        for (dependent, weights, _) in &self.laws {
+            j.row_mut(*dependent).copy_from_slice(weights);
+        }
+        j
+    }
+
+    // damped Newton iteration from x; returns the solution and the number
+    // of iterations
+    pub fn newton(
+        &self,
+        mut x: Vec<f64>,
+        options: &SteadyStateOptions,
+    ) -> Result<(Vec<f64>, usize), SteadyStateError> {
+        let mut f = self.residual(&x);
+        for iteration in 0..options.max_iterations {
+            if max_norm(&f) <= options.tolerance {
+                return Ok((x, iteration));
+            }
+            let lu = Lu::factor(self.jacobian(&x)).ok_or(SteadyStateError::SingularJacobian)?;
+            let dx = lu.solve(&f);
+
+            // backtrack until the residual decreases and concentrations
+            // stay non-negative
+            let norm = max_norm(&f);
+            let mut lambda = 1.0;
+            loop {
+                let trial: Vec<f64> = x.iter().zip(&dx).map(|(xi, di)| xi - lambda * di).collect();
+                let negative = trial
+                    .iter()
+                    .any(|&xi| xi < -options.tolerance * (1.0 + norm));
+                if !negative {
+                    let ft = self.residual(&trial);
+                    if max_norm(&ft) < (1.0 - 1e-4 * lambda) * norm {
+                        x = trial.into_iter().map(|xi| xi.max(0.0)).collect();
+                        f = self.residual(&x);
+                        break;
+                    }
+                }
+                lambda /= 2.0;
+                if lambda < 1e-10 {
+                    return Err(SteadyStateError::NoConvergence(norm));
+                }
+            }
+        }
+        if max_norm(&f) <= options.tolerance {
+            Ok((x, options.max_iterations))
+        } else {
+            Err(SteadyStateError::NoConvergence(max_norm(&f)))
+        }
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn max_norm(v: &[f64]) -> f64 {
+    v.iter().fold(0.0, |m, x| m.max(x.abs()))
+}
+
+// integrate with the stiff method over growing horizons until the rate of
+// change falls to the tolerance
+fn integrate_towards(
+    system: &RateEquations,
+    x0: Vec<f64>,
+    options: &SteadyStateOptions,
+) -> Result<Vec<f64>, SteadyStateError> {
+    let ode = OdeOptions {
+        method: Method::Rosenbrock,
+        ..Default::default()
+    };
+    let mut integrator = Integrator::new(system, 0.0, x0, ode);
+    let mut horizon = 1.0;
+    let mut dx = vec![0.0; system.dim()];
+    while horizon <= options.t_max {
+        while integrator.time() < horizon {
+            integrator
+                .step(horizon)
+                .map_err(|e| SteadyStateError::Simulation(e.into()))?;
+        }
+        system.rhs(horizon, integrator.state(), &mut dx);
+        if max_norm(&dx) <= options.tolerance {
+            break;
+        }
+        horizon *= 10.0;
+    }
+    Ok(integrator.state().to_vec())
+}
+
+// steady state reached from the network's initial conditions
+pub fn steady_state(
+    network: &Network,
+    options: &SteadyStateOptions,
+) -> Result<SteadyState, SteadyStateError> {
+    let system = RateEquations::new(network)?;
+    let laws = conservation::conservation_laws(network)?;
+    steady_state_from(
+        network,
+        &system,
+        &laws,
+        network.initial_conditions(),
+        options,
+    )
+}
+
+// steady state of `system` reached from x0, with the conserved totals of x0
+pub fn steady_state_from(
+    network: &Network,
+    system: &RateEquations,
+    laws: &[ConservationLaw],
+    x0: Vec<f64>,
+    options: &SteadyStateOptions,
+) -> Result<SteadyState, SteadyStateError> {
+    let problem = SteadyStateSystem::new(network, system, laws, &x0);
+
+    let (x, iterations, approach) = match problem.newton(x0.clone(), options) {
+        Ok((x, iterations)) => (x, iterations, Approach::Newton),
+        Err(e) if !options.integrate => return Err(e),
+        Err(_) => {
+            // integration keeps the conserved totals, so the same laws apply
+            let start = integrate_towards(system, x0, options)?;
+            let (x, iterations) = problem.newton(start, options)?;
+            (x, iterations, Approach::Integration)
+        }
+    };
+    let fluxes = system.rates(&x);
+    let residual = {
+        let mut dx = vec![0.0; x.len()];
+        system.rhs(0.0, &x, &mut dx);
+        max_norm(&dx)
+    };
+    Ok(SteadyState {
+        concentrations: x,
+        fluxes,
+        residual,
+        iterations,
+        approach,
+    })
+}
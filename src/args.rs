@@ -1 +1,132 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 
+// subcommands; the first argument selects one, otherwise models are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Print,
+    Fba,
+    Compile,
+    Simulate,
+    SteadyState,
+    Stability,
+    Sensitivity,
+    Control,
+    Fit,
+    Scan,
+    Continuation,
+    Reduce,
+    Check,
+    Gaps,
+    Siphons,
+    Invariants,
+}
+
+impl Command {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "print" => Some(Command::Print),
+            "fba" => Some(Command::Fba),
+            "compile" => Some(Command::Compile),
+            "simulate" => Some(Command::Simulate),
+            "steady-state" | "steady" => Some(Command::SteadyState),
+            "stability" => Some(Command::Stability),
+            "sensitivity" => Some(Command::Sensitivity),
+            "control" | "mca" => Some(Command::Control),
+            "fit" => Some(Command::Fit),
+            "scan" => Some(Command::Scan),
+            "continuation" | "continue" => Some(Command::Continuation),
+            "reduce" => Some(Command::Reduce),
+            "check" | "lint" => Some(Command::Check),
+            "gaps" => Some(Command::Gaps),
+            "siphons" | "persistence" => Some(Command::Siphons),
+            "invariants" => Some(Command::Invariants),
+            _ => None,
+        }
+    }
+}
+
+// options followed by a value, e.g. `--bounds fluxes.txt`
+static VALUED_OPTIONS: &[&str] = &[
+    "bounds",
+    "fraction",
+    "target",
+    "output",
+    "t-end",
+    "points",
+    "method",
+    "rtol",
+    "atol",
+    "seed",
+    "runs",
+    "threads",
+    "epsilon",
+    "dt",
+    "fast",
+    "parameters",
+    "times",
+    "data",
+    "scale",
+    "vary",
+    "grid",
+    "sample",
+    "samples",
+    "observe",
+    "step",
+    "qssa",
+    "equilibrium",
+    "eliminate",
+    "allow",
+    "deny",
+];
+// options that are switched on by their presence
+static FLAG_OPTIONS: &[&str] = &[
+    "fva",
+    "knockouts",
+    "integrate",
+    "steady-state",
+    "eliminate-conserved",
+];
+
+#[derive(Debug, Default)]
+pub struct Options {
+    values: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Options {
+    // consume `--name [value]`, taking the value from the remaining arguments
+    pub fn take(
+        &mut self,
+        name: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), &'static str> {
+        if FLAG_OPTIONS.contains(&name) {
+            self.flags.insert(name.to_string());
+            Ok(())
+        } else if VALUED_OPTIONS.contains(&name) {
+            let value = args.next().ok_or("Missing value for option")?;
+            self.values.insert(name.to_string(), value);
+            Ok(())
+        } else {
+            Err("Unknown Argument")
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|s| s.as_str())
+    }
+
+    pub fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.value(name) {
+            Some(s) => s
+                .parse()
+                .map_err(|_| format!("Invalid value '{s}' for option --{name}")),
+            None => Ok(default),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+pub mod pnml;
+pub mod python;
+pub mod r;
+pub mod sbml;
+
+use crate::data::{Matrix, Overflow, Rational};
+use crate::network::{Expr, Network, Notation, eliminate_common_subexpressions};
+
+// output formats of `reaction_net compile`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Python,
+    R,
+    Sbml,
+    Pnml,
+}
+
+impl Target {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "python" | "py" => Some(Target::Python),
+            "r" => Some(Target::R),
+            "sbml" | "xml" => Some(Target::Sbml),
+            "pnml" => Some(Target::Pnml),
+            _ => None,
+        }
+    }
+}
+
+// fails only if PNML arc weights are too large for exact arithmetic
+pub fn generate(network: &Network, target: Target, model: &str) -> Result<String, Overflow> {
+    match target {
+        Target::Python => Ok(python::generate(network, model)),
+        Target::R => Ok(r::generate(network, model)),
+        Target::Sbml => Ok(sbml::generate(network, model)),
+        Target::Pnml => pnml::generate(network, model),
+    }
+}
+
+// what a symbol in a rate law refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbol {
+    Species(usize),
+    Parameter(usize),
+    Compartment(usize),
+}
+
+pub fn resolve(network: &Network, name: &str) -> Option<Symbol> {
+    if let Some(i) = network.find_species(name) {
+        Some(Symbol::Species(i))
+    } else if let Some(j) = network.find_parameter(name) {
+        Some(Symbol::Parameter(j))
+    } else {
+        network.find_compartment(name).map(Symbol::Compartment)
+    }
+}
+
+// right-hand side of d[x_i]/dt as a sum of named reaction rates, e.g.
+// `r1 - 2 * r3`, scaled by the volume ratio of reaction and species
+// compartments where they differ
+pub fn balance(
+    network: &Network,
+    stoichiometry: &Matrix<Rational>,
+    i: usize,
+    rate: impl Fn(usize) -> String,
+    volume: impl Fn(usize) -> String,
+) -> String {
+    let mut out = String::new();
+    if network.species_attributes(i).is_fixed() {
+        return "0".to_string();
+    }
+    let species_compartment = network.species_attributes(i).compartment;
+    for (j, &c) in stoichiometry.row(i).iter().enumerate() {
+        if c.is_zero() {
+            continue;
+        }
+        let mut term = rate(j);
+        let reaction_compartment = network.reaction_compartment(j);
+        if reaction_compartment != species_compartment {
+            if let Some(rc) = reaction_compartment {
+                term = format!("{term} * {}", volume(rc));
+            }
+            if let Some(sc) = species_compartment {
+                term = format!("{term} / {}", volume(sc));
+            }
+        }
+        let negative = c < Rational::ZERO;
+        let magnitude = c.abs();
+        if magnitude != Rational::ONE {
+            term = format!("{magnitude} * {term}");
+        }
+        if out.is_empty() {
+            if negative {
+                out.push_str("- ");
+            }
+        } else {
+            out.push_str(if negative { " - " } else { " + " });
+        }
+        out.push_str(&term);
+    }
+    if out.is_empty() {
+        out.push('0');
+    }
+    out
+}
+
+// variables the rate equations are differentiated by: species give the
+// Jacobian, parameters the sensitivities d f / d k
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variables {
+    Species,
+    Parameters,
+}
+
+// analytic derivative of the rate equations, as code
+#[derive(Debug, Clone, Default)]
+pub struct Derivatives {
+    // temporaries and partial derivatives of the rate laws, in order
+    pub assignments: Vec<(String, String)>,
+    // non-zero entries (species, variable, d f_i / d variable)
+    pub entries: Vec<(usize, usize, String)>,
+}
+
+pub fn derivatives(
+    network: &Network,
+    variables: Variables,
+    notation: &dyn Notation,
+    partial: impl Fn(usize, usize) -> String,
+    volume: impl Fn(usize) -> String,
+) -> Derivatives {
+    let mut index = Vec::new();
+    let mut expressions = Vec::new();
+    for j in 0..network.num_reactions() {
+        let law = network.rate_law(j);
+        for s in law.symbols() {
+            let k = match (variables, resolve(network, &s)) {
+                (Variables::Species, Some(Symbol::Species(i))) => i,
+                (Variables::Parameters, Some(Symbol::Parameter(p))) => p,
+                _ => continue,
+            };
+            let d = law.derivative(&s).simplify();
+            if d != Expr::Number(0.0) {
+                index.push((j, k));
+                expressions.push(d);
+            }
+        }
+    }
+    // temporaries must not shadow names of the model
+    let shared = eliminate_common_subexpressions(&expressions, "tmp", |name| {
+        resolve(network, name).is_some()
+    });
+
+    let mut out = Derivatives::default();
+    for (name, e) in &shared.definitions {
+        out.assignments.push((name.clone(), e.render(notation)));
+    }
+    for (&(j, k), e) in index.iter().zip(&shared.expressions) {
+        out.assignments.push((partial(j, k), e.render(notation)));
+    }
+
+    let stoichiometry = network.exact_stoichiometric_matrix();
+    let count = match variables {
+        Variables::Species => network.num_species(),
+        Variables::Parameters => network.num_parameters(),
+    };
+    for k in 0..count {
+        // only reactions whose rate depends on variable k contribute
+        let mut masked = Matrix::zeros(stoichiometry.rows(), stoichiometry.cols());
+        for &(j, _) in index.iter().filter(|(_, kk)| *kk == k) {
+            for i in 0..stoichiometry.rows() {
+                masked[(i, j)] = stoichiometry[(i, j)];
+            }
+        }
+        for i in 0..network.num_species() {
+            let rhs = balance(network, &masked, i, |j| partial(j, k), &volume);
+            if rhs != "0" {
+                out.entries.push((i, k, rhs));
+            }
+        }
+    }
+    out
+}
+
+// floating point literal that reads back as the same value
+pub fn literal(x: f64) -> String {
+    format!("{x:?}")
+}
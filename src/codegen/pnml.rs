@@ -0,0 +1,148 @@
+// export to PNML as a place/transition net, for Snoopy and other Petri net
+// tools
+//
+// Places are species and transitions reactions, split into two for
+// reversible reactions as in `analysis::invariants`. Arc weights must be
+// whole numbers, so a transition with fractional coefficients fires the
+// smallest multiple of its reaction with integer ones. Constant and boundary
+// species get an arc each way of equal weight, which leaves them unchanged.
+// Initial markings are the initial conditions rounded to whole tokens.
+use std::collections::HashSet;
+
+use crate::analysis::invariants::{self, Transition};
+use crate::data::rational::gcd;
+use crate::data::{Overflow, Rational};
+use crate::network::{Complex, Network};
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// XML identifiers must start with a letter or `_` and continue with
+// letters, digits, `_`, `-` or `.`
+fn fresh(used: &mut HashSet<String>, prefix: &str, name: &str) -> String {
+    let base: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let base = format!("{prefix}_{base}");
+    let mut id = base.clone();
+    let mut n = 1;
+    while used.contains(&id) {
+        n += 1;
+        id = format!("{base}_{n}");
+    }
+    used.insert(id.clone());
+    id
+}
+
+// smallest positive integer making every coefficient of the transition whole
+fn multiplier(network: &Network, t: &Transition) -> Result<Rational, Overflow> {
+    let denominators = t
+        .inputs(network)
+        .terms()
+        .into_iter()
+        .chain(t.outputs(network).terms())
+        .map(|(_, c)| c.denom());
+    let mut l: i128 = 1;
+    for d in denominators {
+        l = (l / gcd(l, d)).checked_mul(d).ok_or(Overflow)?;
+    }
+    Rational::checked_new(l, 1).ok_or(Overflow)
+}
+
+fn text(tag: &str, value: &str) -> String {
+    format!("<{tag}><text>{}</text></{tag}>", escape(value))
+}
+
+pub fn generate(network: &Network, model: &str) -> Result<String, Overflow> {
+    let mut used = HashSet::new();
+    let net_id = fresh(&mut used, "net", model);
+    let places: Vec<String> = (0..network.num_species())
+        .map(|i| fresh(&mut used, "p", network.species_name(i)))
+        .collect();
+    let transitions = invariants::transitions(network);
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<pnml xmlns=\"http://www.pnml.org/version-2009/grammar/pnml\">\n");
+    out.push_str(&format!(
+        "  <net id=\"{net_id}\" type=\"http://www.pnml.org/version-2009/grammar/ptnet\">\n"
+    ));
+    out.push_str(&format!("    {}\n", text("name", model)));
+    out.push_str("    <page id=\"page\">\n");
+
+    let x0 = network.initial_conditions();
+    for (i, id) in places.iter().enumerate() {
+        let tokens = x0[i].round().max(0.0) as u64;
+        out.push_str(&format!(
+            "      <place id=\"{id}\">{}",
+            text("name", network.species_name(i))
+        ));
+        if tokens > 0 {
+            out.push_str(&text("initialMarking", &tokens.to_string()));
+        }
+        out.push_str("</place>\n");
+    }
+
+    let mut arcs: Vec<(String, String, Rational)> = Vec::new();
+    for t in &transitions {
+        let id = fresh(&mut used, "t", &t.name(network));
+        out.push_str(&format!(
+            "      <transition id=\"{id}\">{}</transition>\n",
+            text("name", &t.name(network))
+        ));
+        let scale = multiplier(network, t)?;
+        let first = arcs.len();
+        let mut arc = |from: &str, to: &str, c: Rational| -> Result<(), Overflow> {
+            let weight = c.checked_mul(scale).ok_or(Overflow)?;
+            match arcs[first..]
+                .iter_mut()
+                .find(|(s, t, _)| s == from && t == to)
+            {
+                Some((_, _, w)) => *w = w.checked_add(weight).ok_or(Overflow)?,
+                None => arcs.push((from.to_string(), to.to_string(), weight)),
+            }
+            Ok(())
+        };
+        let fixed = |i: usize| network.species_attributes(i).is_fixed();
+        let terms = |complex: &Complex| complex.terms().into_iter();
+        for (i, c) in terms(t.inputs(network)) {
+            arc(&places[i], &id, c)?;
+            if fixed(i) {
+                arc(&id, &places[i], c)?;
+            }
+        }
+        for (i, c) in terms(t.outputs(network)) {
+            arc(&id, &places[i], c)?;
+            if fixed(i) {
+                arc(&places[i], &id, c)?;
+            }
+        }
+    }
+    for (k, (source, target, weight)) in arcs.iter().enumerate() {
+        out.push_str(&format!(
+            "      <arc id=\"a{}\" source=\"{source}\" target=\"{target}\">",
+            k + 1
+        ));
+        if *weight != Rational::ONE {
+            out.push_str(&text("inscription", &weight.to_string()));
+        }
+        out.push_str("</arc>\n");
+    }
+
+    out.push_str("    </page>\n");
+    out.push_str("  </net>\n");
+    out.push_str("</pnml>\n");
+    Ok(out)
+}
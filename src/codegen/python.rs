@@ -0,0 +1,152 @@
+// translation into python using numpy
+use super::{Derivatives, Symbol, Variables, balance, derivatives, literal, resolve};
+use crate::network::{Function, Network, Notation};
+
+struct Python<'a> {
+    network: &'a Network,
+}
+
+impl Notation for Python<'_> {
+    fn symbol(&self, name: &str) -> String {
+        match resolve(self.network, name) {
+            Some(Symbol::Species(i)) => format!("x[{i}]"),
+            Some(Symbol::Parameter(j)) => format!("k[{j}]"),
+            Some(Symbol::Compartment(_)) => format!("compartments[{name:?}]"),
+            None => name.to_string(),
+        }
+    }
+
+    fn power(&self) -> &'static str {
+        "**"
+    }
+
+    fn function(&self, f: Function) -> String {
+        format!("numpy.{}", f.name())
+    }
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(", "))
+}
+
+pub fn generate(network: &Network, model: &str) -> String {
+    let n = network.num_species();
+    let notation = Python { network };
+    let mut out = String::new();
+
+    out.push_str(&format!("# generated by reaction_net from {model}\n"));
+    out.push_str("import numpy\n\n");
+
+    let names = |v: &[String]| list(v.iter().map(|s| format!("{s:?}")));
+    out.push_str(&format!("species = {}\n", names(network.species_names())));
+    out.push_str(&format!(
+        "parameters = {}\n",
+        names(network.parameter_names())
+    ));
+    let compartments: Vec<String> = (0..network.num_compartments())
+        .map(|c| {
+            let volume = network.compartment_attributes(c).volume();
+            format!("{:?}: {}", network.compartment_name(c), literal(volume))
+        })
+        .collect();
+    out.push_str(&format!("compartments = {{{}}}\n", compartments.join(", ")));
+    let membership = (0..n).map(|i| match network.species_attributes(i).compartment {
+        Some(c) => format!("{:?}", network.compartment_name(c)),
+        None => "None".to_string(),
+    });
+    out.push_str(&format!("species_compartments = {}\n", list(membership)));
+    out.push_str(&format!(
+        "default_parameters = numpy.array({})\n",
+        list(network.parameter_values().into_iter().map(literal))
+    ));
+    out.push_str(&format!(
+        "initial_conditions = numpy.array({})\n\n",
+        list(network.initial_conditions().into_iter().map(literal))
+    ));
+
+    out.push_str("def vec(x : numpy.ndarray, k : numpy.ndarray) -> numpy.ndarray:\n");
+    out.push_str(&format!("    v = numpy.zeros({n}, dtype = x.dtype)\n"));
+    for j in 0..network.num_reactions() {
+        out.push_str(&format!(
+            "    r{} = {}  # {}\n",
+            j + 1,
+            network.rate_law(j).render(&notation),
+            network.original_reaction_formula(j)
+        ));
+    }
+    let stoichiometry = network.exact_stoichiometric_matrix();
+    for i in 0..n {
+        let rhs = balance(
+            network,
+            &stoichiometry,
+            i,
+            |j| format!("r{}", j + 1),
+            |c| format!("compartments[{:?}]", network.compartment_name(c)),
+        );
+        if rhs != "0" {
+            out.push_str(&format!("    v[{i}] = {rhs}\n"));
+        }
+    }
+    out.push_str("    return v\n");
+
+    let volume = |c| format!("compartments[{:?}]", network.compartment_name(c));
+    out.push_str("\n# d vec / d x\n");
+    out.push_str("def jac(x : numpy.ndarray, k : numpy.ndarray) -> numpy.ndarray:\n");
+    out.push_str(&format!(
+        "    J = numpy.zeros(({n}, {n}), dtype = x.dtype)\n"
+    ));
+    let jacobian = derivatives(
+        network,
+        Variables::Species,
+        &notation,
+        |j, i| format!("dr{}_dx{i}", j + 1),
+        volume,
+    );
+    write_matrix(&mut out, "J", &jacobian);
+
+    let p = network.num_parameters();
+    out.push_str("\n# d vec / d k\n");
+    out.push_str("def sens(x : numpy.ndarray, k : numpy.ndarray) -> numpy.ndarray:\n");
+    out.push_str(&format!(
+        "    S = numpy.zeros(({n}, {p}), dtype = x.dtype)\n"
+    ));
+    let sensitivities = derivatives(
+        network,
+        Variables::Parameters,
+        &notation,
+        |j, l| format!("dr{}_dk{l}", j + 1),
+        volume,
+    );
+    write_matrix(&mut out, "S", &sensitivities);
+
+    let observables: Vec<_> = network.observables().collect();
+    if !observables.is_empty() {
+        let names = list(observables.iter().map(|d| format!("{:?}", d.name)));
+        out.push_str(&format!("\nobservables = {names}\n\n"));
+        out.push_str("# observables at state x, in the order of `observables`\n");
+        out.push_str("def obs(x : numpy.ndarray, k : numpy.ndarray) -> numpy.ndarray:\n");
+        out.push_str(&format!(
+            "    o = numpy.zeros({}, dtype = x.dtype)\n",
+            observables.len()
+        ));
+        for (l, d) in observables.iter().enumerate() {
+            out.push_str(&format!(
+                "    o[{l}] = {}  # {}\n",
+                network.expand(&d.value).render(&notation),
+                d.name
+            ));
+        }
+        out.push_str("    return o\n");
+    }
+    out
+}
+
+fn write_matrix(out: &mut String, name: &str, d: &Derivatives) {
+    for (lhs, rhs) in &d.assignments {
+        out.push_str(&format!("    {lhs} = {rhs}\n"));
+    }
+    for (i, k, rhs) in &d.entries {
+        out.push_str(&format!("    {name}[{i}, {k}] = {rhs}\n"));
+    }
+    out.push_str(&format!("    return {name}\n"));
+}
@@ -0,0 +1,163 @@
+// translation into R
+use super::{Derivatives, Symbol, Variables, balance, derivatives, literal, resolve};
+use crate::network::{Network, Notation};
+
+struct R<'a> {
+    network: &'a Network,
+}
+
+impl Notation for R<'_> {
+    fn symbol(&self, name: &str) -> String {
+        match resolve(self.network, name) {
+            Some(Symbol::Species(i)) => format!("x[{}]", i + 1),
+            Some(Symbol::Parameter(j)) => format!("k[{}]", j + 1),
+            Some(Symbol::Compartment(_)) => format!("compartments[[{name:?}]]"),
+            None => name.to_string(),
+        }
+    }
+}
+
+// R has no literal for an empty vector of a given type
+fn vector(items: Vec<String>, empty: &str) -> String {
+    if items.is_empty() {
+        empty.to_string()
+    } else {
+        format!("c({})", items.join(", "))
+    }
+}
+
+fn named(names: &[String], values: Vec<f64>) -> String {
+    let items = names
+        .iter()
+        .zip(values)
+        .map(|(n, v)| format!("{n:?} = {}", literal(v)))
+        .collect();
+    vector(items, "numeric(0)")
+}
+
+pub fn generate(network: &Network, model: &str) -> String {
+    let n = network.num_species();
+    let notation = R { network };
+    let mut out = String::new();
+
+    out.push_str(&format!("# generated by reaction_net from {model}\n"));
+
+    let names = |v: &[String]| vector(v.iter().map(|s| format!("{s:?}")).collect(), "character(0)");
+    out.push_str(&format!("species <- {}\n", names(network.species_names())));
+    out.push_str(&format!(
+        "parameters <- {}\n",
+        names(network.parameter_names())
+    ));
+    let compartment_names: Vec<String> = (0..network.num_compartments())
+        .map(|c| network.compartment_name(c).to_string())
+        .collect();
+    let volumes = (0..network.num_compartments())
+        .map(|c| network.compartment_attributes(c).volume())
+        .collect();
+    out.push_str(&format!(
+        "compartments <- {}\n",
+        named(&compartment_names, volumes)
+    ));
+    let membership = (0..n)
+        .map(|i| match network.species_attributes(i).compartment {
+            Some(c) => format!("{:?}", network.compartment_name(c)),
+            None => "NA".to_string(),
+        })
+        .collect();
+    out.push_str(&format!(
+        "species_compartments <- {}\n",
+        vector(membership, "character(0)")
+    ));
+    out.push_str(&format!(
+        "default_parameters <- {}\n",
+        named(network.parameter_names(), network.parameter_values())
+    ));
+    out.push_str(&format!(
+        "initial_conditions <- {}\n\n",
+        named(network.species_names(), network.initial_conditions())
+    ));
+
+    out.push_str("vec <- function(x, k){\n");
+    out.push_str(&format!("    v <- numeric({n}) # preallocate\n"));
+    for j in 0..network.num_reactions() {
+        out.push_str(&format!(
+            "    r{} <- {}  # {}\n",
+            j + 1,
+            network.rate_law(j).render(&notation),
+            network.original_reaction_formula(j)
+        ));
+    }
+    let stoichiometry = network.exact_stoichiometric_matrix();
+    for i in 0..n {
+        let rhs = balance(
+            network,
+            &stoichiometry,
+            i,
+            |j| format!("r{}", j + 1),
+            |c| format!("compartments[[{:?}]]", network.compartment_name(c)),
+        );
+        if rhs != "0" {
+            out.push_str(&format!("    v[{}] <- {rhs}\n", i + 1));
+        }
+    }
+    out.push_str("    return(v)\n}\n");
+
+    let volume = |c| format!("compartments[[{:?}]]", network.compartment_name(c));
+    out.push_str("\n# d vec / d x\n");
+    out.push_str("jac <- function(x, k){\n");
+    out.push_str(&format!("    J <- matrix(0, {n}, {n})\n"));
+    let jacobian = derivatives(
+        network,
+        Variables::Species,
+        &notation,
+        |j, i| format!("dr{}_dx{}", j + 1, i + 1),
+        volume,
+    );
+    write_matrix(&mut out, "J", &jacobian);
+
+    let p = network.num_parameters();
+    out.push_str("\n# d vec / d k\n");
+    out.push_str("sens <- function(x, k){\n");
+    out.push_str(&format!("    S <- matrix(0, {n}, {p})\n"));
+    let sensitivities = derivatives(
+        network,
+        Variables::Parameters,
+        &notation,
+        |j, l| format!("dr{}_dk{}", j + 1, l + 1),
+        volume,
+    );
+    write_matrix(&mut out, "S", &sensitivities);
+
+    let observables: Vec<_> = network.observables().collect();
+    if !observables.is_empty() {
+        let observable_names: Vec<String> = observables.iter().map(|d| d.name.clone()).collect();
+        out.push_str(&format!(
+            "\nobservables <- {}\n\n",
+            names(&observable_names)
+        ));
+        out.push_str("# observables at state x, named\n");
+        out.push_str("obs <- function(x, k){\n");
+        out.push_str(&format!("    o <- numeric({})\n", observables.len()));
+        for (l, d) in observables.iter().enumerate() {
+            out.push_str(&format!(
+                "    o[{}] <- {}  # {}\n",
+                l + 1,
+                network.expand(&d.value).render(&notation),
+                d.name
+            ));
+        }
+        out.push_str("    names(o) <- observables\n");
+        out.push_str("    return(o)\n}\n");
+    }
+    out
+}
+
+fn write_matrix(out: &mut String, name: &str, d: &Derivatives) {
+    for (lhs, rhs) in &d.assignments {
+        out.push_str(&format!("    {lhs} <- {rhs}\n"));
+    }
+    for (i, k, rhs) in &d.entries {
+        out.push_str(&format!("    {name}[{}, {}] <- {rhs}\n", i + 1, k + 1));
+    }
+    out.push_str(&format!("    return({name})\n}}\n"));
+}
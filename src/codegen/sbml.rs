@@ -0,0 +1,360 @@
+// export to SBML level 3 version 2
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use super::literal;
+use crate::network::{BinaryOp, Complex, EliminatedSpecies, Expr, Function, Network, StoichCoef};
+
+// compartment of species declared without `@compartment`
+const DEFAULT_COMPARTMENT: &str = "default";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// SBML identifiers share one namespace and must look like `[A-Za-z_][A-Za-z0-9_]*`
+struct Ids {
+    used: HashSet<String>,
+    species: Vec<String>,
+    parameters: Vec<String>,
+    compartments: Vec<String>,
+    by_name: HashMap<String, String>,
+}
+
+impl Ids {
+    fn new(network: &Network) -> Self {
+        let mut ids = Ids {
+            used: HashSet::new(),
+            species: Vec::new(),
+            parameters: Vec::new(),
+            compartments: Vec::new(),
+            by_name: HashMap::new(),
+        };
+        ids.used.insert(DEFAULT_COMPARTMENT.to_string());
+        for c in 0..network.num_compartments() {
+            let id = ids.fresh(network.compartment_name(c));
+            ids.compartments.push(id);
+        }
+        for i in 0..network.num_species() {
+            let id = ids.fresh(network.species_name(i));
+            ids.species.push(id);
+        }
+        for j in 0..network.num_parameters() {
+            let id = ids.fresh(network.parameter_name(j));
+            ids.parameters.push(id);
+        }
+        // rate laws refer to species first, as in the network
+        for (c, id) in ids.compartments.iter().enumerate() {
+            ids.by_name
+                .insert(network.compartment_name(c).to_string(), id.clone());
+        }
+        for (j, id) in ids.parameters.iter().enumerate() {
+            ids.by_name
+                .insert(network.parameter_name(j).to_string(), id.clone());
+        }
+        for (i, id) in ids.species.iter().enumerate() {
+            ids.by_name
+                .insert(network.species_name(i).to_string(), id.clone());
+        }
+        ids
+    }
+
+    fn fresh(&mut self, name: &str) -> String {
+        let mut base: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !base.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            base.insert(0, '_');
+        }
+        let mut id = base.clone();
+        let mut n = 1;
+        while self.used.contains(&id) {
+            n += 1;
+            id = format!("{base}_{n}");
+        }
+        self.used.insert(id.clone());
+        id
+    }
+
+    fn lookup(&self, name: &str) -> String {
+        self.by_name
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+fn mathml(expr: &Expr, ids: &Ids, out: &mut String) {
+    match expr {
+        Expr::Number(x) => out.push_str(&format!("<cn> {x} </cn>")),
+        Expr::Symbol(s) => out.push_str(&format!("<ci> {} </ci>", ids.lookup(s))),
+        Expr::Neg(e) => {
+            out.push_str("<apply><minus/>");
+            mathml(e, ids, out);
+            out.push_str("</apply>");
+        }
+        Expr::Binary(op, a, b) => {
+            let tag = match op {
+                BinaryOp::Add => "plus",
+                BinaryOp::Sub => "minus",
+                BinaryOp::Mul => "times",
+                BinaryOp::Div => "divide",
+                BinaryOp::Pow => "power",
+            };
+            out.push_str(&format!("<apply><{tag}/>"));
+            mathml(a, ids, out);
+            mathml(b, ids, out);
+            out.push_str("</apply>");
+        }
+        Expr::Call(f, e) => {
+            let tag = match f {
+                Function::Exp => "exp",
+                Function::Log => "ln",
+                Function::Sqrt => "root",
+                Function::Abs => "abs",
+            };
+            out.push_str(&format!("<apply><{tag}/>"));
+            mathml(e, ids, out);
+            out.push_str("</apply>");
+        }
+    }
+}
+
+// the complex's species, then any eliminated species with their id
+fn species_references(
+    tag: &str,
+    complex: &Complex,
+    eliminated: &[(&str, StoichCoef)],
+    ids: &Ids,
+    out: &mut String,
+) {
+    if complex.is_empty() && eliminated.is_empty() {
+        return;
+    }
+    let terms = complex
+        .terms()
+        .into_iter()
+        .map(|(id, c)| (ids.species[id].as_str(), c))
+        .chain(eliminated.iter().copied());
+    out.push_str(&format!("        <{tag}>\n"));
+    for (id, c) in terms {
+        out.push_str(&format!(
+            "          <speciesReference species=\"{id}\" stoichiometry=\"{}\" constant=\"true\"/>\n",
+            c.to_f64()
+        ));
+    }
+    out.push_str(&format!("        </{tag}>\n"));
+}
+
+pub fn generate(network: &Network, model: &str) -> String {
+    let mut ids = Ids::new(network);
+    let mut out = String::new();
+    let model_id = ids.fresh(model);
+
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(
+        "<sbml xmlns=\"http://www.sbml.org/sbml/level3/version2/core\" level=\"3\" version=\"2\">\n",
+    );
+    out.push_str(&format!(
+        "  <model id=\"{model_id}\" name=\"{}\">\n",
+        escape(model)
+    ));
+
+    // compartments
+    let needs_default =
+        (0..network.num_species()).any(|i| network.species_attributes(i).compartment.is_none());
+    out.push_str("    <listOfCompartments>\n");
+    if needs_default {
+        out.push_str(&format!(
+            "      <compartment id=\"{DEFAULT_COMPARTMENT}\" spatialDimensions=\"3\" size=\"1\" constant=\"true\"/>\n"
+        ));
+    }
+    for c in 0..network.num_compartments() {
+        let attr = network.compartment_attributes(c);
+        out.push_str(&format!(
+            "      <compartment id=\"{}\" name=\"{}\" spatialDimensions=\"3\" size=\"{}\" constant=\"true\"/>\n",
+            ids.compartments[c],
+            escape(network.compartment_name(c)),
+            literal(attr.volume())
+        ));
+    }
+    out.push_str("    </listOfCompartments>\n");
+
+    // observables, some of which stand for eliminated species
+    let observables: Vec<_> = network
+        .observables()
+        .map(|d| (ids.fresh(&d.name), d))
+        .collect();
+    let eliminated = |name: &str| -> Option<&EliminatedSpecies> {
+        network
+            .eliminated_species()
+            .iter()
+            .find(|e| e.observable == name)
+    };
+
+    // species
+    out.push_str("    <listOfSpecies>\n");
+    for i in 0..network.num_species() {
+        let attr = network.species_attributes(i);
+        let compartment = match attr.compartment {
+            Some(c) => ids.compartments[c].as_str(),
+            None => DEFAULT_COMPARTMENT,
+        };
+        out.push_str(&format!(
+            "      <species id=\"{}\" name=\"{}\" compartment=\"{compartment}\" initialConcentration=\"{}\" hasOnlySubstanceUnits=\"false\" boundaryCondition=\"{}\" constant=\"{}\"/>\n",
+            ids.species[i],
+            escape(network.species_name(i)),
+            literal(attr.initial.unwrap_or(0.0)),
+            attr.is_fixed(),
+            attr.constant
+        ));
+    }
+    // eliminated species are boundary species set by rules, so that
+    // reactions can still refer to them
+    for (id, d) in &observables {
+        let Some(e) = eliminated(&d.name) else {
+            continue;
+        };
+        let compartment = match e.attributes.compartment {
+            Some(c) => ids.compartments[c].as_str(),
+            None => DEFAULT_COMPARTMENT,
+        };
+        out.push_str(&format!(
+            "      <species id=\"{id}\" name=\"{}\" compartment=\"{compartment}\" hasOnlySubstanceUnits=\"false\" boundaryCondition=\"true\" constant=\"false\"/>\n",
+            escape(&d.name)
+        ));
+    }
+    out.push_str("    </listOfSpecies>\n");
+
+    // parameters, then other observables as variable parameters set by rules
+    let variables: Vec<_> = observables
+        .iter()
+        .filter(|(_, d)| eliminated(&d.name).is_none())
+        .collect();
+    if network.num_parameters() > 0 || !variables.is_empty() {
+        out.push_str("    <listOfParameters>\n");
+        for (j, value) in network.parameter_values().into_iter().enumerate() {
+            out.push_str(&format!(
+                "      <parameter id=\"{}\" name=\"{}\" value=\"{}\" constant=\"true\"/>\n",
+                ids.parameters[j],
+                escape(network.parameter_name(j)),
+                literal(value)
+            ));
+        }
+        for (id, d) in &variables {
+            out.push_str(&format!(
+                "      <parameter id=\"{id}\" name=\"{}\" constant=\"false\"/>\n",
+                escape(&d.name)
+            ));
+        }
+        out.push_str("    </listOfParameters>\n");
+    }
+    if !observables.is_empty() {
+        out.push_str("    <listOfRules>\n");
+        for (id, d) in &observables {
+            let mut math = String::new();
+            mathml(&network.expand(&d.value), &ids, &mut math);
+            out.push_str(&format!("      <assignmentRule variable=\"{id}\">\n"));
+            out.push_str(&format!(
+                "        <math xmlns=\"http://www.w3.org/1998/Math/MathML\">{math}</math>\n"
+            ));
+            out.push_str("      </assignmentRule>\n");
+        }
+        out.push_str("    </listOfRules>\n");
+    }
+
+    // reactions
+    if network.num_reactions() > 0 {
+        out.push_str("    <listOfReactions>\n");
+    }
+    for (j, rxn) in network.reactions().iter().enumerate() {
+        let name = network.reaction_name(j);
+        let mut attributes = format!(
+            "id=\"{}\" name=\"{}\" reversible=\"{}\"",
+            ids.fresh(&name),
+            escape(&name),
+            rxn.is_reversible()
+        );
+        if let Some(c) = network.reaction_compartment(j) {
+            attributes.push_str(&format!(" compartment=\"{}\"", ids.compartments[c]));
+        }
+        out.push_str(&format!("      <reaction {attributes}>\n"));
+        let eliminated_terms = |side: fn(&EliminatedSpecies) -> &[(usize, StoichCoef)]| {
+            observables
+                .iter()
+                .filter_map(|(id, d)| Some((id.as_str(), eliminated(&d.name)?)))
+                .filter_map(|(id, e)| {
+                    let (_, c) = side(e).iter().find(|(k, _)| *k == j)?;
+                    Some((id, *c))
+                })
+                .collect::<Vec<_>>()
+        };
+        let reactants = eliminated_terms(|e| &e.reactants);
+        let products = eliminated_terms(|e| &e.products);
+        species_references(
+            "listOfReactants",
+            rxn.get_reactants(),
+            &reactants,
+            &ids,
+            &mut out,
+        );
+        species_references(
+            "listOfProducts",
+            rxn.get_products(),
+            &products,
+            &ids,
+            &mut out,
+        );
+
+        // species in the rate law that are neither consumed nor produced
+        let rate = network.rate_law(j);
+        let modifiers: BTreeSet<usize> = rate
+            .symbols()
+            .iter()
+            .filter_map(|s| network.find_species(s))
+            .filter(|&i| {
+                rxn.get_reactants().coefficient(i).is_zero()
+                    && rxn.get_products().coefficient(i).is_zero()
+            })
+            .collect();
+        if !modifiers.is_empty() {
+            out.push_str("        <listOfModifiers>\n");
+            for i in modifiers {
+                out.push_str(&format!(
+                    "          <modifierSpeciesReference species=\"{}\"/>\n",
+                    ids.species[i]
+                ));
+            }
+            out.push_str("        </listOfModifiers>\n");
+        }
+
+        // kinetic laws are in amount per time
+        let law = match network.reaction_compartment(j) {
+            Some(c) => Expr::binary(
+                BinaryOp::Mul,
+                Expr::symbol(network.compartment_name(c)),
+                rate,
+            ),
+            None => rate,
+        };
+        let mut math = String::new();
+        mathml(&law, &ids, &mut math);
+        out.push_str("        <kineticLaw>\n");
+        out.push_str(&format!(
+            "          <math xmlns=\"http://www.w3.org/1998/Math/MathML\">{math}</math>\n"
+        ));
+        out.push_str("        </kineticLaw>\n");
+        out.push_str("      </reaction>\n");
+    }
+    if network.num_reactions() > 0 {
+        out.push_str("    </listOfReactions>\n");
+    }
+
+    out.push_str("  </model>\n");
+    out.push_str("</sbml>\n");
+    out
+}
@@ -1,4 +1,8 @@
+pub mod matrix;
+pub mod rational;
 pub mod registry;
+pub use matrix::Matrix;
+pub use rational::{Overflow, Rational};
 pub use registry::Registry;
 
 //pub mod free_vector;
@@ -0,0 +1,332 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+// exact fraction num/den in lowest terms with den > 0
+//
+// Coefficients read from a model fit in 64 bits, but exact elimination on
+// them can grow numerators and denominators quickly. Values are stored in
+// 128 bits, and the checked operations report when even that is exceeded.
+// The operators panic on overflow and are meant for sums and products of a
+// few coefficients, such as building a stoichiometric matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+// a result of exact arithmetic does not fit in 128 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "coefficient too large for exact arithmetic")
+    }
+}
+
+impl Error for Overflow {}
+
+// of values above i128::MIN, which no rational holds
+pub fn gcd(mut a: i128, mut b: i128) -> i128 {
+    a = a.abs();
+    b = b.abs();
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational { num: 0, den: 1 };
+    pub const ONE: Rational = Rational { num: 1, den: 1 };
+
+    pub fn new(num: i64, den: i64) -> Self {
+        Self::reduce(num as i128, den as i128).expect("64-bit fractions fit in 128 bits")
+    }
+
+    // num/den in lowest terms, or None if it does not fit
+    pub fn checked_new(num: i128, den: i128) -> Option<Self> {
+        Self::reduce(num, den)
+    }
+
+    // normalize num/den, or None if it does not fit; i128::MIN is excluded
+    // so that every value can be negated
+    fn reduce(num: i128, den: i128) -> Option<Self> {
+        assert!(den != 0, "rational with zero denominator");
+        if num == i128::MIN || den == i128::MIN {
+            return None;
+        }
+        let g = gcd(num, den).max(1);
+        let (num, den) = (num / g, den / g);
+        if den < 0 {
+            Some(Self {
+                num: num.checked_neg()?,
+                den: den.checked_neg()?,
+            })
+        } else {
+            Some(Self { num, den })
+        }
+    }
+
+    pub fn integer(n: i64) -> Self {
+        Self {
+            num: n as i128,
+            den: 1,
+        }
+    }
+
+    // closest fraction with a small denominator, e.g. 0.1 -> 1/10
+    pub fn from_f64(x: f64) -> Option<Self> {
+        if !x.is_finite() {
+            return None;
+        }
+        let tolerance = 1e-12 * x.abs().max(1.0);
+        let (mut h0, mut h1) = (0i128, 1i128);
+        let (mut k0, mut k1) = (1i128, 0i128);
+        let mut r = x;
+        for _ in 0..64 {
+            let a = r.floor();
+            if a.abs() > 1e15 {
+                return None;
+            }
+            let a = a as i128;
+            (h0, h1) = (h1, a * h1 + h0);
+            (k0, k1) = (k1, a * k1 + k0);
+            if k1 > i64::MAX as i128 || h1.abs() > i64::MAX as i128 {
+                return None;
+            }
+            if (h1 as f64 / k1 as f64 - x).abs() <= tolerance {
+                return Self::reduce(h1, k1);
+            }
+            r = 1.0 / (r - a as f64);
+        }
+        None
+    }
+
+    pub fn numer(&self) -> i128 {
+        self.num
+    }
+
+    pub fn denom(&self) -> i128 {
+        self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            num: self.num.abs(),
+            den: self.den,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    // sums and differences over the least common denominator
+    pub fn checked_add(self, o: Rational) -> Option<Rational> {
+        let g = gcd(self.den, o.den);
+        let (a, b) = (self.den / g, o.den / g);
+        let num = self
+            .num
+            .checked_mul(b)?
+            .checked_add(o.num.checked_mul(a)?)?;
+        Self::reduce(num, self.den.checked_mul(b)?)
+    }
+
+    pub fn checked_sub(self, o: Rational) -> Option<Rational> {
+        self.checked_add(o.checked_neg()?)
+    }
+
+    // products with common factors cancelled first
+    pub fn checked_mul(self, o: Rational) -> Option<Rational> {
+        let g1 = gcd(self.num, o.den).max(1);
+        let g2 = gcd(o.num, self.den).max(1);
+        let num = (self.num / g1).checked_mul(o.num / g2)?;
+        let den = (self.den / g2).checked_mul(o.den / g1)?;
+        Self::reduce(num, den)
+    }
+
+    pub fn checked_div(self, o: Rational) -> Option<Rational> {
+        assert!(!o.is_zero(), "rational division by zero");
+        self.checked_mul(Self::reduce(o.den, o.num)?)
+    }
+
+    pub fn checked_neg(self) -> Option<Rational> {
+        Some(Self {
+            num: self.num.checked_neg()?,
+            den: self.den,
+        })
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Rational::ZERO
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Self {
+        Rational::integer(n)
+    }
+}
+
+// integers beyond i64 are rejected when a model is read
+impl TryFrom<u64> for Rational {
+    type Error = Overflow;
+    fn try_from(n: u64) -> Result<Self, Overflow> {
+        let n = i64::try_from(n).map_err(|_| Overflow)?;
+        Ok(Rational::integer(n))
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+    fn add(self, o: Rational) -> Rational {
+        self.checked_add(o).expect("rational overflow")
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+    fn sub(self, o: Rational) -> Rational {
+        self.checked_sub(o).expect("rational overflow")
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+    fn mul(self, o: Rational) -> Rational {
+        self.checked_mul(o).expect("rational overflow")
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+    fn div(self, o: Rational) -> Rational {
+        self.checked_div(o).expect("rational overflow")
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+    fn neg(self) -> Rational {
+        self.checked_neg().expect("rational overflow")
+    }
+}
+
+impl AddAssign for Rational {
+    fn add_assign(&mut self, o: Rational) {
+        *self = *self + o;
+    }
+}
+
+impl SubAssign for Rational {
+    fn sub_assign(&mut self, o: Rational) {
+        *self = *self - o;
+    }
+}
+
+impl Sum for Rational {
+    fn sum<I: Iterator<Item = Rational>>(iter: I) -> Rational {
+        iter.fold(Rational::ZERO, |a, b| a + b)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, o: &Self) -> Ordering {
+        // compare the integer parts first so that cross products stay small
+        let (a, b) = (self.num.div_euclid(self.den), o.num.div_euclid(o.den));
+        if a != b {
+            return a.cmp(&b);
+        }
+        let (r, s) = (self.num.rem_euclid(self.den), o.num.rem_euclid(o.den));
+        match (r.checked_mul(o.den), s.checked_mul(self.den)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            _ => (r as f64 / self.den as f64).total_cmp(&(s as f64 / o.den as f64)),
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, o: &Self) -> Option<Ordering> {
+        Some(self.cmp(o))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduces_to_lowest_terms_with_positive_denominator() {
+        let r = Rational::new(6, -4);
+        assert_eq!((r.numer(), r.denom()), (-3, 2));
+        assert_eq!(Rational::new(0, -7), Rational::ZERO);
+        assert_eq!(
+            Rational::new(2, 4) + Rational::new(1, 3),
+            Rational::new(5, 6)
+        );
+    }
+
+    #[test]
+    fn checked_operations_report_overflow() {
+        let big = Rational::checked_new(i128::MAX, 1).unwrap();
+        assert_eq!(big.checked_add(Rational::ONE), None);
+        assert_eq!(big.checked_mul(Rational::integer(2)), None);
+        assert_eq!(Rational::checked_new(i128::MIN, -1), None);
+        // cancelling before multiplying keeps representable products exact
+        let half = Rational::new(1, 2);
+        assert_eq!(
+            Rational::checked_new(i128::MAX - 1, 1)
+                .unwrap()
+                .checked_mul(half),
+            Rational::checked_new(i128::MAX / 2, 1)
+        );
+    }
+
+    #[test]
+    fn coefficients_beyond_64_bits_are_rejected() {
+        assert_eq!(
+            Rational::try_from(1u64 << 62),
+            Ok(Rational::integer(1 << 62))
+        );
+        assert_eq!(Rational::try_from(1u64 << 63), Err(Overflow));
+    }
+
+    #[test]
+    fn compares_without_overflow() {
+        let a = Rational::checked_new(i128::MAX, i128::MAX - 1).unwrap();
+        let b = Rational::checked_new(i128::MAX - 1, i128::MAX - 2).unwrap();
+        assert!(a < b);
+        assert!(Rational::new(-1, 3) < Rational::new(-1, 4));
+    }
+
+    #[test]
+    fn decimals_become_small_fractions() {
+        assert_eq!(Rational::from_f64(0.1), Some(Rational::new(1, 10)));
+        assert_eq!(Rational::from_f64(-2.5), Some(Rational::new(-5, 2)));
+        assert_eq!(Rational::from_f64(f64::NAN), None);
+    }
+}
@@ -2,27 +2,27 @@ use std::collections::HashMap;
 use std::hash::Hash;
 // data structure for labeling a set with indices
 // used for symbol table and species registry
-#[derive(Debug)]
-pub struct Registry<T>
+// each symbol carries attributes, e.g. initial conditions of a species
+#[derive(Debug, Default)]
+pub struct Registry<T, A = ()>
 where
     T: Eq + Hash + Clone,
 {
     index_map: HashMap<T, usize>,
     symbols: Vec<T>, // ??? make std::rc::Rc<String> instead of String ???
-                     //attributes: Vec<T>,
+    attributes: Vec<A>,
 }
 
-impl<T: Eq + Hash + Clone> Registry<T> {
+impl<T: Eq + Hash + Clone, A: Default> Registry<T, A> {
     pub fn new() -> Self {
         Self {
             index_map: HashMap::new(),
             symbols: Vec::new(),
-            //attributes : Vec::new(),
+            attributes: Vec::new(),
         }
     }
 
-    pub fn register(&mut self, symbol: T) -> usize
-where {
+    pub fn register(&mut self, symbol: T) -> usize {
         if let Some(&id) = self.index_map.get(&symbol) {
             return id;
         }
@@ -31,6 +31,7 @@ where {
 
         self.index_map.insert(symbol.clone(), new_id);
         self.symbols.push(symbol);
+        self.attributes.push(A::default());
 
         new_id
     }
@@ -38,4 +39,28 @@ where {
     pub fn get_symbol(&self, idx: usize) -> &T {
         &self.symbols[idx]
     }
+
+    pub fn get_index(&self, symbol: &T) -> Option<usize> {
+        self.index_map.get(symbol).copied()
+    }
+
+    pub fn get_attributes(&self, idx: usize) -> &A {
+        &self.attributes[idx]
+    }
+
+    pub fn get_attributes_mut(&mut self, idx: usize) -> &mut A {
+        &mut self.attributes[idx]
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn symbols(&self) -> &[T] {
+        &self.symbols
+    }
 }
@@ -1,3 +1,4 @@
 pub mod grammar;
 pub mod parser;
 pub mod scanner;
+pub mod writer;
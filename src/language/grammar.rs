@@ -1,7 +1,5 @@
-use std::error::Error;
-
 // Terminal Symbols
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Terminal {
     LeftParen,
     RightParen,
@@ -23,8 +21,29 @@ pub enum Terminal {
     LeftRightArrow,
     Tick,
     Comma,
+    EmptySet,
+    Caret,
+    Tilde,
+    At,
     Identifier(String),
     Number(u64),
+    Real(f64),
+    // keywords
+    Flux,
+    Maximize,
+    Minimize,
+    Species,
+    Param,
+    Compartment,
+    When,
+    Then,
+    // the keyword `at`, as opposed to `@`
+    AtTime,
+    Rule,
+    Observable,
+    Formula,
+    Allow,
+    Deny,
 }
 
 impl Terminal {
@@ -32,6 +51,10 @@ impl Terminal {
         matches!(*self, Terminal::Number(_))
     }
 
+    pub fn is_numeric(&self) -> bool {
+        matches!(*self, Terminal::Number(_) | Terminal::Real(_))
+    }
+
     pub fn is_identifier(&self) -> bool {
         matches!(*self, Terminal::Identifier(_))
     }
@@ -43,6 +66,14 @@ impl Terminal {
         }
     }
 
+    pub fn get_value(self) -> Option<f64> {
+        match self {
+            Terminal::Number(n) => Some(n as f64),
+            Terminal::Real(x) => Some(x),
+            _ => None,
+        }
+    }
+
     pub fn get_identifier(self) -> Option<String> {
         match self {
             Terminal::Identifier(s) => Some(s),
@@ -51,12 +82,30 @@ impl Terminal {
     }
 }
 
-pub fn is_yield_symbol(s: &Terminal) -> bool {
-    match s {
-        Terminal::RightArrow => true,
-        Terminal::LeftArrow => true,
-        Terminal::LeftRightArrow => true,
-        Terminal::Equal => true,
-        _ => false,
+// reserved words; quote an identifier to use one as a species name
+pub fn keyword(lexeme: &str) -> Option<Terminal> {
+    match lexeme {
+        "flux" => Some(Terminal::Flux),
+        "maximize" => Some(Terminal::Maximize),
+        "minimize" => Some(Terminal::Minimize),
+        "species" => Some(Terminal::Species),
+        "param" => Some(Terminal::Param),
+        "compartment" => Some(Terminal::Compartment),
+        "when" => Some(Terminal::When),
+        "then" => Some(Terminal::Then),
+        "at" => Some(Terminal::AtTime),
+        "rule" => Some(Terminal::Rule),
+        "observable" => Some(Terminal::Observable),
+        "formula" => Some(Terminal::Formula),
+        "allow" => Some(Terminal::Allow),
+        "deny" => Some(Terminal::Deny),
+        _ => None,
     }
 }
+
+pub fn is_yield_symbol(s: &Terminal) -> bool {
+    matches!(
+        s,
+        Terminal::RightArrow | Terminal::LeftArrow | Terminal::LeftRightArrow | Terminal::Equal
+    )
+}
@@ -6,8 +6,14 @@ use crate::language::grammar;
 use crate::language::grammar::Terminal;
 use crate::language::scanner::{LexError, LineNum, Scanner};
 
+use crate::data::Overflow;
+
 // import reaction network
-use crate::network::{Complex, Network, Reaction};
+use crate::network::{
+    AssignOp, Assignment, BinaryOp, ChemicalFormula, Comparison, Complex, Definition,
+    DefinitionError, Event, Expr, FluxBound, Function, LintLevel, Network, ParameterAttributes,
+    Prior, Reaction, Sense, SpeciesAttributes, SpeciesId, StoichCoef, Trigger,
+};
 
 // Errors for syntax analysis
 #[derive(Debug)]
@@ -41,6 +47,7 @@ impl Error for SyntaxError {}
 pub enum ParseError {
     Lex(LexError),
     Syntax(SyntaxError),
+    Definition(DefinitionError),
     UnexpectedEOF,
 }
 
@@ -49,7 +56,8 @@ impl fmt::Display for ParseError {
         match self {
             ParseError::Lex(e) => write!(f, "Scanning Error: {}", e),
             ParseError::Syntax(e) => write!(f, "Syntax Error: {}", e),
-            ParseError::UnexpectedEOF => write!(f, "Unexpected enf of input"),
+            ParseError::Definition(e) => write!(f, "Definition Error: {}", e),
+            ParseError::UnexpectedEOF => write!(f, "Unexpected end of input"),
         }
     }
 }
@@ -62,6 +70,12 @@ impl From<LexError> for ParseError {
     }
 }
 
+impl From<DefinitionError> for ParseError {
+    fn from(e: DefinitionError) -> Self {
+        ParseError::Definition(e)
+    }
+}
+
 impl From<SyntaxError> for ParseError {
     fn from(e: SyntaxError) -> Self {
         ParseError::Syntax(e)
@@ -129,9 +143,8 @@ impl<'a> Parser<'a> {
 
     // check if next token satisfies predicate
     fn peek_if(&mut self, predicate: impl FnOnce(&Terminal) -> bool) -> bool {
-        let m = self.peek_token();
         if let Ok(Some(token)) = self.peek_token() {
-            predicate(&token)
+            predicate(token)
         } else {
             false
         }
@@ -158,84 +171,648 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn emit_error<S, E>(msg: S) -> Result<E, ParseError>
+    // consume the expected token or fail with a syntax error
+    fn expect(&mut self, symbol: Terminal, msg: &str) -> Result<(), ParseError> {
+        if self.next_if_match(symbol)?.is_some() {
+            Ok(())
+        } else {
+            self.emit_error(msg)
+        }
+    }
+
+    fn emit_error<S, E>(&self, msg: S) -> Result<E, ParseError>
     where
         S: Into<String> + AsRef<str>,
     {
-        let e = SyntaxError::new(msg);
+        let mut e = SyntaxError::new(msg);
+        e.line = self.scanner.get_line_num();
         Err(ParseError::Syntax(e))
     }
 
     // build CRN from recursiving descent parsing
     pub fn parse(&mut self) -> Result<Network, ParseError> {
-        let crn = Network::new();
-        self.reaction_list(crn)?;
+        let mut crn = Network::new();
+        self.parse_into(&mut crn)?;
         Ok(crn)
     }
 
+    // add the statements of another source, e.g. a side file of flux bounds
+    pub fn parse_into(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        self.statement_list(crn)?;
+        crn.order_definitions()?;
+        crn.resolve_parameters();
+        Ok(())
+    }
+
     // grammar productions for recursive descent
-    fn reaction_list(&mut self, crn: &mut Network) -> Result<(), ParseError> {
-        println!("Deriving reaction_list");
-        let rxn = self.reaction(crn)?;
-        crn.add_reaction(rxn);
-        self.next_reaction(crn)?;
+    fn statement_list(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        // skip blank lines
+        while self.advance_if_match(Terminal::SemiColon) {}
+        if self.peek_token()?.is_none() {
+            return Ok(());
+        }
+        self.statement(crn)?;
+        self.next_statement(crn)
+    }
+
+    fn next_statement(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        if self.advance_if_match(Terminal::SemiColon) {
+            self.statement_list(crn)
+        } else if self.peek_token()?.is_none() {
+            Ok(())
+        } else {
+            self.emit_error("Expected newline or ';' but found unexpected")
+        }
+    }
+
+    fn statement(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        if self.advance_if_match(Terminal::Flux) {
+            self.flux_bound(crn)
+        } else if self.advance_if_match(Terminal::Param) {
+            self.param_block(crn)
+        } else if self.advance_if_match(Terminal::Compartment) {
+            self.compartment_block(crn)
+        } else if self.advance_if_match(Terminal::Species) {
+            self.species_block(crn)
+        } else if self.advance_if_match(Terminal::Maximize) {
+            self.objective(crn, Sense::Maximize)
+        } else if self.advance_if_match(Terminal::Minimize) {
+            self.objective(crn, Sense::Minimize)
+        } else if self.advance_if_match(Terminal::When) {
+            self.when_event(crn)
+        } else if self.advance_if_match(Terminal::AtTime) {
+            self.scheduled_event(crn)
+        } else if self.advance_if_match(Terminal::Rule) {
+            self.definition(crn, false)
+        } else if self.advance_if_match(Terminal::Observable) {
+            self.definition(crn, true)
+        } else if self.advance_if_match(Terminal::Formula) {
+            self.formula_block(crn)
+        } else if self.advance_if_match(Terminal::Allow) {
+            self.lint_levels(crn, LintLevel::Allow)
+        } else if self.advance_if_match(Terminal::Deny) {
+            self.lint_levels(crn, LintLevel::Deny)
+        } else {
+            let rxn = self.reaction(crn)?;
+            crn.add_reaction(rxn);
+            Ok(())
+        }
+    }
+
+    // flux name [lower, upper]
+    fn flux_bound(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        let reaction = self.identifier("Expected reaction name after 'flux'")?;
+        self.expect(Terminal::LeftBracket, "Expected '[' before flux bounds")?;
+        let lower = self.bound()?;
+        self.expect(Terminal::Comma, "Expected ',' between flux bounds")?;
+        let upper = self.bound()?;
+        self.expect(Terminal::RightBracket, "Expected ']' after flux bounds")?;
+        if lower > upper {
+            return self.emit_error(format!(
+                "Lower flux bound of '{reaction}' exceeds its upper bound"
+            ));
+        }
+        crn.add_flux_bound(FluxBound {
+            reaction,
+            lower,
+            upper,
+        });
         Ok(())
     }
 
-    fn reaction(&mut self, crn: &mut Network) -> Result<Reaction, ParseError> {
-        println!("Deriving reaction");
-        let left = self.complex(crn)?;
-        let y = self.yield_symbol()?;
-        let right = self.complex(crn)?;
-        let out = match y {
-            Terminal::RightArrow => Ok(Reaction::forward(left, right)),
-            Terminal::LeftArrow => Ok(Reaction::forward(right, left)),
-            Terminal::LeftRightArrow => Ok(Reaction::reversible(left, right)),
-            Terminal::Equal => Ok(Reaction::reversible(left, right)),
-            _ => panic!("`yield_symbol()` returned terminal that was not an arrow."),
-        };
+    // allow code, code, ... | deny code, code, ...
+    fn lint_levels(&mut self, crn: &mut Network, level: LintLevel) -> Result<(), ParseError> {
+        let code = self.identifier("Expected lint code")?;
+        crn.set_lint_level(code, level);
+        if self.advance_if_match(Terminal::Comma) {
+            self.lint_levels(crn, level)
+        } else {
+            Ok(())
+        }
+    }
 
-        out
+    // maximize name | minimize name
+    fn objective(&mut self, crn: &mut Network, sense: Sense) -> Result<(), ParseError> {
+        let reaction = self.identifier("Expected reaction name for objective")?;
+        crn.set_objective(reaction, sense);
+        Ok(())
     }
 
-    fn next_reaction(&mut self, crn: &mut Network) -> Result<(), ParseError> {
-        println!("Deriving next_reaction");
-        if self.advance_if_match(Terminal::SemiColon) {
-            if self.peek_if(|x| *x != Terminal::SemiColon) {
-                let rxn = self.reaction(crn)?;
-                crn.add_reaction(rxn);
+    // species { E = 1.0 uM; S = 100 uM [constant] } or a single `species E = 1.0`
+    fn species_block(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        if self.advance_if_match(Terminal::LeftBrace) {
+            self.species_entries(crn)
+        } else {
+            self.species_declaration(crn)
+        }
+    }
+
+    fn species_entries(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        while self.advance_if_match(Terminal::SemiColon) {}
+        if self.advance_if_match(Terminal::RightBrace) {
+            return Ok(());
+        }
+        self.species_declaration(crn)?;
+        if self.advance_if_match(Terminal::SemiColon) || self.peek_if_match(Terminal::RightBrace) {
+            self.species_entries(crn)
+        } else {
+            self.emit_error("Expected ';', newline or '}' after species declaration")
+        }
+    }
+
+    // name [= value [unit]] [[flag, ...]] [: description]
+    fn species_declaration(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        let name = self.identifier("Expected species name")?;
+        let id = self.species_id(crn, name)?;
+        let mut attr = crn.species_attributes(id).clone();
+
+        if self.advance_if_match(Terminal::Equal) {
+            let initial = self.signed_value()?;
+            if initial < 0.0 {
+                return self.emit_error(format!(
+                    "Initial condition of '{}' must be non-negative",
+                    crn.species_name(id)
+                ));
             }
-            let next = self.peek_token()?;
-            if next.is_some() {
-                self.next_reaction(crn)?;
+            attr.initial = Some(initial);
+            if let Some(Terminal::Identifier(unit)) = self.next_if(|x| x.is_identifier())? {
+                attr.unit = Some(unit);
             }
+        }
+        if self.advance_if_match(Terminal::LeftBracket) {
+            self.species_flags(&mut attr)?;
+        }
+        if self.advance_if_match(Terminal::Colon) {
+            attr.description = Some(self.identifier("Expected a quoted description after ':'")?);
+        }
+
+        *crn.species_attributes_mut(id) = attr;
+        Ok(())
+    }
+
+    fn species_flags(&mut self, attr: &mut SpeciesAttributes) -> Result<(), ParseError> {
+        let flag = self.identifier("Expected species attribute")?;
+        match flag.as_str() {
+            "constant" => attr.constant = true,
+            "boundary" => attr.boundary = true,
+            _ => return self.emit_error(format!("Unknown species attribute '{flag}'")),
+        }
+        if self.advance_if_match(Terminal::Comma) {
+            self.species_flags(attr)
+        } else {
+            self.expect(
+                Terminal::RightBracket,
+                "Expected ']' after species attributes",
+            )
+        }
+    }
+
+    // formula { H2O : H2 O; "NH4(+)" : N H4 +1 } or a single `formula H2O : H2 O`
+    fn formula_block(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        if self.advance_if_match(Terminal::LeftBrace) {
+            self.formula_entries(crn)
+        } else {
+            self.formula_declaration(crn)
+        }
+    }
+
+    fn formula_entries(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        while self.advance_if_match(Terminal::SemiColon) {}
+        if self.advance_if_match(Terminal::RightBrace) {
+            return Ok(());
+        }
+        self.formula_declaration(crn)?;
+        if self.advance_if_match(Terminal::SemiColon) || self.peek_if_match(Terminal::RightBrace) {
+            self.formula_entries(crn)
+        } else {
+            self.emit_error("Expected ';', newline or '}' after formula declaration")
+        }
+    }
+
+    // species : part+ [(+|-) [charge]], where each part is a formula such as
+    // `H4`, `C6H12O6` or `"SO4(2-)"`
+    fn formula_declaration(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        let name = self.identifier("Expected species name after 'formula'")?;
+        let id = self.species_id(crn, name)?;
+        self.expect(Terminal::Colon, "Expected ':' before formula")?;
+        let mut formula = ChemicalFormula::default();
+        let mut parts = 0;
+        while let Some(Terminal::Identifier(part)) = self.next_if(|x| x.is_identifier())? {
+            let Some(parsed) = ChemicalFormula::parse(&part) else {
+                return self.emit_error(format!("'{part}' is not a chemical formula"));
+            };
+            formula.add(&parsed);
+            parts += 1;
+        }
+        if parts == 0 {
+            return self.emit_error("Expected elements of formula");
+        }
+        let sign = if self.advance_if_match(Terminal::Plus) {
+            Some(1)
+        } else if self.advance_if_match(Terminal::Minus) {
+            Some(-1)
+        } else {
+            None
+        };
+        if let Some(sign) = sign {
+            let magnitude = match self.next_if(|x| x.is_number())? {
+                Some(n) => n.get_number() as i64,
+                None => 1,
+            };
+            formula.charge += sign * magnitude;
+        }
+        crn.species_attributes_mut(id).formula = Some(formula);
+        Ok(())
+    }
+
+    // compartment { chloroplast = 1.2; cytosol = 3.0 } or
+    // compartment chloroplast = 1.2, cytosol = 3.0
+    fn compartment_block(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        if self.advance_if_match(Terminal::LeftBrace) {
+            self.compartment_entries(crn)
+        } else {
+            self.compartment_list(crn)
+        }
+    }
+
+    fn compartment_list(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        self.compartment_declaration(crn)?;
+        if self.advance_if_match(Terminal::Comma) {
+            self.compartment_list(crn)
+        } else {
             Ok(())
+        }
+    }
+
+    fn compartment_entries(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        while self.advance_if_match(Terminal::SemiColon) {}
+        if self.advance_if_match(Terminal::RightBrace) {
+            return Ok(());
+        }
+        self.compartment_declaration(crn)?;
+        if self.advance_if_match(Terminal::SemiColon)
+            || self.advance_if_match(Terminal::Comma)
+            || self.peek_if_match(Terminal::RightBrace)
+        {
+            self.compartment_entries(crn)
+        } else {
+            self.emit_error("Expected ';', newline or '}' after compartment declaration")
+        }
+    }
+
+    // name [= size [unit]] [: description]
+    fn compartment_declaration(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        let name = self.identifier("Expected compartment name")?;
+        let id = crn.register_compartment(name);
+        let mut attr = crn.compartment_attributes(id).clone();
+        if self.advance_if_match(Terminal::Equal) {
+            // numbers are finite, so the size need only be positive
+            let size = self.signed_value()?;
+            if size <= 0.0 {
+                return self.emit_error(format!(
+                    "Size of compartment '{}' must be positive",
+                    crn.compartment_name(id)
+                ));
+            }
+            attr.size = Some(size);
+            if let Some(Terminal::Identifier(unit)) = self.next_if(|x| x.is_identifier())? {
+                attr.unit = Some(unit);
+            }
+        }
+        if self.advance_if_match(Terminal::Colon) {
+            attr.description = Some(self.identifier("Expected a quoted description after ':'")?);
+        }
+        *crn.compartment_attributes_mut(id) = attr;
+        Ok(())
+    }
+
+    // species name with an optional `@compartment`
+    fn species_id(&mut self, crn: &mut Network, name: String) -> Result<SpeciesId, ParseError> {
+        if self.advance_if_match(Terminal::At) {
+            let compartment = self.identifier("Expected compartment name after '@'")?;
+            Ok(crn.register_species_in(format!("{name}@{compartment}"), compartment))
+        } else {
+            Ok(crn.register_species(name))
+        }
+    }
+
+    // rule name = expression, or observable name = expression
+    fn definition(&mut self, crn: &mut Network, observable: bool) -> Result<(), ParseError> {
+        let name = self.identifier("Expected name of rule or observable")?;
+        self.expect(
+            Terminal::Equal,
+            "Expected '=' after name of rule or observable",
+        )?;
+        let value = self.expression(crn)?;
+        crn.add_definition(Definition {
+            name,
+            value,
+            observable,
+        });
+        Ok(())
+    }
+
+    // when expression comparison expression then assignments
+    fn when_event(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        let left = self.expression(crn)?;
+        let comparison = if self.advance_if_match(Terminal::Less) {
+            if self.advance_if_match(Terminal::Equal) {
+                Comparison::LessEqual
+            } else {
+                Comparison::Less
+            }
+        } else if self.advance_if_match(Terminal::Greater) {
+            if self.advance_if_match(Terminal::Equal) {
+                Comparison::GreaterEqual
+            } else {
+                Comparison::Greater
+            }
+        } else {
+            return self.emit_error("Expected '<', '<=', '>' or '>=' in event condition");
+        };
+        let right = self.expression(crn)?;
+        self.no_time(crn, &left)?;
+        self.no_time(crn, &right)?;
+        if !self.advance_if_match(Terminal::Then) {
+            self.expect(Terminal::Colon, "Expected 'then' after event condition")?;
+        }
+        let assignments = self.assignments(crn)?;
+        crn.add_event(Event {
+            trigger: Trigger::When(left, comparison, right),
+            assignments,
+        });
+        Ok(())
+    }
+
+    // at [t =] time [, time ...] : assignments
+    fn scheduled_event(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        if self
+            .next_if(|x| matches!(x, Terminal::Identifier(s) if s == "t" || s == "time"))?
+            .is_some()
+        {
+            self.expect(Terminal::Equal, "Expected '=' after 't'")?;
+        }
+        let mut times = vec![self.signed_value()?];
+        while self.advance_if_match(Terminal::Comma) {
+            times.push(self.signed_value()?);
+        }
+        if times.iter().any(|t| *t < 0.0 || !t.is_finite()) {
+            return self.emit_error("Event times must be finite and non-negative");
+        }
+        if !self.advance_if_match(Terminal::Then) {
+            self.expect(Terminal::Colon, "Expected ':' after event times")?;
+        }
+        let assignments = self.assignments(crn)?;
+        crn.add_event(Event {
+            trigger: Trigger::At(times),
+            assignments,
+        });
+        Ok(())
+    }
+
+    // events see the state but not the time, so an undeclared `t` or `time`
+    // would silently be a parameter
+    fn no_time(&self, crn: &Network, expr: &Expr) -> Result<(), ParseError> {
+        for name in ["t", "time"] {
+            let declared = crn.find_species(name).is_some() || crn.find_parameter(name).is_some();
+            if !declared && expr.symbols().contains(name) {
+                return self.emit_error(format!(
+                    "Event expressions cannot use the time '{name}'; use 'at {name} = ...' for events at given times"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // name (= | += | -=) expression [, ...]
+    fn assignments(&mut self, crn: &mut Network) -> Result<Vec<Assignment>, ParseError> {
+        let mut assignments = Vec::new();
+        loop {
+            let name = self.identifier("Expected species or parameter to assign")?;
+            let target = if self.peek_if_match(Terminal::At) {
+                let id = self.species_id(crn, name)?;
+                crn.species_name(id).to_string()
+            } else {
+                name
+            };
+            let op = if self.advance_if_match(Terminal::Plus) {
+                AssignOp::Add
+            } else if self.advance_if_match(Terminal::Minus) {
+                AssignOp::Subtract
+            } else {
+                AssignOp::Set
+            };
+            self.expect(Terminal::Equal, "Expected '=', '+=' or '-=' in assignment")?;
+            let value = self.expression(crn)?;
+            self.no_time(crn, &value)?;
+            assignments.push(Assignment { target, op, value });
+            if !self.advance_if_match(Terminal::Comma) {
+                return Ok(assignments);
+            }
+        }
+    }
+
+    // param { Km = 0.5 [0.01, 10] uM; Vmax = 2 } or a single `param Km = 0.5`
+    fn param_block(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        if self.advance_if_match(Terminal::LeftBrace) {
+            self.param_entries(crn)
+        } else {
+            self.param_declaration(crn)
+        }
+    }
+
+    fn param_entries(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        while self.advance_if_match(Terminal::SemiColon) {}
+        if self.advance_if_match(Terminal::RightBrace) {
+            return Ok(());
+        }
+        self.param_declaration(crn)?;
+        if self.advance_if_match(Terminal::SemiColon) || self.peek_if_match(Terminal::RightBrace) {
+            self.param_entries(crn)
+        } else {
+            self.emit_error("Expected ';', newline or '}' after parameter declaration")
+        }
+    }
+
+    // name [= value] [[lower, upper]] [unit] [~ prior(a, b)] [: description]
+    fn param_declaration(&mut self, crn: &mut Network) -> Result<(), ParseError> {
+        let name = self.identifier("Expected parameter name")?;
+        let mut attr = ParameterAttributes::default();
+
+        if self.advance_if_match(Terminal::Equal) {
+            attr.value = Some(self.signed_value()?);
+        }
+        if self.advance_if_match(Terminal::LeftBracket) {
+            let lower = self.bound()?;
+            self.expect(Terminal::Comma, "Expected ',' between parameter bounds")?;
+            let upper = self.bound()?;
+            self.expect(
+                Terminal::RightBracket,
+                "Expected ']' after parameter bounds",
+            )?;
+            if lower > upper {
+                return self.emit_error(format!(
+                    "Lower bound of parameter '{name}' exceeds its upper bound"
+                ));
+            }
+            if let Some(v) = attr.value
+                && (v < lower || v > upper)
+            {
+                return self.emit_error(format!(
+                    "Value of parameter '{name}' lies outside its bounds"
+                ));
+            }
+            attr.lower = Some(lower).filter(|x| x.is_finite());
+            attr.upper = Some(upper).filter(|x| x.is_finite());
+        }
+        if let Some(Terminal::Identifier(unit)) = self.next_if(|x| x.is_identifier())? {
+            attr.unit = Some(unit);
+        }
+        if self.advance_if_match(Terminal::Tilde) {
+            attr.prior = Some(self.prior()?);
+        }
+        if self.advance_if_match(Terminal::Colon) {
+            attr.description = Some(self.identifier("Expected a quoted description after ':'")?);
+        }
+
+        crn.declare_parameter(name, attr);
+        Ok(())
+    }
+
+    // normal(mean, sd) | lognormal(mu, sigma) | uniform(lower, upper)
+    fn prior(&mut self) -> Result<Prior, ParseError> {
+        let name = self.identifier("Expected prior distribution after '~'")?;
+        self.expect(Terminal::LeftParen, "Expected '(' after prior distribution")?;
+        let a = self.signed_value()?;
+        self.expect(Terminal::Comma, "Expected ',' between prior arguments")?;
+        let b = self.signed_value()?;
+        self.expect(Terminal::RightParen, "Expected ')' after prior arguments")?;
+        match Prior::from_name(&name, a, b) {
+            Some(prior) => Ok(prior),
+            None => self.emit_error(format!(
+                "Unknown prior '{name}'; expected normal, lognormal or uniform"
+            )),
+        }
+    }
+
+    fn identifier(&mut self, msg: &str) -> Result<String, ParseError> {
+        match self.next_if(|x| x.is_identifier())? {
+            Some(Terminal::Identifier(s)) => Ok(s),
+            _ => self.emit_error(msg),
+        }
+    }
+
+    // finite number with optional sign
+    fn signed_value(&mut self) -> Result<f64, ParseError> {
+        let sign = self.sign();
+        Ok(sign * self.finite_value()?)
+    }
+
+    // signed number of a flux or parameter bound, where `inf` is unbounded
+    fn bound(&mut self) -> Result<f64, ParseError> {
+        let sign = self.sign();
+        if self
+            .next_if(|x| matches!(x, Terminal::Identifier(s) if s == "inf"))?
+            .is_some()
+        {
+            return Ok(sign * f64::INFINITY);
+        }
+        Ok(sign * self.finite_value()?)
+    }
+
+    fn sign(&mut self) -> f64 {
+        if self.advance_if_match(Terminal::Minus) {
+            -1.0
         } else {
-            let msg = format!("Expected newline or ';' but found unexpected");
-            Self::emit_error(msg)
+            1.0
+        }
+    }
+
+    fn finite_value(&mut self) -> Result<f64, ParseError> {
+        match self.pop_token()? {
+            Some(t) if t.is_numeric() => match t.get_value().unwrap() {
+                value if value.is_finite() => Ok(value),
+                _ => self.emit_error("Expected a finite number"),
+            },
+            Some(Terminal::Identifier(s)) if s == "inf" => {
+                self.emit_error("Expected a finite number")
+            }
+            _ => self.emit_error("Expected a number"),
         }
     }
 
+    fn reaction(&mut self, crn: &mut Network) -> Result<Reaction, ParseError> {
+        // an identifier followed by ':' names the reaction
+        let mut name = None;
+        let left = if let Some(Terminal::Identifier(id)) = self.next_if(|x| x.is_identifier())? {
+            if self.advance_if_match(Terminal::Colon) {
+                name = Some(id);
+                self.complex(crn)?
+            } else {
+                let mut cplx = Complex::new();
+                let id = self.species_id(crn, id)?;
+                cplx.add_term(id, StoichCoef::ONE);
+                self.next_monomial(crn, &mut cplx)?;
+                cplx
+            }
+        } else {
+            self.complex(crn)?
+        };
+
+        let y = self.yield_symbol()?;
+        let right = self.complex(crn)?;
+        let mut rxn = match y {
+            Terminal::RightArrow => Reaction::forward(left, right),
+            Terminal::LeftArrow => Reaction::forward(right, left),
+            Terminal::LeftRightArrow => Reaction::reversible(left, right),
+            Terminal::Equal => Reaction::reversible(left, right),
+            _ => panic!("`yield_symbol()` returned terminal that was not an arrow."),
+        };
+        if let Some(name) = name {
+            rxn.set_name(name);
+        }
+        // kinetics annotation
+        if self.advance_if_match(Terminal::Colon) {
+            rxn.set_kinetics(self.expression(crn)?);
+        }
+        Ok(rxn)
+    }
+
     fn yield_symbol(&mut self) -> Result<Terminal, ParseError> {
-        println!("Deriving yield");
         let maybe_token = self.next_if(grammar::is_yield_symbol)?;
         if let Some(s) = maybe_token {
             Ok(s)
         } else {
-            Self::emit_error("Expected yield symbol '->', '<-', '<->' or '='")
+            self.emit_error("Expected yield symbol '->', '<-', '<->' or '='")
         }
     }
 
     fn complex(&mut self, crn: &mut Network) -> Result<Complex, ParseError> {
-        println!("Deriving complex");
         let mut cplx = Complex::new();
+        if self.empty_complex()? {
+            return Ok(cplx);
+        }
         self.monomial(crn, &mut cplx)?;
         self.next_monomial(crn, &mut cplx)?;
         Ok(cplx)
     }
 
+    // `0`, `∅` or `{}` for inflow and outflow reactions
+    fn empty_complex(&mut self) -> Result<bool, ParseError> {
+        if self.advance_if_match(Terminal::Number(0)) || self.advance_if_match(Terminal::EmptySet) {
+            Ok(true)
+        } else if self.advance_if_match(Terminal::LeftBrace) {
+            self.expect(
+                Terminal::RightBrace,
+                "Expected '}' to close the empty complex",
+            )?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     fn next_monomial(&mut self, crn: &mut Network, cplx: &mut Complex) -> Result<(), ParseError> {
-        println!("Deriving next_monomial");
         if self.advance_if_match(Terminal::Plus) {
             self.monomial(crn, cplx)?;
             self.next_monomial(crn, cplx)?;
@@ -244,33 +821,62 @@ impl<'a> Parser<'a> {
     }
 
     fn monomial(&mut self, crn: &mut Network, cplx: &mut Complex) -> Result<(), ParseError> {
-        println!("Deriving monomial");
-        let coef = if self.peek_if(|x| x.is_number()) {
-            let Some(Terminal::Number(coef)) = self.pop_token()? else {
-                panic!("Could't unwrap Number")
-            };
+        let coef = if self.peek_if(|x| x.is_numeric()) {
+            let coef = self.coefficient()?;
             self.advance_if_match(Terminal::Star);
             coef
         } else {
-            1
+            StoichCoef::ONE
         };
         self.species(crn, cplx, coef)?;
         Ok(())
     }
 
+    // integer `2`, fraction `1/2` or decimal `0.5`
+    fn coefficient(&mut self) -> Result<StoichCoef, ParseError> {
+        let coef = match self.pop_token()? {
+            Some(Terminal::Number(n)) => {
+                if self.advance_if_match(Terminal::Slash) {
+                    match self.pop_token()? {
+                        Some(Terminal::Number(d)) if d != 0 => {
+                            match (i64::try_from(n), i64::try_from(d)) {
+                                (Ok(n), Ok(d)) => Ok(StoichCoef::new(n, d)),
+                                _ => self.emit_error(Overflow.to_string()),
+                            }
+                        }
+                        _ => self.emit_error("Expected a nonzero integer denominator after '/'"),
+                    }
+                } else {
+                    match StoichCoef::try_from(n) {
+                        Ok(c) => Ok(c),
+                        Err(e) => self.emit_error(e.to_string()),
+                    }
+                }
+            }
+            Some(Terminal::Real(x)) => match StoichCoef::from_f64(x) {
+                Some(c) => Ok(c),
+                None => self.emit_error(format!("Cannot represent coefficient {x} exactly")),
+            },
+            _ => panic!("Couldn't unwrap coefficient"),
+        }?;
+        if coef.is_zero() {
+            return self.emit_error("Stoichiometric coefficients must be positive");
+        }
+        Ok(coef)
+    }
+
     fn species(
         &mut self,
         crn: &mut Network,
         cplx: &mut Complex,
-        coef: u64,
+        coef: StoichCoef,
     ) -> Result<(), ParseError> {
-        println!("Deriving species");
-        let maybe_token = self.peek_token()?;
         if self.peek_if(|x| x.is_identifier()) {
             let Some(Terminal::Identifier(sp)) = self.pop_token()? else {
                 panic!("Couldn't unwrap Identifier!")
             };
-            crn.add_term_to(cplx, sp, coef);
+            let id = self.species_id(crn, sp)?;
+            cplx.add_term(id, coef);
             Ok(())
         //        } else if self.advance_if_match(Terminal::LeftParen) {
         //            self.complex()?;
@@ -279,7 +885,298 @@ impl<'a> Parser<'a> {
         //            }
         //            Ok(())
         } else {
-            Self::emit_error("Factor Error.")
+            self.emit_error("Factor Error.")
+        }
+    }
+
+    // rate-law expressions
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self, crn: &mut Network) -> Result<Expr, ParseError> {
+        let mut left = self.term(crn)?;
+        loop {
+            let op = if self.advance_if_match(Terminal::Plus) {
+                BinaryOp::Add
+            } else if self.advance_if_match(Terminal::Minus) {
+                BinaryOp::Sub
+            } else {
+                return Ok(left);
+            };
+            let right = self.term(crn)?;
+            left = Expr::binary(op, left, right);
+        }
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self, crn: &mut Network) -> Result<Expr, ParseError> {
+        let mut left = self.unary(crn)?;
+        loop {
+            let op = if self.advance_if_match(Terminal::Star) {
+                BinaryOp::Mul
+            } else if self.advance_if_match(Terminal::Slash) {
+                BinaryOp::Div
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary(crn)?;
+            left = Expr::binary(op, left, right);
+        }
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self, crn: &mut Network) -> Result<Expr, ParseError> {
+        if self.advance_if_match(Terminal::Minus) {
+            Ok(Expr::Neg(Box::new(self.unary(crn)?)))
+        } else {
+            self.power(crn)
+        }
+    }
+
+    // power := primary ['^' unary]
+    fn power(&mut self, crn: &mut Network) -> Result<Expr, ParseError> {
+        let base = self.primary(crn)?;
+        if self.advance_if_match(Terminal::Caret) {
+            let exponent = self.unary(crn)?;
+            Ok(Expr::binary(BinaryOp::Pow, base, exponent))
+        } else {
+            Ok(base)
+        }
+    }
+
+    // primary := number | identifier | function '(' expression ')' | '(' expression ')'
+    fn primary(&mut self, crn: &mut Network) -> Result<Expr, ParseError> {
+        match self.pop_token()? {
+            Some(Terminal::Number(n)) => Ok(Expr::Number(n as f64)),
+            Some(Terminal::Real(x)) => Ok(Expr::Number(x)),
+            Some(Terminal::Identifier(s)) => {
+                if self.peek_if_match(Terminal::At) {
+                    let id = self.species_id(crn, s)?;
+                    Ok(Expr::symbol(crn.species_name(id)))
+                } else if self.advance_if_match(Terminal::LeftParen) {
+                    let Some(func) = Function::from_name(&s) else {
+                        return self.emit_error(format!("Unknown function '{s}'"));
+                    };
+                    let arg = self.expression(crn)?;
+                    self.expect(Terminal::RightParen, "Expected ')' after function argument")?;
+                    Ok(Expr::Call(func, Box::new(arg)))
+                } else {
+                    Ok(Expr::Symbol(s))
+                }
+            }
+            Some(Terminal::LeftParen) => {
+                let e = self.expression(crn)?;
+                self.expect(Terminal::RightParen, "Unmatched parentheses. Expected ')'")?;
+                Ok(e)
+            }
+            Some(t) => self.emit_error(format!("Unexpected {t:?} in expression")),
+            None => Err(ParseError::UnexpectedEOF),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Rational;
+
+    fn parse(text: &str) -> Result<Network, ParseError> {
+        Parser::new(Scanner::scan(text)).parse()
+    }
+
+    fn error(text: &str) -> String {
+        parse(text).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn species_declarations_set_attributes() {
+        let network = parse(
+            "species {\n  E = 1.5 uM : \"enzyme\"\n  S = 100 uM [constant]\n  X [boundary]\n}\n\
+             E + S -> X\n",
+        )
+        .unwrap();
+        let e = network.species_attributes(network.find_species("E").unwrap());
+        assert_eq!(e.initial, Some(1.5));
+        assert_eq!(e.unit.as_deref(), Some("uM"));
+        assert_eq!(e.description.as_deref(), Some("enzyme"));
+        let s = network.species_attributes(network.find_species("S").unwrap());
+        assert!(s.constant && !s.boundary);
+        let x = network.species_attributes(network.find_species("X").unwrap());
+        assert!(x.boundary && x.initial.is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_initial_conditions() {
+        let message = error("species A = -1\nA -> B\n");
+        assert!(message.contains("Initial condition of 'A' must be non-negative"));
+        for value in ["inf", "1e999"] {
+            let message = error(&format!("species A = {value}\nA -> B\n"));
+            assert!(message.contains("Expected a finite number"));
+        }
+    }
+
+    #[test]
+    fn parameter_declarations_set_attributes() {
+        let network = parse(
+            "param {\n  Km = 0.5 [0.01, 10] uM ~ lognormal(0, 1) : \"affinity\"\n  \
+             k [0, inf]\n  unused = 2\n}\nA -> B : k * A / (Km + A + j)\n",
+        )
+        .unwrap();
+        let km = network.parameter_attributes(network.find_parameter("Km").unwrap());
+        assert_eq!(km.value, Some(0.5));
+        assert_eq!((km.lower, km.upper), (Some(0.01), Some(10.0)));
+        assert_eq!(km.unit.as_deref(), Some("uM"));
+        assert_eq!(
+            km.prior,
+            Some(Prior::LogNormal {
+                mu: 0.0,
+                sigma: 1.0
+            })
+        );
+        assert_eq!(km.description.as_deref(), Some("affinity"));
+        // an infinite bound is no bound
+        let k = network.parameter_attributes(network.find_parameter("k").unwrap());
+        assert_eq!((k.value, k.lower, k.upper), (None, Some(0.0), None));
+        let warnings: Vec<String> = network
+            .parameter_warnings()
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.iter().any(|w| w.contains("'j'")));
+        assert!(warnings.iter().any(|w| w.contains("'unused'")));
+    }
+
+    #[test]
+    fn rejects_invalid_parameter_values() {
+        for value in ["inf", "-inf", "1e999"] {
+            assert!(error(&format!("param k = {value}\n")).contains("Expected a finite number"));
         }
+        assert!(error("param k = 20 [0, 10]\n").contains("lies outside its bounds"));
+        assert!(error("param k [1, 0]\n").contains("exceeds its upper bound"));
+    }
+
+    #[test]
+    fn flux_bounds_may_be_infinite() {
+        let network = parse("R : A <-> B\nflux R [-inf, 5]\nmaximize R\n").unwrap();
+        let bound = &network.flux_bounds()[0];
+        assert_eq!((bound.lower, bound.upper), (f64::NEG_INFINITY, 5.0));
+    }
+
+    #[test]
+    fn compartments_scale_their_species() {
+        let network = parse(
+            "compartment {\n  chl = 1.2 L : \"chloroplast\"\n  cyt = 3\n}\n\
+             GCA@chl <-> GCA@cyt\n",
+        )
+        .unwrap();
+        let chl = network.find_compartment("chl").unwrap();
+        let attr = network.compartment_attributes(chl);
+        assert_eq!((attr.size, attr.unit.as_deref()), (Some(1.2), Some("L")));
+        assert_eq!(attr.description.as_deref(), Some("chloroplast"));
+        let inner = network.find_species("GCA@chl").unwrap();
+        let outer = network.find_species("GCA@cyt").unwrap();
+        assert_eq!(network.species_attributes(inner).compartment, Some(chl));
+        assert_eq!(network.species_volume(inner), 1.2);
+        assert_eq!(network.species_volume(outer), 3.0);
+    }
+
+    #[test]
+    fn rejects_invalid_compartment_sizes() {
+        for size in ["0", "-1"] {
+            let message = error(&format!("compartment c = {size}\n"));
+            assert!(message.contains("Size of compartment 'c' must be positive"));
+        }
+        for size in ["inf", "1e999"] {
+            assert!(
+                error(&format!("compartment c = {size}\n")).contains("Expected a finite number")
+            );
+        }
+    }
+
+    #[test]
+    fn empty_complexes_make_exchange_reactions() {
+        let network = parse("feed : 0 -> A\nA -> \u{2205}\nA + B -> {}\nA -> B\n").unwrap();
+        assert_eq!(network.num_species(), 2);
+        assert_eq!(network.exchange_reactions(), vec![0, 1, 2]);
+        let reactions = network.reactions();
+        assert!(reactions[0].get_reactants().is_empty());
+        assert_eq!(reactions[2].get_reactants().terms().len(), 2);
+        assert!(reactions[2].get_products().is_empty());
+        assert!(!reactions[3].is_exchange());
+    }
+
+    #[test]
+    fn events_parse_triggers_and_assignments() {
+        let network = parse(
+            "param k_syn = 1\n0 -> ATP : k_syn\nATP -> 0\n\
+             when ATP < 0.1 then k_syn = 0\nat t = 50, 100: ATP += 10, k_syn -= 1\n",
+        )
+        .unwrap();
+        let events = network.events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0].trigger,
+            Trigger::When(_, Comparison::Less, _)
+        ));
+        assert_eq!(events[1].trigger, Trigger::At(vec![50.0, 100.0]));
+        let ops: Vec<AssignOp> = events[1].assignments.iter().map(|a| a.op).collect();
+        assert_eq!(ops, vec![AssignOp::Add, AssignOp::Subtract]);
+        assert_eq!(events[1].assignments[0].target, "ATP");
+    }
+
+    #[test]
+    fn rejects_invalid_events() {
+        assert!(error("A -> 0\nat t = -1: A = 1\n").contains("finite and non-negative"));
+        assert!(error("A -> 0\nwhen t > 1 then A = 0\n").contains("'t'"));
+    }
+
+    #[test]
+    fn rules_and_observables_are_ordered_and_expanded() {
+        let network = parse(
+            "observable bound_fraction = ES / total_E\nobservable total_E = E + ES\n\
+             rule Keq = kf / kr\nE + S <-> ES : kf * E * S - kf / Keq * ES\n",
+        )
+        .unwrap();
+        let names: Vec<&str> = network
+            .definitions()
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, ["total_E", "bound_fraction", "Keq"]);
+        assert_eq!(network.observables().count(), 2);
+        assert!(network.find_parameter("Keq").is_none());
+        let law = network.expand(&network.rate_law(0));
+        assert!(!law.symbols().contains("Keq"));
+        assert!(law.symbols().contains("kr"));
+    }
+
+    #[test]
+    fn rejects_inconsistent_definitions() {
+        let message = error("rule a = b\nrule b = 2 * a\nX -> 0 : a * X\n");
+        assert!(message.contains("Rules depend on each other"));
+        assert!(error("rule a = 1\nrule a = 2\n").contains("defined more than once"));
+        assert!(error("species A = 1\nobservable A = 2\nA -> 0\n").contains("also a species"));
+    }
+
+    #[test]
+    fn formulas_check_element_and_charge_balance() {
+        let network = parse(
+            "formula {\n  \"NH4(+)\" : N H4 +1\n  NH3 : N H3\n}\n\
+             \"NH4(+)\" -> NH3 + \"H(+)\"\nNH3 + H2O -> \"NH4(+)\"\n",
+        )
+        .unwrap();
+        let ammonium = network.find_species("NH4(+)").unwrap();
+        let formula = network.species_formula(ammonium).unwrap();
+        assert_eq!((formula.elements["H"], formula.charge), (4, 1));
+        let warnings = network.balance_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].reaction, "R2");
+        assert_eq!(
+            warnings[0].elements,
+            vec![
+                ("H".to_string(), Rational::integer(-1)),
+                ("O".to_string(), Rational::integer(-1))
+            ]
+        );
+        assert_eq!(warnings[0].charge, Rational::ONE);
     }
 }
@@ -4,6 +4,7 @@ use std::fmt;
 use std::iter::Peekable;
 use std::str::Chars;
 // import terminal symbols
+use crate::language::grammar;
 use crate::language::grammar::Terminal;
 
 pub type LineNum = u64;
@@ -85,10 +86,8 @@ impl<'a> Scanner<'a> {
                 self.increment_line_num();
             }
 
-            if c == '*' {
-                if self.match_next(|c| *c == '/') {
-                    break;
-                }
+            if c == '*' && self.match_next(|c| *c == '/') {
+                break;
             }
         }
     }
@@ -138,16 +137,66 @@ impl<'a> Scanner<'a> {
         Terminal::Identifier(lexeme)
     }
 
+    // look two characters ahead without consuming
+    fn peek_second(&self) -> Option<char> {
+        let mut ahead = self.characters.clone();
+        ahead.next();
+        ahead.next()
+    }
+
+    fn fraction_and_exponent(&mut self, lexeme: &mut String) {
+        // fractional part
+        if self.characters.peek() == Some(&'.')
+            && self.peek_second().is_some_and(|c| c.is_ascii_digit())
+        {
+            lexeme.push('.');
+            self.pop();
+            while let Some(c) = self.take_next_if(|c| c.is_ascii_digit()) {
+                lexeme.push(c);
+            }
+        }
+
+        // exponent e.g. 1.5e-3
+        if matches!(self.characters.peek(), Some('e') | Some('E')) {
+            let mut ahead = self.characters.clone();
+            ahead.next();
+            let sign = ahead.next_if(|c| *c == '+' || *c == '-');
+            if ahead.peek().is_some_and(|c| c.is_ascii_digit()) {
+                lexeme.push('e');
+                self.pop();
+                if let Some(s) = sign {
+                    lexeme.push(s);
+                    self.pop();
+                }
+                while let Some(c) = self.take_next_if(|c| c.is_ascii_digit()) {
+                    lexeme.push(c);
+                }
+            }
+        }
+    }
+
     fn identifier_or_number(&mut self, c: char) -> Terminal {
         let mut lexeme = String::new();
         lexeme.push(c);
-        while let Some(c) = self.take_next_if(|c| c.is_alphanumeric()) {
+        if c.is_ascii_digit() {
+            while let Some(c) = self.take_next_if(|c| c.is_ascii_digit()) {
+                lexeme.push(c);
+            }
+            let integer = lexeme.len();
+            self.fraction_and_exponent(&mut lexeme);
+            if lexeme.len() > integer
+                && let Ok(x) = lexeme.parse::<f64>()
+            {
+                return Terminal::Real(x);
+            }
+        }
+        while let Some(c) = self.take_next_if(|c| c.is_alphanumeric() || *c == '_') {
             lexeme.push(c);
         }
         let maybe_number = lexeme.parse::<u64>();
         match maybe_number {
             Ok(n) => Terminal::Number(n),
-            _ => Terminal::Identifier(lexeme),
+            _ => grammar::keyword(&lexeme).unwrap_or(Terminal::Identifier(lexeme)),
         }
     }
 }
@@ -177,7 +226,7 @@ impl<'a> Iterator for Scanner<'a> {
                 continue;
             }
 
-            if c.is_alphanumeric() {
+            if c.is_alphanumeric() || c == '_' {
                 let t = self.identifier_or_number(c);
                 return Some(Ok(t));
             }
@@ -196,6 +245,10 @@ impl<'a> Iterator for Scanner<'a> {
                 '=' => Ok(Terminal::Equal),
                 '\'' => Ok(Terminal::Tick),
                 ',' => Ok(Terminal::Comma),
+                '∅' => Ok(Terminal::EmptySet),
+                '^' => Ok(Terminal::Caret),
+                '~' => Ok(Terminal::Tilde),
+                '@' => Ok(Terminal::At),
                 '-' => Ok(self.rightarrow_or_minus()),
                 '>' => Ok(Terminal::Greater),
                 '<' => Ok(self.leftarrow_or_less()),
@@ -0,0 +1,153 @@
+// a network written back out in the model language, so that derived models
+// (e.g. reduced ones) can be saved, read and edited like hand-written ones
+use crate::network::expression::quote_symbol;
+use crate::network::{Declaration, LintLevel, Network, Sense};
+
+// trailing `: "description"` of a declaration
+fn description(text: &Option<String>) -> String {
+    match text {
+        Some(d) => format!(" : \"{d}\""),
+        None => String::new(),
+    }
+}
+
+fn block(out: &mut String, keyword: &str, entries: &[String]) {
+    match entries {
+        [] => {}
+        [entry] => out.push_str(&format!("{keyword} {entry}\n")),
+        _ => {
+            out.push_str(&format!("{keyword} {{\n"));
+            for entry in entries {
+                out.push_str(&format!("    {entry}\n"));
+            }
+            out.push_str("}\n");
+        }
+    }
+}
+
+pub fn write(network: &Network) -> String {
+    let mut out = String::new();
+
+    let compartments: Vec<String> = (0..network.num_compartments())
+        .map(|c| {
+            let attr = network.compartment_attributes(c);
+            let mut entry = quote_symbol(network.compartment_name(c));
+            if let Some(size) = attr.size {
+                entry.push_str(&format!(" = {size}"));
+                if let Some(unit) = &attr.unit {
+                    entry.push_str(&format!(" {unit}"));
+                }
+            }
+            entry + &description(&attr.description)
+        })
+        .collect();
+    block(&mut out, "compartment", &compartments);
+
+    // every species is declared, so that one left only in rate laws (e.g.
+    // after a reduction) is not read back as a parameter
+    let species: Vec<String> = (0..network.num_species())
+        .map(|i| {
+            let attr = network.species_attributes(i);
+            let mut entry = quote_symbol(network.species_name(i));
+            if let Some(x) = attr.initial {
+                entry.push_str(&format!(" = {x}"));
+                if let Some(unit) = &attr.unit {
+                    entry.push_str(&format!(" {unit}"));
+                }
+            }
+            let flags: Vec<&str> = [(attr.constant, "constant"), (attr.boundary, "boundary")]
+                .into_iter()
+                .filter_map(|(set, flag)| set.then_some(flag))
+                .collect();
+            if !flags.is_empty() {
+                entry.push_str(&format!(" [{}]", flags.join(", ")));
+            }
+            entry + &description(&attr.description)
+        })
+        .collect();
+    block(&mut out, "species", &species);
+
+    let formulas: Vec<String> = (0..network.num_species())
+        .filter_map(|i| {
+            let formula = network.species_attributes(i).formula.as_ref()?;
+            Some(format!(
+                "{} : {formula}",
+                quote_symbol(network.species_name(i))
+            ))
+        })
+        .collect();
+    block(&mut out, "formula", &formulas);
+
+    // parameters that were never declared are left for the parser to infer
+    let parameters: Vec<String> = (0..network.num_parameters())
+        .filter(|&k| network.parameter_attributes(k).declaration == Declaration::Explicit)
+        .map(|k| {
+            let attr = network.parameter_attributes(k);
+            let mut entry = quote_symbol(network.parameter_name(k));
+            if let Some(v) = attr.value {
+                entry.push_str(&format!(" = {v}"));
+            }
+            if attr.lower.is_some() || attr.upper.is_some() {
+                let lower = attr.lower.unwrap_or(f64::NEG_INFINITY);
+                let upper = attr.upper.unwrap_or(f64::INFINITY);
+                entry.push_str(&format!(" [{lower}, {upper}]"));
+            }
+            if let Some(unit) = &attr.unit {
+                entry.push_str(&format!(" {unit}"));
+            }
+            if let Some(prior) = &attr.prior {
+                entry.push_str(&format!(" ~ {prior}"));
+            }
+            entry + &description(&attr.description)
+        })
+        .collect();
+    block(&mut out, "param", &parameters);
+
+    for d in network.definitions() {
+        let keyword = if d.observable { "observable" } else { "rule" };
+        out.push_str(&format!(
+            "{keyword} {} = {}\n",
+            quote_symbol(&d.name),
+            d.value
+        ));
+    }
+
+    for (j, rxn) in network.reactions().iter().enumerate() {
+        if let Some(name) = rxn.get_name() {
+            out.push_str(&format!("{} : ", quote_symbol(name)));
+        }
+        out.push_str(&network.reaction_formula(j));
+        if let Some(rate) = rxn.get_kinetics() {
+            out.push_str(&format!(" : {rate}"));
+        }
+        out.push('\n');
+    }
+
+    for event in network.events() {
+        out.push_str(&format!("{event}\n"));
+    }
+    for bound in network.flux_bounds() {
+        out.push_str(&format!(
+            "flux {} [{}, {}]\n",
+            quote_symbol(&bound.reaction),
+            bound.lower,
+            bound.upper
+        ));
+    }
+    for (code, level) in network.lint_levels() {
+        let keyword = match level {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => continue,
+            LintLevel::Deny => "deny",
+        };
+        out.push_str(&format!("{keyword} {code}\n"));
+    }
+    if let Some((reaction, sense)) = network.objective() {
+        let keyword = match sense {
+            Sense::Maximize => "maximize",
+            Sense::Minimize => "minimize",
+        };
+        out.push_str(&format!("{keyword} {}\n", quote_symbol(reaction)));
+    }
+    out
+}
@@ -1,18 +1,38 @@
 use std::error::Error;
 use std::ffi::OsStr;
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-mod data;
-mod language;
-mod network;
-use language::grammar::Terminal;
-use language::parser::{Parser, SyntaxError};
-use language::scanner::{LexError, Scanner};
-use network::Network;
+use std::thread;
+pub mod analysis;
+mod args;
+pub mod codegen;
+pub mod data;
+pub mod language;
+pub mod network;
+pub mod numeric;
+pub mod simulation;
+use analysis::continuation::{Bifurcation, ContinuationOptions};
+use analysis::estimation::{EstimationOptions, Scale};
+use analysis::fba::FluxBalance;
+use analysis::lint::Lint;
+use analysis::reduction::Approximation;
+use analysis::scan::{Axis, Design, Mode, ScanOptions, Variable};
+use analysis::steady_state::{Approach, SteadyStateOptions};
+use args::{Command, Options};
+use codegen::Target;
+use data::Rational;
+use language::parser::Parser;
+use language::scanner::Scanner;
+use network::{Declaration, LintLevel, Network};
+use numeric::Method;
+use simulation::{SimulationOptions, StochasticMethod, StochasticOptions};
 
 pub struct Config {
     callname: String,
+    command: Command,
     files: Vec<PathBuf>,
+    options: Options,
     print_usage: bool,
 }
 
@@ -21,17 +41,26 @@ impl Config {
         let callname = args.next().expect("No callname found...");
 
         let mut files: Vec<PathBuf> = Vec::new();
+        let mut options = Options::default();
         let mut print_usage = false;
+        let mut command = None;
 
-        for arg in args {
+        while let Some(arg) = args.next() {
             if !is_option(&arg) {
+                if command.is_none()
+                    && files.is_empty()
+                    && let Some(c) = Command::from_name(&arg)
+                {
+                    command = Some(c);
+                    continue;
+                }
                 let file = Path::new(&arg);
                 // skip files without extensions
-                if let Some(ext) = file.extension() {
-                    if valid_extension(ext) {
-                        files.push(file.to_path_buf());
-                        continue;
-                    }
+                if let Some(ext) = file.extension()
+                    && valid_extension(ext)
+                {
+                    files.push(file.to_path_buf());
+                    continue;
                 }
             }
 
@@ -41,20 +70,21 @@ impl Config {
             }
 
             // trim --
-            let _arg_op = (&arg)
-                .strip_prefix("--")
-                .expect("Optional arguments should start with `--`");
-
-            return Err("Unknown Argument");
+            let Some(arg_op) = arg.strip_prefix("--") else {
+                return Err("Optional arguments should start with `--`");
+            };
+            options.take(arg_op, &mut args)?;
         }
 
-        if files.len() == 0 {
+        if files.is_empty() && !print_usage {
             return Err("No files to parse...");
         }
 
         Ok(Config {
             callname,
+            command: command.unwrap_or(Command::Print),
             files,
+            options,
             print_usage,
         })
     }
@@ -62,25 +92,1026 @@ impl Config {
 
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
     if config.print_usage {
-        println!("{USAGE}");
+        println!("{}", USAGE.replace("reaction_net", &config.callname));
         return Ok(());
     }
 
-    let network = Network::new();
+    match config.command {
+        Command::Print => print_networks(&config),
+        Command::Fba => flux_balance(&config),
+        Command::Compile => compile(&config),
+        Command::Simulate => simulate(&config),
+        Command::SteadyState => steady_state(&config),
+        Command::Stability => stability(&config),
+        Command::Sensitivity => sensitivity(&config),
+        Command::Control => control(&config),
+        Command::Fit => fit(&config),
+        Command::Scan => scan(&config),
+        Command::Continuation => continuation(&config),
+        Command::Reduce => reduce(&config),
+        Command::Check => check(&config),
+        Command::Gaps => gaps(&config),
+        Command::Siphons => siphons(&config),
+        Command::Invariants => invariants(&config),
+    }
+}
+
+// parse a model file into a network
+pub fn load(file: &Path) -> Result<Network, Box<dyn Error>> {
+    let contents = fs::read_to_string(file)?;
+    let scanner = Scanner::scan(&contents);
+    let mut parser = Parser::new(scanner);
+    Ok(parser.parse()?)
+}
+
+// report semantic problems that do not prevent analysis
+fn warn(file: &Path, network: &Network) {
+    for w in network.parameter_warnings() {
+        let lint = match w {
+            network::ParameterWarning::Undeclared(_) => Lint::UndeclaredParameter,
+            network::ParameterWarning::Unused(_) => Lint::UnusedParameter,
+        };
+        if !analysis::lint::allowed(network, lint) {
+            eprintln!("warning: {}: {w}", file.display());
+        }
+    }
+    if !analysis::lint::allowed(network, Lint::Unbalanced) {
+        for w in network.balance_warnings() {
+            eprintln!("warning: {}: {w}", file.display());
+        }
+    }
+}
+
+// parse additional declarations (e.g. flux bounds) into an existing network
+pub fn load_into(file: &Path, network: &mut Network) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(file)?;
+    let scanner = Scanner::scan(&contents);
+    let mut parser = Parser::new(scanner);
+    Ok(parser.parse_into(network)?)
+}
 
-    for file in config.files {
+fn print_networks(config: &Config) -> Result<(), Box<dyn Error>> {
+    for file in &config.files {
         let contents = fs::read_to_string(file)?;
         println!("{contents}");
 
         let scanner = Scanner::scan(&contents);
         let mut parser = Parser::new(scanner);
         let crn = parser.parse()?;
+        warn(file, &crn);
         println!("{crn:?}")
     }
 
     Ok(())
 }
 
+fn flux_balance(config: &Config) -> Result<(), Box<dyn Error>> {
+    let fraction: f64 = config.options.parse_or("fraction", 1.0)?;
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(format!(
+            "Invalid value '{fraction}' for option --fraction; expected a number from 0 to 1"
+        )
+        .into());
+    }
+    for file in &config.files {
+        let mut network = load(file)?;
+        if let Some(bounds) = config.options.value("bounds") {
+            load_into(Path::new(bounds), &mut network)?;
+        }
+        warn(file, &network);
+        let fba = FluxBalance::new(&network)?;
+
+        let optimum = fba.optimize()?;
+        let (objective, _) = network
+            .objective()
+            .expect("objective checked by FluxBalance");
+        println!("objective\t{objective}\t{}", optimum.objective);
+
+        if config.options.flag("fva") {
+            let ranges = fba.variability(fraction)?;
+            println!("reaction\tflux\tminimum\tmaximum");
+            for (j, range) in ranges.iter().enumerate() {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    network.reaction_name(j),
+                    optimum.fluxes[j],
+                    range.minimum,
+                    range.maximum
+                );
+            }
+        } else {
+            println!("reaction\tflux");
+            for (j, v) in optimum.fluxes.iter().enumerate() {
+                println!("{}\t{v}", network.reaction_name(j));
+            }
+        }
+
+        if config.options.flag("knockouts") {
+            println!("knockout\tobjective");
+            for ko in fba.knockouts()? {
+                let value = match ko.objective {
+                    Some(v) => v.to_string(),
+                    None => "infeasible".to_string(),
+                };
+                println!("{}\t{value}", network.reaction_name(ko.reaction));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// translate each model into code or SBML
+fn compile(config: &Config) -> Result<(), Box<dyn Error>> {
+    let name = config.options.value("target").unwrap_or("python");
+    let target = Target::from_name(name).ok_or(format!("Unknown target '{name}'"))?;
+    let mut output = String::new();
+    for file in &config.files {
+        let mut network = load(file)?;
+        warn(file, &network);
+        if config.options.flag("eliminate-conserved") {
+            network = analysis::conservation::eliminate_dependent(&network)?;
+        }
+        if !network.events().is_empty() {
+            eprintln!(
+                "warning: {}: events are not included in generated code",
+                file.display()
+            );
+        }
+        let model = file
+            .file_stem()
+            .map_or("model".into(), |s| s.to_string_lossy());
+        output.push_str(&codegen::generate(&network, target, &model)?);
+    }
+    write_output(config, output.as_bytes())
+}
+
+// integrate each model, or sample stochastic trajectories, and write the
+// time courses as CSV
+fn simulate(config: &Config) -> Result<(), Box<dyn Error>> {
+    let method = config.options.value("method");
+    if let Some(method) = method.and_then(StochasticMethod::from_name) {
+        return simulate_stochastic(config, method);
+    }
+    let options = simulation_options(config)?;
+    let mut output = Vec::new();
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        simulation::simulate(&network, &options)?.write_csv(&mut output)?;
+    }
+    write_output(config, &output)
+}
+
+// time span, output times and integrator of deterministic simulations
+fn simulation_options(config: &Config) -> Result<SimulationOptions, Box<dyn Error>> {
+    let mut options = SimulationOptions::default();
+    options.t_end = config.options.parse_or("t-end", options.t_end)?;
+    options.points = config.options.parse_or("points", options.points)?;
+    options.ode.rtol = config.options.parse_or("rtol", options.ode.rtol)?;
+    options.ode.atol = config.options.parse_or("atol", options.ode.atol)?;
+    if let Some(name) = config.options.value("method") {
+        options.ode.method = Method::from_name(name).ok_or(format!("Unknown method '{name}'"))?;
+    }
+    Ok(options)
+}
+
+fn simulate_stochastic(config: &Config, method: StochasticMethod) -> Result<(), Box<dyn Error>> {
+    let mut options = StochasticOptions {
+        method,
+        ..Default::default()
+    };
+    options.t_end = config.options.parse_or("t-end", options.t_end)?;
+    options.points = config.options.parse_or("points", options.points)?;
+    options.seed = config.options.parse_or("seed", options.seed)?;
+    options.epsilon = config.options.parse_or("epsilon", options.epsilon)?;
+    options.fast = config.options.parse_or("fast", options.fast)?;
+    if config.options.value("dt").is_some() {
+        options.step = Some(config.options.parse_or("dt", 0.0)?);
+    }
+    let runs: usize = config.options.parse_or("runs", 1)?;
+    let threads = config.options.parse_or(
+        "threads",
+        thread::available_parallelism().map_or(1, |n| n.get()),
+    )?;
+
+    let mut output = Vec::new();
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        if runs == 1 {
+            simulation::simulate_stochastic(&network, &options)?.write_csv(&mut output)?;
+        } else {
+            let courses = simulation::ensemble(&network, &options, runs, threads)?;
+            simulation::write_ensemble_csv(&courses, &mut output)?;
+        }
+    }
+    write_output(config, &output)
+}
+
+// steady-state concentrations, fluxes and conserved totals of each model
+fn steady_state(config: &Config) -> Result<(), Box<dyn Error>> {
+    let options = SteadyStateOptions {
+        integrate: config.options.flag("integrate"),
+        ..Default::default()
+    };
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let state = analysis::steady_state(&network, &options)?;
+        if state.approach == Approach::Integration {
+            eprintln!(
+                "note: {}: Newton failed from the initial conditions; started from a time course",
+                file.display()
+            );
+        }
+        println!("species\tconcentration");
+        for (i, x) in state.concentrations.iter().enumerate() {
+            println!("{}\t{x}", network.species_name(i));
+        }
+        println!("reaction\tflux");
+        for (j, v) in state.fluxes.iter().enumerate() {
+            println!("{}\t{v}", network.reaction_name(j));
+        }
+        let observables = simulation::Observables::new(&network)?;
+        if !observables.names().is_empty() {
+            println!("observable\tvalue");
+            let values = observables.eval(&state.concentrations, &network.parameter_values());
+            for (name, y) in observables.names().iter().zip(values) {
+                println!("{name}\t{y}");
+            }
+        }
+        let laws = analysis::conservation_laws(&network)?;
+        if !laws.is_empty() {
+            println!("conserved\ttotal");
+            for law in laws {
+                println!(
+                    "{}\t{}",
+                    law.formula(&network),
+                    law.total(&network, &state.concentrations)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// eigenvalues of the steady state of each model, with the time scale of
+// each mode and the species taking part in it
+fn stability(config: &Config) -> Result<(), Box<dyn Error>> {
+    let options = SteadyStateOptions {
+        integrate: config.options.flag("integrate"),
+        ..Default::default()
+    };
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let state = analysis::steady_state(&network, &options)?;
+        let system = simulation::RateEquations::new(&network)?;
+        let laws = analysis::conservation_laws(&network)?;
+        let result = analysis::stability::analyze(&network, &system, &laws, &state.concentrations)?;
+        println!("stability\t{}", result.stability);
+        println!("mode\teigenvalue\ttime_scale\tperiod\tspecies");
+        for (k, mode) in result.modes.iter().enumerate() {
+            // species accounting for most of the mode
+            let mut species = Vec::new();
+            let mut covered = 0.0;
+            for &(i, p) in &mode.participation {
+                if covered >= 0.9 || p < 0.05 {
+                    break;
+                }
+                covered += p;
+                species.push(format!("{} ({:.2})", network.species_name(i), p));
+            }
+            let period = mode.period.map_or("-".to_string(), |p| p.to_string());
+            println!(
+                "{}\t{}\t{}\t{period}\t{}",
+                k + 1,
+                mode.eigenvalue,
+                mode.time_scale,
+                species.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+// parameters named by `--parameters a,b,...`, or all of them
+fn chosen_parameters(config: &Config, network: &Network) -> Result<Vec<usize>, Box<dyn Error>> {
+    match config.options.value("parameters") {
+        Some(list) => list
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                network
+                    .find_parameter(name)
+                    .ok_or_else(|| format!("Unknown parameter '{name}'").into())
+            })
+            .collect(),
+        None => Ok((0..network.num_parameters()).collect()),
+    }
+}
+
+// sample times given by `--times t1,t2,...`, or evenly spaced ones
+fn chosen_times(config: &Config, options: &SimulationOptions) -> Result<Vec<f64>, Box<dyn Error>> {
+    match config.options.value("times") {
+        Some(list) => {
+            let mut times = list
+                .split(',')
+                .map(|t| {
+                    t.trim()
+                        .parse::<f64>()
+                        .map_err(|_| format!("Invalid time '{t}' for option --times"))
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+            times.sort_by(f64::total_cmp);
+            Ok(times)
+        }
+        None => Ok(simulation::deterministic::output_times(
+            options.t_end,
+            options.points,
+        )?),
+    }
+}
+
+// sensitivities of species and fluxes to parameters, along a time course or
+// at the steady state, as tidy CSV
+fn sensitivity(config: &Config) -> Result<(), Box<dyn Error>> {
+    let options = simulation_options(config)?;
+    let steady = SteadyStateOptions {
+        integrate: config.options.flag("integrate"),
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let parameters = chosen_parameters(config, &network)?;
+        let result = if config.options.flag("steady-state") {
+            analysis::sensitivity::at_steady_state(&network, &parameters, &steady)?
+        } else {
+            let times = chosen_times(config, &options)?;
+            analysis::sensitivity::time_course(&network, &parameters, &times, options.ode)?
+        };
+        writeln!(output, "time,variable,parameter,sensitivity,normalized")?;
+        for point in &result.points {
+            let time = if point.time.is_finite() {
+                point.time.to_string()
+            } else {
+                "steady".to_string()
+            };
+            for (c, &k) in parameters.iter().enumerate() {
+                let parameter = simulation::csv_field(network.parameter_name(k));
+                for i in 0..network.num_species() {
+                    writeln!(
+                        output,
+                        "{time},{},{parameter},{},{}",
+                        simulation::csv_field(network.species_name(i)),
+                        point.species_sensitivities[(i, c)],
+                        result.species_coefficient(point, i, c)
+                    )?;
+                }
+                for j in 0..network.num_reactions() {
+                    writeln!(
+                        output,
+                        "{time},{},{parameter},{},{}",
+                        simulation::csv_field(&network.reaction_name(j)),
+                        point.flux_sensitivities[(j, c)],
+                        result.flux_coefficient(point, j, c)
+                    )?;
+                }
+            }
+        }
+    }
+    write_output(config, &output)
+}
+
+// elasticities and control coefficients at the steady state of each model
+fn control(config: &Config) -> Result<(), Box<dyn Error>> {
+    let options = SteadyStateOptions {
+        integrate: config.options.flag("integrate"),
+        ..Default::default()
+    };
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let mca = analysis::control_analysis(&network, &options)?;
+        let species: Vec<String> = network.species_names().to_vec();
+        let reactions: Vec<String> = (0..network.num_reactions())
+            .map(|j| network.reaction_name(j))
+            .collect();
+        // coefficients normalized by a zero flux or concentration are
+        // undefined, so their rows are left out
+        let mut reaction_rows = Vec::new();
+        for (j, name) in reactions.iter().enumerate() {
+            if mca.fluxes[j] == 0.0 {
+                eprintln!(
+                    "warning: {}: the flux of reaction '{name}' is zero at the steady state; \
+                     its elasticities and flux control coefficients are left out",
+                    file.display()
+                );
+            } else {
+                reaction_rows.push(j);
+            }
+        }
+        let mut species_rows = Vec::new();
+        for (i, name) in species.iter().enumerate() {
+            if mca.concentrations[i] == 0.0 {
+                eprintln!(
+                    "warning: {}: the concentration of '{name}' is zero at the steady state; \
+                     its concentration control coefficients are left out",
+                    file.display()
+                );
+            } else {
+                species_rows.push(i);
+            }
+        }
+        print_table(
+            "elasticity",
+            &reactions,
+            &reaction_rows,
+            &species,
+            &mca.elasticities,
+        );
+        print_table(
+            "flux_control",
+            &reactions,
+            &reaction_rows,
+            &reactions,
+            &mca.flux_control,
+        );
+        print_table(
+            "concentration_control",
+            &species,
+            &species_rows,
+            &reactions,
+            &mca.concentration_control,
+        );
+    }
+    Ok(())
+}
+
+// fit parameters to the measurements of `--data`, reporting estimates with
+// confidence intervals and the residual of each measurement
+fn fit(config: &Config) -> Result<(), Box<dyn Error>> {
+    let path = config
+        .options
+        .value("data")
+        .ok_or("fit needs measurements: --data <file.csv>")?;
+    let contents = fs::read_to_string(path)?;
+    let mut options = EstimationOptions {
+        ode: simulation_options(config)?.ode,
+        ..Default::default()
+    };
+    options.scale = match config.options.value("scale").unwrap_or("log") {
+        "log" => Scale::Log,
+        "linear" => Scale::Linear,
+        name => return Err(format!("Unknown scale '{name}'").into()),
+    };
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let data = analysis::Data::parse(&contents, &network)?;
+        // declared parameters unless chosen explicitly
+        let parameters = if config.options.value("parameters").is_some() {
+            chosen_parameters(config, &network)?
+        } else {
+            (0..network.num_parameters())
+                .filter(|&k| network.parameter_attributes(k).declaration == Declaration::Explicit)
+                .collect()
+        };
+        if parameters.is_empty() {
+            return Err(
+                "No parameters to fit; declare them with `param` or use --parameters".into(),
+            );
+        }
+        let result = analysis::estimation::fit(&network, &data, &parameters, &options)?;
+        println!("objective\t{}", result.objective);
+        println!("iterations\t{}", result.iterations);
+        println!("parameter\testimate\tstd_error\tlower_95\tupper_95");
+        for e in &result.estimates {
+            println!(
+                "{}\t{}\t{}\t{}\t{}",
+                network.parameter_name(e.parameter),
+                e.value,
+                e.std_error,
+                e.lower,
+                e.upper
+            );
+        }
+        println!("time\tspecies\tobserved\tpredicted\tresidual");
+        for (k, m) in data.measurements.iter().enumerate() {
+            println!(
+                "{}\t{}\t{}\t{}\t{}",
+                m.time,
+                network.species_name(m.species),
+                m.value,
+                result.predictions[k],
+                result.residuals[k]
+            );
+        }
+    }
+    Ok(())
+}
+
+// ranges of `--vary name=lower:upper[:log],...`
+fn scan_axes(config: &Config, network: &Network) -> Result<Vec<Axis>, Box<dyn Error>> {
+    let list = config
+        .options
+        .value("vary")
+        .ok_or("scan needs ranges: --vary name=lower:upper[:log]")?;
+    list.split(',')
+        .map(|item| {
+            let invalid = || format!("Invalid range '{item}'; expected name=lower:upper[:log]");
+            let (name, range) = item.split_once('=').ok_or_else(invalid)?;
+            let name = name.trim();
+            let variable = Variable::find(network, name)
+                .ok_or_else(|| format!("Unknown parameter or species '{name}'"))?;
+            let parts: Vec<&str> = range.split(':').map(str::trim).collect();
+            let log = match parts.get(2) {
+                None | Some(&"lin") | Some(&"linear") => false,
+                Some(&"log") => true,
+                Some(_) => return Err(invalid().into()),
+            };
+            if parts.len() > 3 || parts.len() < 2 {
+                return Err(invalid().into());
+            }
+            let lower: f64 = parts[0].parse().map_err(|_| invalid())?;
+            let upper: f64 = parts[1].parse().map_err(|_| invalid())?;
+            Ok(Axis {
+                variable,
+                lower,
+                upper,
+                log,
+            })
+        })
+        .collect()
+}
+
+// species concentrations and reaction fluxes named by `--observe`, or all
+// species
+fn observables(config: &Config, network: &Network) -> Result<Vec<Observable>, Box<dyn Error>> {
+    match config.options.value("observe") {
+        Some(list) => list
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                let defined = || network.observables().position(|d| d.name == name);
+                network
+                    .find_species(name)
+                    .map(Observable::Species)
+                    .or_else(|| defined().map(Observable::Defined))
+                    .or_else(|| network.find_reaction(name).map(Observable::Flux))
+                    .ok_or_else(|| {
+                        format!("Unknown species, observable or reaction '{name}'").into()
+                    })
+            })
+            .collect(),
+        None => Ok((0..network.num_species())
+            .map(Observable::Species)
+            .chain((0..network.observables().count()).map(Observable::Defined))
+            .collect()),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Observable {
+    Species(usize),
+    // index among the network's observables
+    Defined(usize),
+    Flux(usize),
+}
+
+// simulations or steady states over a grid or sample of parameter values
+// and initial conditions, as tidy CSV
+fn scan(config: &Config) -> Result<(), Box<dyn Error>> {
+    let simulation = simulation_options(config)?;
+    let samples: usize = config.options.parse_or("samples", 100)?;
+    let design = match config.options.value("sample") {
+        None => Design::Grid(config.options.parse_or("grid", 11)?),
+        Some("lhs" | "latin-hypercube") => Design::LatinHypercube {
+            samples,
+            seed: config.options.parse_or("seed", 0)?,
+        },
+        Some("sobol") => Design::Sobol { samples },
+        Some(name) => return Err(format!("Unknown sampling '{name}'").into()),
+    };
+    let mode = if config.options.flag("steady-state") {
+        Mode::SteadyState
+    } else {
+        Mode::TimeCourse(chosen_times(config, &simulation)?)
+    };
+    let options = ScanOptions {
+        design,
+        mode,
+        ode: simulation.ode,
+        steady: SteadyStateOptions {
+            integrate: config.options.flag("integrate"),
+            ..Default::default()
+        },
+        threads: config.options.parse_or(
+            "threads",
+            thread::available_parallelism().map_or(1, |n| n.get()),
+        )?,
+    };
+
+    let mut output = Vec::new();
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let axes = scan_axes(config, &network)?;
+        let observed = observables(config, &network)?;
+        let names: Vec<String> = axes
+            .iter()
+            .map(|a| simulation::csv_field(a.variable.name(&network)))
+            .collect();
+        writeln!(output, "sample,{},time,observable,value", names.join(","))?;
+        let mut failed = 0;
+        for (k, sample) in analysis::scan::scan(&network, &axes, &options)?
+            .into_iter()
+            .enumerate()
+        {
+            let Ok(snapshots) = sample.outcome else {
+                failed += 1;
+                continue;
+            };
+            let values: Vec<String> = sample.values.iter().map(|v| v.to_string()).collect();
+            for snapshot in &snapshots {
+                let time = if snapshot.time.is_finite() {
+                    snapshot.time.to_string()
+                } else {
+                    "steady".to_string()
+                };
+                for &o in &observed {
+                    let (name, value) = match o {
+                        Observable::Species(i) => (
+                            network.species_name(i).to_string(),
+                            snapshot.concentrations[i],
+                        ),
+                        Observable::Defined(l) => {
+                            let d = network.observables().nth(l).expect("observable exists");
+                            (d.name.clone(), snapshot.observed[l])
+                        }
+                        Observable::Flux(j) => (network.reaction_name(j), snapshot.fluxes[j]),
+                    };
+                    writeln!(
+                        output,
+                        "{},{},{time},{},{value}",
+                        k + 1,
+                        values.join(","),
+                        simulation::csv_field(&name)
+                    )?;
+                }
+            }
+        }
+        if failed > 0 {
+            eprintln!(
+                "warning: {}: {failed} samples failed and were left out",
+                file.display()
+            );
+        }
+    }
+    write_output(config, &output)
+}
+
+// branch of steady states over the range of one parameter, with stability
+// and bifurcations, as CSV
+fn continuation(config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut output = Vec::new();
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let axes = scan_axes(config, &network)?;
+        let [axis] = axes[..] else {
+            return Err("continuation needs one range: --vary parameter=lower:upper".into());
+        };
+        let Variable::Parameter(parameter) = axis.variable else {
+            return Err("continuation varies a parameter, not an initial condition".into());
+        };
+        let options = ContinuationOptions {
+            lower: axis.lower.min(axis.upper),
+            upper: axis.lower.max(axis.upper),
+            step: config.options.parse_or("step", 0.0)?,
+            steady: SteadyStateOptions {
+                integrate: config.options.flag("integrate"),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let branch = analysis::continuation::continuation(&network, parameter, &options)?;
+        let species: Vec<String> = network
+            .species_names()
+            .iter()
+            .map(|s| simulation::csv_field(s))
+            .collect();
+        writeln!(
+            output,
+            "{},{},stability,bifurcation,period",
+            simulation::csv_field(network.parameter_name(parameter)),
+            species.join(",")
+        )?;
+        for point in &branch.points {
+            let x: Vec<String> = point.concentrations.iter().map(|v| v.to_string()).collect();
+            let (bifurcation, period) = match point.bifurcation {
+                Some(Bifurcation::Hopf { frequency }) => (
+                    "hopf".to_string(),
+                    (2.0 * std::f64::consts::PI / frequency).to_string(),
+                ),
+                Some(b) => (b.to_string(), String::new()),
+                None => (String::new(), String::new()),
+            };
+            writeln!(
+                output,
+                "{},{},{},{bifurcation},{period}",
+                point.parameter,
+                x.join(","),
+                point.stability
+            )?;
+        }
+        for point in branch.bifurcations() {
+            eprintln!(
+                "note: {}: {} at {} = {}",
+                file.display(),
+                point.bifurcation.expect("filtered to bifurcations"),
+                network.parameter_name(parameter),
+                point.parameter
+            );
+        }
+    }
+    write_output(config, &output)
+}
+
+// species or reactions named in a comma-separated option
+fn named(
+    list: &str,
+    kind: &str,
+    find: impl Fn(&str) -> Option<usize>,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    list.split(',')
+        .map(|name| {
+            let name = name.trim();
+            find(name).ok_or_else(|| format!("Unknown {kind} '{name}'").into())
+        })
+        .collect()
+}
+
+// each model with fast species or reactions eliminated, written back out in
+// the model language
+fn reduce(config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut output = String::new();
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let species = |list| named(list, "species", |s| network.find_species(s));
+        let (approximation, eliminate, how) = match (
+            config.options.value("qssa"),
+            config.options.value("equilibrium"),
+        ) {
+            (Some(list), None) => (
+                Approximation::QuasiSteadyState,
+                species(list)?,
+                format!("quasi-steady state of {list}"),
+            ),
+            (None, Some(list)) => {
+                let fast = named(list, "reaction", |r| network.find_reaction(r))?;
+                let eliminate = match config.options.value("eliminate") {
+                    Some(list) => species(list)?,
+                    None => analysis::reduction::equilibrium_species(&network, &fast)?,
+                };
+                (
+                    Approximation::RapidEquilibrium(fast),
+                    eliminate,
+                    format!("rapid equilibrium of {list}"),
+                )
+            }
+            _ => {
+                return Err(
+                    "reduce needs either --qssa species,... or --equilibrium reactions,...".into(),
+                );
+            }
+        };
+        let reduction = analysis::reduction::reduce(&network, &eliminate, &approximation)?;
+        for (moiety, left) in &reduction.split {
+            eprintln!(
+                "warning: {}: the reduction splits the conserved moiety {moiety}, so the reduced \
+                 rate laws treat {} as constant instead of using its total; eliminate them too",
+                file.display(),
+                left.join(", ")
+            );
+        }
+        let model = file
+            .file_stem()
+            .map_or("model".into(), |s| s.to_string_lossy());
+        output.push_str(&format!("// {model} reduced by {how}\n"));
+        for (name, value) in &reduction.eliminated {
+            output.push_str(&format!("// {name} = {value}\n"));
+        }
+        output.push_str(&language::writer::write(&reduction.network));
+    }
+    write_output(config, output.as_bytes())
+}
+
+// lints of each model, with their levels set by the model and then by
+// `--allow` and `--deny`; fails if a denied lint fires
+fn check(config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut overrides = Vec::new();
+    for (option, level) in [("allow", LintLevel::Allow), ("deny", LintLevel::Deny)] {
+        if let Some(list) = config.options.value(option) {
+            overrides.extend(list.split(',').map(|code| (code.trim().to_string(), level)));
+        }
+    }
+    let mut denied = 0;
+    for file in &config.files {
+        let network = load(file)?;
+        let (levels, unknown) = analysis::lint::levels(&network, &overrides);
+        for code in unknown {
+            eprintln!("warning: {}: unknown lint '{code}'", file.display());
+        }
+        let mut warnings = 0;
+        for diagnostic in analysis::lint::check(&network) {
+            let level = levels
+                .iter()
+                .find(|(l, _)| *l == diagnostic.lint)
+                .map_or(LintLevel::Warn, |(_, level)| *level);
+            let severity = match level {
+                LintLevel::Allow => continue,
+                LintLevel::Warn => {
+                    warnings += 1;
+                    "warning"
+                }
+                LintLevel::Deny => {
+                    denied += 1;
+                    "error"
+                }
+            };
+            println!(
+                "{severity}: {}: {} [{}]",
+                file.display(),
+                diagnostic.message,
+                diagnostic.lint
+            );
+        }
+        if warnings > 0 {
+            eprintln!("note: {}: {warnings} warnings", file.display());
+        }
+    }
+    if denied > 0 {
+        return Err(format!("{denied} denied lints").into());
+    }
+    Ok(())
+}
+
+// report dead-end species and the reactions they block
+fn gaps(config: &Config) -> Result<(), Box<dyn Error>> {
+    for file in &config.files {
+        let mut network = load(file)?;
+        if let Some(bounds) = config.options.value("bounds") {
+            load_into(Path::new(bounds), &mut network)?;
+        }
+        warn(file, &network);
+        let gaps = analysis::gaps::gaps(&network);
+
+        println!("species\tgap\tround\treactions");
+        for dead_end in &gaps.dead_ends {
+            let reactions: Vec<String> = dead_end
+                .reactions
+                .iter()
+                .map(|&j| network.reaction_name(j))
+                .collect();
+            println!(
+                "{}\t{}\t{}\t{}",
+                network.species_name(dead_end.species),
+                dead_end.gap,
+                dead_end.round,
+                reactions.join(",")
+            );
+        }
+        println!("blocked\tcause");
+        for &(j, cause) in &gaps.blocked {
+            let cause = match cause {
+                Some(i) => network.species_name(i).to_string(),
+                None => "bounds".to_string(),
+            };
+            println!("{}\t{cause}", network.reaction_name(j));
+        }
+        eprintln!(
+            "note: {}: {} of {} reactions blocked",
+            file.display(),
+            gaps.blocked.len(),
+            network.num_reactions()
+        );
+    }
+    Ok(())
+}
+
+// minimal siphons and traps, and the persistence criterion
+fn siphons(config: &Config) -> Result<(), Box<dyn Error>> {
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let result = analysis::siphons::persistence(&network);
+        let names = |set: &[usize]| -> String {
+            let names: Vec<&str> = set.iter().map(|&i| network.species_name(i)).collect();
+            names.join(",")
+        };
+
+        println!("siphon\tconservation law");
+        for siphon in &result.siphons {
+            let conserved = if result.critical.contains(siphon) {
+                "no"
+            } else {
+                "yes"
+            };
+            println!("{}\t{conserved}", names(siphon));
+        }
+        println!("trap");
+        for trap in &result.traps {
+            println!("{}", names(trap));
+        }
+
+        let file = file.display();
+        if !result.critical.is_empty() {
+            eprintln!(
+                "note: {file}: {} siphons contain no conservation law; persistence is not shown",
+                result.critical.len()
+            );
+        } else if result.is_persistent() {
+            eprintln!(
+                "note: {file}: every siphon contains a conservation law; the network is persistent"
+            );
+        } else {
+            eprintln!(
+                "note: {file}: every siphon contains a conservation law; the network is persistent if its trajectories are bounded"
+            );
+        }
+    }
+    Ok(())
+}
+
+// weighted sum of names, e.g. `2 A + B`
+fn weighted_sum(weights: &[Rational], name: impl Fn(usize) -> String) -> String {
+    let terms: Vec<String> = weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| !w.is_zero())
+        .map(|(k, &w)| {
+            if w == Rational::ONE {
+                name(k)
+            } else {
+                format!("{w} {}", name(k))
+            }
+        })
+        .collect();
+    terms.join(" + ")
+}
+
+// minimal P- and T-invariants of the network as a Petri net
+fn invariants(config: &Config) -> Result<(), Box<dyn Error>> {
+    for file in &config.files {
+        let network = load(file)?;
+        warn(file, &network);
+        let transitions = analysis::invariants::transitions(&network);
+        let p_invariants = analysis::invariants::p_invariants(&network)?;
+        let t_invariants = analysis::invariants::t_invariants(&network)?;
+
+        println!("p-invariant");
+        for y in p_invariants {
+            let species = |i| network::expression::quote_symbol(network.species_name(i));
+            println!("{}", weighted_sum(&y, species));
+        }
+        println!("t-invariant");
+        for x in t_invariants {
+            println!("{}", weighted_sum(&x, |k| transitions[k].name(&network)));
+        }
+    }
+    Ok(())
+}
+
+// matrix as a tab-separated table with named rows and columns
+// the rows of `values` numbered in `shown`, labelled from `rows`
+fn print_table(
+    title: &str,
+    rows: &[String],
+    shown: &[usize],
+    columns: &[String],
+    values: &data::Matrix,
+) {
+    println!("{title}\t{}", columns.join("\t"));
+    for &i in shown {
+        let cells: Vec<String> = values.row(i).iter().map(|v| v.to_string()).collect();
+        println!("{}\t{}", rows[i], cells.join("\t"));
+    }
+}
+
+// stdout, or the file given by `--output`
+fn write_output(config: &Config, output: &[u8]) -> Result<(), Box<dyn Error>> {
+    match config.options.value("output") {
+        Some(path) => fs::write(path, output)?,
+        None => io::stdout().write_all(output)?,
+    }
+    Ok(())
+}
+
 fn is_option(arg: &str) -> bool {
     arg.starts_with("-")
 }
@@ -90,12 +1121,82 @@ fn is_help(arg: &str) -> bool {
 }
 
 fn valid_extension(ext: &OsStr) -> bool {
-    let valid_exts = vec![OsStr::new("txt"), OsStr::new("rxn"), OsStr::new("crn")];
-    valid_exts.contains(&&ext)
+    let valid_exts = [OsStr::new("txt"), OsStr::new("rxn"), OsStr::new("crn")];
+    valid_exts.contains(&ext)
 }
 
-static USAGE: &str = "reaction_net <filename.crn> [options]
+static USAGE: &str = "reaction_net [command] <filename.crn> [options]
+
+Commands:
+    print                     Parse and print the network (default).
+    fba                       Flux balance analysis of the declared objective.
+    compile                   Translate the model into code (python, r) or SBML.
+    steady-state              Solve for the steady state reached from the initial conditions.
+    stability                 Eigenvalues, time scales and stability of the steady state.
+    sensitivity               Sensitivities of species and fluxes to parameters, as CSV.
+    control                   Metabolic control analysis: elasticities and control coefficients.
+    fit                       Fit parameters to measured concentrations by weighted least squares.
+    scan                      Simulations or steady states over ranges of parameters or initial
+                              conditions, as CSV.
+    continuation              Branch of steady states over a parameter range, with stability,
+                              folds and Hopf bifurcations, as CSV.
+    check                     Lint the model for likely mistakes such as misspelled species.
+    siphons                   Minimal siphons and traps, and whether each siphon contains a
+                              conservation law, which shows the network persistent.
+    invariants                Minimal P-invariants (conserved weighted sums of species) and
+                              T-invariants (cycles of reactions) of the network as a Petri net.
+    gaps                      Dead-end species and the reactions that can never carry a
+                              steady-state flux.
+    reduce                    Eliminate fast species by the quasi-steady-state or rapid-equilibrium
+                              approximation and print the reduced model.
+    simulate                  Integrate the rate equations, or sample stochastic
+                              trajectories, and print the time course as CSV.
 
 Options:
     --help                    Print usage. 
+    --bounds <file>           Read flux bounds and objective from a side file.
+    --fva                     Report flux variability ranges.
+    --fraction <f>            Fraction of the optimum kept during variability analysis (default 1).
+    --knockouts               Report the objective with each reaction knocked out.
+    --target <lang>           Output of compile: python (default), r, sbml or pnml.
+    --eliminate-conserved     Compile with one species per conservation law replaced by its
+                              total minus the others; the totals become parameters.
+    --integrate               Integrate towards the steady state when Newton's method fails
+                              (steady-state, stability, sensitivity, control).
+    --parameters <a,b,...>    Parameters of a sensitivity analysis (default all) or of a fit
+                              (default those declared with `param`).
+    --data <file.csv>         Measurements to fit: a time column, one column per species and
+                              optional <species>_sd columns of standard deviations.
+    --scale <name>            Fit positive parameters on a log (default) or linear scale.
+    --vary <name=lo:hi[:log]> Ranges of a scan: parameters, or species' initial conditions;
+                              several are separated by commas. A continuation takes one
+                              parameter range.
+    --grid <n>                Values per range of a grid scan (default 11).
+    --step <h>                Initial arclength step of a continuation (default 1% of the range).
+    --sample <design>         Sample the ranges instead of a grid: lhs (Latin hypercube) or sobol.
+    --samples <n>             Number of sampled points (default 100).
+    --observe <a,b,...>       Species or reaction fluxes written by a scan (default all species).
+    --qssa <a,b,...>          Species at quasi-steady state, eliminated by reduce.
+    --equilibrium <r1,...>    Fast reactions at equilibrium, for reduce.
+    --eliminate <a,b,...>     Species eliminated with fast reactions (default those in
+                              conservation laws among the fast reactions' species).
+    --allow <lint,...>        Lints that check does not report.
+    --deny <lint,...>         Lints that make check fail.
+    --times <t1,t2,...>       Output times of a sensitivity analysis.
+    --steady-state            Sensitivities or scans at the steady state instead of along a
+                              time course.
+    --output <file>           Write compiled or simulated output to a file instead of stdout.
+    --t-end <t>               End time of a simulation (default 10).
+    --points <n>              Number of output times of a simulation (default 101).
+    --method <name>           Integrator: rk45 (default) or stiff; or a stochastic
+                              simulation: ssa (Gillespie), next-reaction, tau-leaping,
+                              langevin or hybrid.
+    --seed <n>                Seed of stochastic simulations and Latin hypercubes (default 0).
+    --runs <n>                Number of stochastic trajectories (default 1).
+    --threads <n>             Threads used for several trajectories or scan samples.
+    --epsilon <f>             Error control of tau-leaping (default 0.03).
+    --dt <t>                  Time step of langevin and hybrid simulations.
+    --fast <n>                Firings per step above which hybrid channels are fast (default 10).
+    --rtol <f>                Relative tolerance of the integrator (default 1e-6).
+    --atol <f>                Absolute tolerance of the integrator (default 1e-9).
      ";
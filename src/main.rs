@@ -1,8 +1,8 @@
 use std::env;
 use std::process;
 
-use reaction_net::run;
 use reaction_net::Config;
+use reaction_net::run;
 
 fn main() {
     let args = env::args();
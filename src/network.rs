@@ -1,16 +1,72 @@
+pub mod algebra;
+pub mod compartment;
 pub mod complex;
+pub mod definition;
+pub mod event;
+pub mod expression;
+pub mod formula;
+pub mod parameter;
 pub mod reaction;
+pub mod species;
 
-use crate::data::Registry;
+use std::collections::{BTreeMap, BTreeSet};
 
+use crate::data::{Matrix, Rational, Registry};
+
+pub use algebra::{Subexpressions, eliminate_common_subexpressions};
+pub use compartment::CompartmentAttributes;
 pub use complex::{Complex, SpeciesId, StoichCoef};
+pub use definition::{Definition, DefinitionError};
+pub use event::{AssignOp, Assignment, Comparison, Event, Trigger};
+pub use expression::{BinaryOp, Expr, Function, Notation};
+pub use formula::{ChemicalFormula, Imbalance};
+pub use parameter::{Declaration, ParameterAttributes, ParameterWarning, Prior};
 pub use reaction::Reaction;
+pub use species::{EliminatedSpecies, SpeciesAttributes};
+
+pub type SpeciesRegistry = Registry<String, SpeciesAttributes>;
+pub type ParameterRegistry = Registry<String, ParameterAttributes>;
+pub type ParameterId = usize;
+pub type CompartmentRegistry = Registry<String, CompartmentAttributes>;
+pub type CompartmentId = usize;
+
+// direction of optimization for flux balance analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sense {
+    Maximize,
+    Minimize,
+}
+
+// how a lint is reported, set in the model by `allow` and `deny`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+// flux constraint declared in the model, e.g. `flux uptake [-10, 0]`
+#[derive(Debug, Clone)]
+pub struct FluxBound {
+    pub reaction: String,
+    pub lower: f64,
+    pub upper: f64,
+}
 
-pub type SpeciesRegistry = Registry<String>;
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Network {
     species: SpeciesRegistry,
     reactions: Vec<Reaction>,
+    parameters: ParameterRegistry,
+    compartments: CompartmentRegistry,
+    flux_bounds: Vec<FluxBound>,
+    objective: Option<(String, Sense)>,
+    events: Vec<Event>,
+    // rules and observables, in dependency order once resolved
+    definitions: Vec<Definition>,
+    // lint codes with the level the model sets for them
+    lint_levels: Vec<(String, LintLevel)>,
+    eliminated: Vec<EliminatedSpecies>,
 }
 
 impl Network {
@@ -18,6 +74,14 @@ impl Network {
         Self {
             species: SpeciesRegistry::new(),
             reactions: Vec::new(),
+            parameters: ParameterRegistry::new(),
+            compartments: CompartmentRegistry::new(),
+            flux_bounds: Vec::new(),
+            objective: None,
+            events: Vec::new(),
+            definitions: Vec::new(),
+            lint_levels: Vec::new(),
+            eliminated: Vec::new(),
         }
     }
 
@@ -33,4 +97,478 @@ impl Network {
         let id = self.register_species(s);
         complex.add_term(id, c);
     }
+
+    // explicit `param` declaration; replaces any earlier attributes
+    pub fn declare_parameter(
+        &mut self,
+        name: String,
+        mut attr: ParameterAttributes,
+    ) -> ParameterId {
+        let id = self.parameters.register(name);
+        attr.declaration = Declaration::Explicit;
+        *self.parameters.get_attributes_mut(id) = attr;
+        id
+    }
+
+    // add rate constants and other rate-law, event and rule symbols missing
+    // from the parameter table
+    pub fn resolve_parameters(&mut self) {
+        let laws = (0..self.reactions.len()).map(|i| {
+            let implicit = self.reactions[i].get_kinetics().is_none();
+            (self.rate_law(i).symbols(), implicit)
+        });
+        let events = self.events.iter().map(|e| (e.symbols(), false));
+        let definitions = self.definitions.iter().map(|d| (d.value.symbols(), false));
+        let symbols: Vec<_> = laws.chain(events).chain(definitions).collect();
+        for (names, implicit) in symbols {
+            for name in names {
+                if self.find_species(&name).is_some()
+                    || self.find_compartment(&name).is_some()
+                    || self.find_definition(&name).is_some()
+                {
+                    continue;
+                }
+                if self.parameters.get_index(&name).is_none() {
+                    let id = self.parameters.register(name);
+                    if implicit {
+                        self.parameters.get_attributes_mut(id).declaration = Declaration::Implicit;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn register_compartment(&mut self, name: String) -> CompartmentId {
+        self.compartments.register(name)
+    }
+
+    // `name@compartment` places a species in a compartment
+    pub fn register_species_in(&mut self, name: String, compartment: String) -> SpeciesId {
+        let c = self.register_compartment(compartment);
+        let id = self.register_species(name);
+        self.species_attributes_mut(id).compartment = Some(c);
+        id
+    }
+
+    pub fn add_flux_bound(&mut self, bound: FluxBound) {
+        self.flux_bounds.push(bound);
+    }
+
+    pub fn set_objective(&mut self, reaction: String, sense: Sense) {
+        self.objective = Some((reaction, sense));
+    }
+
+    pub fn set_lint_level(&mut self, code: String, level: LintLevel) {
+        self.lint_levels.push((code, level));
+    }
+
+    pub fn add_eliminated_species(&mut self, species: EliminatedSpecies) {
+        self.eliminated.push(species);
+    }
+
+    pub fn eliminated_species(&self) -> &[EliminatedSpecies] {
+        &self.eliminated
+    }
+
+    // in order of declaration; later settings of a code take precedence
+    pub fn lint_levels(&self) -> &[(String, LintLevel)] {
+        &self.lint_levels
+    }
+
+    pub fn add_event(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn add_definition(&mut self, definition: Definition) {
+        self.definitions.push(definition);
+    }
+
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    pub fn find_definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|d| d.name == name)
+    }
+
+    pub fn observables(&self) -> impl Iterator<Item = &Definition> {
+        self.definitions.iter().filter(|d| d.observable)
+    }
+
+    // check names and sort definitions so each follows those it uses
+    pub fn order_definitions(&mut self) -> Result<(), DefinitionError> {
+        for (k, d) in self.definitions.iter().enumerate() {
+            if self.definitions[..k].iter().any(|e| e.name == d.name) {
+                return Err(DefinitionError::Duplicate(d.name.clone()));
+            }
+            if self.find_species(&d.name).is_some() || self.find_parameter(&d.name).is_some() {
+                return Err(DefinitionError::Conflict(d.name.clone()));
+            }
+        }
+        self.definitions = definition::dependency_order(&self.definitions)?;
+        Ok(())
+    }
+
+    // the expression with every rule and observable written out in full
+    pub fn expand(&self, expr: &Expr) -> Expr {
+        self.definitions
+            .iter()
+            .rev()
+            .fold(expr.clone(), |e, d| e.substitute(&d.name, &d.value))
+    }
+
+    pub fn num_species(&self) -> usize {
+        self.species.len()
+    }
+
+    pub fn num_reactions(&self) -> usize {
+        self.reactions.len()
+    }
+
+    pub fn species_name(&self, id: SpeciesId) -> &str {
+        self.species.get_symbol(id)
+    }
+
+    pub fn species_attributes(&self, id: SpeciesId) -> &SpeciesAttributes {
+        self.species.get_attributes(id)
+    }
+
+    pub fn species_attributes_mut(&mut self, id: SpeciesId) -> &mut SpeciesAttributes {
+        self.species.get_attributes_mut(id)
+    }
+
+    // initial concentrations; undeclared species start at zero
+    pub fn initial_conditions(&self) -> Vec<f64> {
+        (0..self.num_species())
+            .map(|i| self.species_attributes(i).initial.unwrap_or(0.0))
+            .collect()
+    }
+
+    pub fn species_names(&self) -> &[String] {
+        self.species.symbols()
+    }
+
+    pub fn find_species(&self, name: &str) -> Option<SpeciesId> {
+        self.species.get_index(&name.to_string())
+    }
+
+    pub fn reactions(&self) -> &[Reaction] {
+        &self.reactions
+    }
+
+    // anonymous reactions are named by position: R1, R2, ...
+    pub fn reaction_name(&self, idx: usize) -> String {
+        match self.reactions[idx].get_name() {
+            Some(name) => name.to_string(),
+            None => format!("R{}", idx + 1),
+        }
+    }
+
+    pub fn find_reaction(&self, name: &str) -> Option<usize> {
+        (0..self.reactions.len()).find(|&i| self.reaction_name(i) == name)
+    }
+
+    // indices of inflow and outflow reactions
+    pub fn exchange_reactions(&self) -> Vec<usize> {
+        (0..self.reactions.len())
+            .filter(|&i| self.reactions[i].is_exchange())
+            .collect()
+    }
+
+    pub fn num_compartments(&self) -> usize {
+        self.compartments.len()
+    }
+
+    pub fn compartment_name(&self, id: CompartmentId) -> &str {
+        self.compartments.get_symbol(id)
+    }
+
+    pub fn find_compartment(&self, name: &str) -> Option<CompartmentId> {
+        self.compartments.get_index(&name.to_string())
+    }
+
+    pub fn compartment_attributes(&self, id: CompartmentId) -> &CompartmentAttributes {
+        self.compartments.get_attributes(id)
+    }
+
+    pub fn compartment_attributes_mut(&mut self, id: CompartmentId) -> &mut CompartmentAttributes {
+        self.compartments.get_attributes_mut(id)
+    }
+
+    pub fn species_volume(&self, id: SpeciesId) -> f64 {
+        match self.species_attributes(id).compartment {
+            Some(c) => self.compartment_attributes(c).volume(),
+            None => 1.0,
+        }
+    }
+
+    // a reaction happens in the compartment of its reactants,
+    // or of its products for inflow reactions
+    pub fn reaction_compartment(&self, idx: usize) -> Option<CompartmentId> {
+        let rxn = &self.reactions[idx];
+        rxn.get_reactants()
+            .terms()
+            .into_iter()
+            .chain(rxn.get_products().terms())
+            .find_map(|(id, _)| self.species_attributes(id).compartment)
+    }
+
+    pub fn reaction_volume(&self, idx: usize) -> f64 {
+        match self.reaction_compartment(idx) {
+            Some(c) => self.compartment_attributes(c).volume(),
+            None => 1.0,
+        }
+    }
+
+    // rate laws give concentration per time in the reaction's compartment, so
+    // d[x_i]/dt = sum_j N_ij V_j / V_i r_j; fixed species do not change
+    pub fn rate_matrix(&self) -> Matrix {
+        let mut m = self.stoichiometric_matrix();
+        for i in 0..m.rows() {
+            let fixed = self.species_attributes(i).is_fixed();
+            let vi = self.species_volume(i);
+            for j in 0..m.cols() {
+                m[(i, j)] = if fixed {
+                    0.0
+                } else {
+                    m[(i, j)] * self.reaction_volume(j) / vi
+                };
+            }
+        }
+        m
+    }
+
+    pub fn complex_formula(&self, complex: &Complex) -> String {
+        if complex.is_empty() {
+            return "0".to_string();
+        }
+        let terms: Vec<String> = complex
+            .terms()
+            .into_iter()
+            .map(|(id, c)| {
+                let name = expression::quote_symbol(self.species_name(id));
+                if c == Rational::ONE {
+                    name
+                } else {
+                    format!("{c} {name}")
+                }
+            })
+            .collect();
+        terms.join(" + ")
+    }
+
+    // reaction written as in the language, e.g. `A + B -> C`
+    pub fn reaction_formula(&self, idx: usize) -> String {
+        let rxn = &self.reactions[idx];
+        let arrow = if rxn.is_reversible() { "<->" } else { "->" };
+        format!(
+            "{} {arrow} {}",
+            self.complex_formula(rxn.get_reactants()),
+            self.complex_formula(rxn.get_products())
+        )
+    }
+
+    // reaction as first written, including any eliminated species
+    pub fn original_reaction_formula(&self, idx: usize) -> String {
+        match self.reactions[idx].get_original_formula() {
+            Some(formula) => formula.to_string(),
+            None => self.reaction_formula(idx),
+        }
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.parameters.len()
+    }
+
+    pub fn parameter_name(&self, id: ParameterId) -> &str {
+        self.parameters.get_symbol(id)
+    }
+
+    pub fn parameter_names(&self) -> &[String] {
+        self.parameters.symbols()
+    }
+
+    pub fn find_parameter(&self, name: &str) -> Option<ParameterId> {
+        self.parameters.get_index(&name.to_string())
+    }
+
+    pub fn parameter_attributes(&self, id: ParameterId) -> &ParameterAttributes {
+        self.parameters.get_attributes(id)
+    }
+
+    pub fn parameter_attributes_mut(&mut self, id: ParameterId) -> &mut ParameterAttributes {
+        self.parameters.get_attributes_mut(id)
+    }
+
+    // default parameter vector in parameter table order
+    pub fn parameter_values(&self) -> Vec<f64> {
+        (0..self.num_parameters())
+            .map(|i| self.parameter_attributes(i).value_or_default())
+            .collect()
+    }
+
+    // rate constants of the default mass-action rate law
+    pub fn rate_constant_names(&self, idx: usize) -> (String, Option<String>) {
+        let name = self.reaction_name(idx);
+        if self.reactions[idx].is_reversible() {
+            (format!("kf_{name}"), Some(format!("kr_{name}")))
+        } else {
+            (format!("k_{name}"), None)
+        }
+    }
+
+    // mass action: k * prod(reactants) [- kr * prod(products)]
+    pub fn mass_action(&self, idx: usize) -> Expr {
+        let rxn = &self.reactions[idx];
+        let (kf, kr) = self.rate_constant_names(idx);
+        let forward = self.monomial(Expr::symbol(kf), rxn.get_reactants());
+        match kr {
+            Some(kr) => {
+                let reverse = self.monomial(Expr::symbol(kr), rxn.get_products());
+                Expr::binary(BinaryOp::Sub, forward, reverse)
+            }
+            None => forward,
+        }
+    }
+
+    fn monomial(&self, coef: Expr, complex: &Complex) -> Expr {
+        complex.terms().into_iter().fold(coef, |acc, (id, c)| {
+            let s = Expr::symbol(self.species_name(id));
+            let factor = if c == Rational::ONE {
+                s
+            } else {
+                Expr::binary(BinaryOp::Pow, s, Expr::Number(c.to_f64()))
+            };
+            Expr::binary(BinaryOp::Mul, acc, factor)
+        })
+    }
+
+    // explicit kinetics, with rules written out, or the default mass-action
+    // rate law
+    pub fn rate_law(&self, idx: usize) -> Expr {
+        match self.reactions[idx].get_kinetics() {
+            Some(rate) => self.expand(rate),
+            None => self.mass_action(idx),
+        }
+    }
+
+    // parameters used without declaration, and declarations never used
+    pub fn parameter_warnings(&self) -> Vec<ParameterWarning> {
+        let mut used = std::collections::BTreeSet::new();
+        for i in 0..self.reactions.len() {
+            used.extend(self.rate_law(i).symbols());
+        }
+        for event in &self.events {
+            used.extend(event.symbols());
+        }
+        for d in &self.definitions {
+            used.extend(d.value.symbols());
+        }
+        let mut warnings = Vec::new();
+        for (id, name) in self.parameter_names().iter().enumerate() {
+            match self.parameter_attributes(id).declaration {
+                Declaration::Undeclared if used.contains(name) => {
+                    warnings.push(ParameterWarning::Undeclared(name.clone()))
+                }
+                Declaration::Explicit if !used.contains(name) => {
+                    warnings.push(ParameterWarning::Unused(name.clone()))
+                }
+                _ => {}
+            }
+        }
+        warnings
+    }
+
+    // declared formula of a species, or one read from its name
+    pub fn species_formula(&self, id: SpeciesId) -> Option<ChemicalFormula> {
+        self.species_attributes(id)
+            .formula
+            .clone()
+            .or_else(|| ChemicalFormula::inferred(self.species_name(id)))
+    }
+
+    // reactions that create or destroy atoms or charge; exchange reactions
+    // and those with species of unknown formula are not checked
+    pub fn balance_warnings(&self) -> Vec<Imbalance> {
+        let formulas: Vec<Option<ChemicalFormula>> = (0..self.num_species())
+            .map(|i| self.species_formula(i))
+            .collect();
+        let stoichiometry = self.exact_stoichiometric_matrix();
+        let mut warnings = Vec::new();
+        for (j, rxn) in self.reactions.iter().enumerate() {
+            if rxn.is_exchange() {
+                continue;
+            }
+            let involved: BTreeSet<SpeciesId> = rxn
+                .get_reactants()
+                .terms()
+                .into_iter()
+                .chain(rxn.get_products().terms())
+                .map(|(i, _)| i)
+                .collect();
+            if involved.iter().any(|&i| formulas[i].is_none()) {
+                continue;
+            }
+            // a reaction whose totals overflow exact arithmetic is not checked
+            let totals = || -> Option<(BTreeMap<String, Rational>, Rational)> {
+                let mut elements: BTreeMap<String, Rational> = BTreeMap::new();
+                let mut charge = Rational::ZERO;
+                for &i in &involved {
+                    let c = stoichiometry[(i, j)];
+                    let formula = formulas[i].as_ref().expect("checked above");
+                    for (element, &n) in &formula.elements {
+                        let n = Rational::try_from(n).ok()?;
+                        let total = elements.entry(element.clone()).or_default();
+                        *total = total.checked_add(c.checked_mul(n)?)?;
+                    }
+                    charge = charge.checked_add(c.checked_mul(Rational::from(formula.charge))?)?;
+                }
+                Some((elements, charge))
+            };
+            let Some((elements, charge)) = totals() else {
+                continue;
+            };
+            let elements: Vec<(String, Rational)> =
+                elements.into_iter().filter(|(_, n)| !n.is_zero()).collect();
+            if !elements.is_empty() || !charge.is_zero() {
+                warnings.push(Imbalance {
+                    reaction: self.reaction_name(j),
+                    elements,
+                    charge,
+                });
+            }
+        }
+        warnings
+    }
+
+    pub fn flux_bounds(&self) -> &[FluxBound] {
+        &self.flux_bounds
+    }
+
+    pub fn objective(&self) -> Option<(&str, Sense)> {
+        self.objective.as_ref().map(|(r, s)| (r.as_str(), *s))
+    }
+
+    // rows are species, columns are reactions
+    pub fn stoichiometric_matrix(&self) -> Matrix {
+        self.exact_stoichiometric_matrix().map(|c| c.to_f64())
+    }
+
+    // stoichiometry without rounding, for structural analysis
+    pub fn exact_stoichiometric_matrix(&self) -> Matrix<Rational> {
+        let mut n = Matrix::zeros(self.num_species(), self.num_reactions());
+        for (j, rxn) in self.reactions.iter().enumerate() {
+            for (id, c) in rxn.get_reactants().terms() {
+                n[(id, j)] -= c;
+            }
+            for (id, c) in rxn.get_products().terms() {
+                n[(id, j)] += c;
+            }
+        }
+        n
+    }
 }
@@ -0,0 +1,659 @@
+// symbolic algebra on rate-law expressions: differentiation, simplification
+// and common-subexpression elimination
+//
+// Simplification writes sums as a constant plus coefficients times products,
+// and products as a coefficient times powers of distinct bases. Like terms
+// and like factors are collected, constants are folded, and a product with a
+// single sum factor inside a sum is distributed so that its terms can cancel.
+// Sums are never expanded otherwise, so rate laws keep their shape.
+use std::collections::{BTreeSet, HashMap};
+
+use super::expression::{BinaryOp, Expr, Function};
+
+impl Expr {
+    fn contains(&self, name: &str) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Symbol(s) => s == name,
+            Expr::Neg(e) | Expr::Call(_, e) => e.contains(name),
+            Expr::Binary(_, a, b) => a.contains(name) || b.contains(name),
+        }
+    }
+
+    // partial derivative with respect to the symbol `name`, a species or a
+    // parameter; zeros and ones are folded away as the result is built
+    pub fn derivative(&self, name: &str) -> Expr {
+        if !self.contains(name) {
+            return Expr::Number(0.0);
+        }
+        match self {
+            Expr::Number(_) => Expr::Number(0.0),
+            Expr::Symbol(_) => Expr::Number(1.0),
+            Expr::Neg(e) => neg(e.derivative(name)),
+            Expr::Binary(op, a, b) => {
+                let da = a.derivative(name);
+                let db = b.derivative(name);
+                let (a, b) = (a.as_ref().clone(), b.as_ref().clone());
+                match op {
+                    BinaryOp::Add => add(da, db),
+                    BinaryOp::Sub => sub(da, db),
+                    BinaryOp::Mul => add(mul(da, b), mul(a, db)),
+                    BinaryOp::Div => div(
+                        sub(mul(da, b.clone()), mul(a, db)),
+                        pow(b, Expr::Number(2.0)),
+                    ),
+                    // d(a^n) = n a^(n-1) da for exponents free of `name`
+                    BinaryOp::Pow if !b.contains(name) => {
+                        let lowered = pow(a, sub(b.clone(), Expr::Number(1.0)));
+                        mul(mul(b, lowered), da)
+                    }
+                    // d(a^b) = a^b (db ln a + b da / a)
+                    BinaryOp::Pow => {
+                        let log_a = Expr::Call(Function::Log, Box::new(a.clone()));
+                        let inner = add(mul(db, log_a), div(mul(b.clone(), da), a.clone()));
+                        mul(pow(a, b), inner)
+                    }
+                }
+            }
+            Expr::Call(f, e) => {
+                let de = e.derivative(name);
+                let e = e.as_ref().clone();
+                let outer = match f {
+                    Function::Exp => Expr::Call(Function::Exp, Box::new(e)),
+                    Function::Log => div(Expr::Number(1.0), e),
+                    Function::Sqrt => {
+                        div(Expr::Number(0.5), Expr::Call(Function::Sqrt, Box::new(e)))
+                    }
+                    Function::Abs => div(e.clone(), Expr::Call(Function::Abs, Box::new(e))),
+                };
+                mul(outer, de)
+            }
+        }
+    }
+
+    // an equivalent expression with constants folded and like terms collected
+    pub fn simplify(&self) -> Expr {
+        match self {
+            Expr::Number(_) | Expr::Symbol(_) => self.clone(),
+            Expr::Neg(e) => Sum::of(&e.simplify()).scaled(-1.0).build(),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.simplify(), b.simplify());
+                match op {
+                    BinaryOp::Add | BinaryOp::Sub => {
+                        let sign = if *op == BinaryOp::Add { 1.0 } else { -1.0 };
+                        let mut sum = Sum::of(&a);
+                        sum.extend(Sum::of(&b).scaled(sign));
+                        sum.build()
+                    }
+                    BinaryOp::Mul => Product::of(&a).times(&Product::of(&b)).build(),
+                    BinaryOp::Div => Product::of(&a).times(&Product::of(&b).raised(-1.0)).build(),
+                    BinaryOp::Pow => simplify_power(a, b),
+                }
+            }
+            Expr::Call(f, e) => {
+                let e = e.simplify();
+                match (f, e) {
+                    (f, Expr::Number(x)) if f.apply(x).is_finite() => Expr::Number(f.apply(x)),
+                    (Function::Log, Expr::Call(Function::Exp, inner)) => *inner,
+                    (Function::Exp, Expr::Call(Function::Log, inner)) => *inner,
+                    (f, e) => Expr::Call(*f, Box::new(e)),
+                }
+            }
+        }
+    }
+
+    // the expression with every occurrence of the symbol `name` replaced
+    pub fn substitute(&self, name: &str, replacement: &Expr) -> Expr {
+        match self {
+            Expr::Symbol(s) if s == name => replacement.clone(),
+            Expr::Number(_) | Expr::Symbol(_) => self.clone(),
+            Expr::Neg(e) => Expr::Neg(Box::new(e.substitute(name, replacement))),
+            Expr::Binary(op, a, b) => Expr::binary(
+                *op,
+                a.substitute(name, replacement),
+                b.substitute(name, replacement),
+            ),
+            Expr::Call(f, e) => Expr::Call(*f, Box::new(e.substitute(name, replacement))),
+        }
+    }
+
+    fn is_compound(&self) -> bool {
+        match self {
+            Expr::Number(_) | Expr::Symbol(_) => false,
+            Expr::Neg(e) => e.is_compound(),
+            Expr::Binary(..) | Expr::Call(..) => true,
+        }
+    }
+}
+
+// constructors used by `derivative` that fold constant operands
+fn neg(e: Expr) -> Expr {
+    match e {
+        Expr::Number(x) => Expr::Number(-x),
+        Expr::Neg(inner) => *inner,
+        e => Expr::Neg(Box::new(e)),
+    }
+}
+
+fn add(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) => Expr::Number(x + y),
+        (Expr::Number(x), e) | (e, Expr::Number(x)) if x == 0.0 => e,
+        (a, b) => Expr::binary(BinaryOp::Add, a, b),
+    }
+}
+
+fn sub(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) => Expr::Number(x - y),
+        (a, Expr::Number(0.0)) => a,
+        (Expr::Number(0.0), b) => neg(b),
+        (a, b) => Expr::binary(BinaryOp::Sub, a, b),
+    }
+}
+
+fn mul(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) => Expr::Number(x * y),
+        (Expr::Number(x), _) | (_, Expr::Number(x)) if x == 0.0 => Expr::Number(0.0),
+        (Expr::Number(x), e) | (e, Expr::Number(x)) if x == 1.0 => e,
+        (a, b) => Expr::binary(BinaryOp::Mul, a, b),
+    }
+}
+
+fn div(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Number(0.0), _) => Expr::Number(0.0),
+        (a, Expr::Number(1.0)) => a,
+        (a, b) => Expr::binary(BinaryOp::Div, a, b),
+    }
+}
+
+fn pow(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (_, Expr::Number(0.0)) => Expr::Number(1.0),
+        (a, Expr::Number(1.0)) => a,
+        (a, b) => Expr::binary(BinaryOp::Pow, a, b),
+    }
+}
+
+// a^b for simplified a and b
+fn simplify_power(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) if x.powf(y).is_finite() => Expr::Number(x.powf(y)),
+        (_, Expr::Number(0.0)) => Expr::Number(1.0),
+        (a, Expr::Number(1.0)) => a,
+        // integer powers distribute over products and multiply exponents
+        (a, Expr::Number(n)) if n.fract() == 0.0 => Product::of(&a).raised(n).build(),
+        (a, b) => Expr::binary(BinaryOp::Pow, a, b),
+    }
+}
+
+fn add_exponents(a: &Expr, b: &Expr) -> Expr {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) => Expr::Number(x + y),
+        _ => Expr::binary(BinaryOp::Add, a.clone(), b.clone()).simplify(),
+    }
+}
+
+// coefficient times powers of distinct bases
+#[derive(Debug, Clone)]
+struct Product {
+    coefficient: f64,
+    factors: Vec<(Expr, Expr)>,
+}
+
+impl Product {
+    fn constant(coefficient: f64) -> Self {
+        Self {
+            coefficient,
+            factors: Vec::new(),
+        }
+    }
+
+    // decompose a simplified expression
+    fn of(e: &Expr) -> Product {
+        match e {
+            Expr::Number(x) => Product::constant(*x),
+            Expr::Neg(inner) => Product::of(inner).scaled(-1.0),
+            Expr::Binary(BinaryOp::Mul, a, b) => Product::of(a).times(&Product::of(b)),
+            Expr::Binary(BinaryOp::Div, a, b) => Product::of(a).times(&Product::of(b).raised(-1.0)),
+            Expr::Binary(BinaryOp::Pow, base, exponent) => {
+                let mut p = Product::constant(1.0);
+                p.multiply(base, exponent);
+                p
+            }
+            e => {
+                let mut p = Product::constant(1.0);
+                p.multiply(e, &Expr::Number(1.0));
+                p
+            }
+        }
+    }
+
+    fn scaled(mut self, c: f64) -> Self {
+        self.coefficient *= c;
+        self
+    }
+
+    fn multiply(&mut self, base: &Expr, exponent: &Expr) {
+        if let (Expr::Number(x), Expr::Number(y)) = (base, exponent) {
+            let folded = self.coefficient * x.powf(*y);
+            if folded.is_finite() {
+                self.coefficient = folded;
+                return;
+            }
+        }
+        match self.factors.iter_mut().find(|(b, _)| b == base) {
+            Some((_, e)) => *e = add_exponents(e, exponent),
+            None => self.factors.push((base.clone(), exponent.clone())),
+        }
+    }
+
+    fn times(mut self, other: &Product) -> Self {
+        self.coefficient *= other.coefficient;
+        for (base, exponent) in &other.factors {
+            self.multiply(base, exponent);
+        }
+        self
+    }
+
+    fn raised(self, n: f64) -> Self {
+        Self {
+            coefficient: self.coefficient.powf(n),
+            factors: self
+                .factors
+                .into_iter()
+                .map(|(base, exponent)| {
+                    let exponent = match exponent {
+                        Expr::Number(x) => Expr::Number(x * n),
+                        e => Expr::binary(BinaryOp::Mul, Expr::Number(n), e).simplify(),
+                    };
+                    (base, exponent)
+                })
+                .collect(),
+        }
+    }
+
+    fn remove_unit_powers(&mut self) {
+        self.factors.retain(|(_, e)| *e != Expr::Number(0.0));
+    }
+
+    // identifies products that differ only in their coefficient
+    fn key(&self) -> String {
+        let mut parts: Vec<String> = self
+            .factors
+            .iter()
+            .map(|(base, exponent)| format!("({base})^({exponent})"))
+            .collect();
+        parts.sort();
+        parts.join("*")
+    }
+
+    // the product with its coefficient replaced by its magnitude
+    fn build_magnitude(&self) -> Expr {
+        let power = |base: &Expr, exponent: f64| pow(base.clone(), Expr::Number(exponent));
+        let mut numerator: Vec<Expr> = Vec::new();
+        let mut denominator: Vec<Expr> = Vec::new();
+        for (base, exponent) in &self.factors {
+            match exponent {
+                Expr::Number(0.0) => {}
+                Expr::Number(x) if *x < 0.0 => denominator.push(power(base, -x)),
+                Expr::Number(x) => numerator.push(power(base, *x)),
+                e => numerator.push(Expr::binary(BinaryOp::Pow, base.clone(), e.clone())),
+            }
+        }
+        let magnitude = self.coefficient.abs();
+        if magnitude != 1.0 || numerator.is_empty() {
+            numerator.insert(0, Expr::Number(magnitude));
+        }
+        let fold = |factors: Vec<Expr>| {
+            factors
+                .into_iter()
+                .reduce(|a, b| Expr::binary(BinaryOp::Mul, a, b))
+        };
+        let numerator = fold(numerator).expect("numerator has a factor");
+        match fold(denominator) {
+            Some(d) => Expr::binary(BinaryOp::Div, numerator, d),
+            None => numerator,
+        }
+    }
+
+    fn build(mut self) -> Expr {
+        self.remove_unit_powers();
+        if self.coefficient == 0.0 {
+            return Expr::Number(0.0);
+        }
+        if self.factors.is_empty() {
+            return Expr::Number(self.coefficient);
+        }
+        let magnitude = self.build_magnitude();
+        if self.coefficient > 0.0 {
+            magnitude
+        } else if self.coefficient == -1.0 {
+            Expr::Neg(Box::new(magnitude))
+        } else {
+            negate_leading(magnitude)
+        }
+    }
+}
+
+// negate the literal coefficient leading a product, e.g. `2 * x / y`
+fn negate_leading(e: Expr) -> Expr {
+    match e {
+        Expr::Number(x) => Expr::Number(-x),
+        Expr::Binary(op @ (BinaryOp::Mul | BinaryOp::Div), a, b) => {
+            Expr::Binary(op, Box::new(negate_leading(*a)), b)
+        }
+        e => Expr::Neg(Box::new(e)),
+    }
+}
+
+// constant plus a combination of products
+#[derive(Debug, Clone)]
+struct Sum {
+    constant: f64,
+    terms: Vec<Product>,
+}
+
+impl Sum {
+    // decompose a simplified expression
+    fn of(e: &Expr) -> Sum {
+        let mut sum = Sum {
+            constant: 0.0,
+            terms: Vec::new(),
+        };
+        match e {
+            Expr::Binary(BinaryOp::Add, a, b) => {
+                sum.extend(Sum::of(a));
+                sum.extend(Sum::of(b));
+            }
+            Expr::Binary(BinaryOp::Sub, a, b) => {
+                sum.extend(Sum::of(a));
+                sum.extend(Sum::of(b).scaled(-1.0));
+            }
+            Expr::Neg(inner) => sum.extend(Sum::of(inner).scaled(-1.0)),
+            e => sum.add_product(Product::of(e)),
+        }
+        sum
+    }
+
+    fn scaled(mut self, c: f64) -> Self {
+        self.constant *= c;
+        for term in &mut self.terms {
+            term.coefficient *= c;
+        }
+        self
+    }
+
+    fn extend(&mut self, other: Sum) {
+        self.constant += other.constant;
+        for term in other.terms {
+            self.add_term(term);
+        }
+    }
+
+    // a product with exactly one sum factor is distributed over its terms
+    fn add_product(&mut self, mut product: Product) {
+        product.remove_unit_powers();
+        let sums: Vec<usize> = (0..product.factors.len())
+            .filter(|&k| {
+                matches!(
+                    product.factors[k],
+                    (
+                        Expr::Binary(BinaryOp::Add | BinaryOp::Sub, _, _),
+                        Expr::Number(1.0)
+                    )
+                )
+            })
+            .collect();
+        if let [k] = sums[..] {
+            let (inner, _) = product.factors.remove(k);
+            for term in Sum::of(&inner).terms_with_constant() {
+                let coefficient = term.coefficient;
+                let distributed = Product {
+                    coefficient: product.coefficient,
+                    factors: product.factors.clone(),
+                }
+                .times(&Product {
+                    coefficient,
+                    ..term
+                });
+                self.add_term(distributed);
+            }
+        } else {
+            self.add_term(product);
+        }
+    }
+
+    fn terms_with_constant(self) -> Vec<Product> {
+        let mut terms = self.terms;
+        if self.constant != 0.0 {
+            terms.push(Product::constant(self.constant));
+        }
+        terms
+    }
+
+    fn add_term(&mut self, mut term: Product) {
+        term.remove_unit_powers();
+        if term.factors.is_empty() {
+            self.constant += term.coefficient;
+            return;
+        }
+        let key = term.key();
+        match self.terms.iter_mut().find(|t| t.key() == key) {
+            Some(t) => t.coefficient += term.coefficient,
+            None => self.terms.push(term),
+        }
+    }
+
+    fn build(self) -> Expr {
+        let mut terms: Vec<Product> = self
+            .terms
+            .into_iter()
+            .filter(|t| t.coefficient != 0.0)
+            .collect();
+        if self.constant != 0.0 {
+            terms.push(Product::constant(self.constant));
+        }
+        let mut out: Option<Expr> = None;
+        for term in terms {
+            let negative = term.coefficient < 0.0;
+            out = Some(match out {
+                None => term.build(),
+                Some(acc) => {
+                    let op = if negative {
+                        BinaryOp::Sub
+                    } else {
+                        BinaryOp::Add
+                    };
+                    Expr::binary(op, acc, term.build_magnitude())
+                }
+            });
+        }
+        out.unwrap_or(Expr::Number(0.0))
+    }
+}
+
+// expressions sharing named temporaries for their repeated subexpressions
+#[derive(Debug, Clone)]
+pub struct Subexpressions {
+    // temporaries in order of definition; each may use earlier ones
+    pub definitions: Vec<(String, Expr)>,
+    pub expressions: Vec<Expr>,
+}
+
+fn count_subexpressions(e: &Expr, counts: &mut HashMap<String, usize>) {
+    if !e.is_compound() {
+        return;
+    }
+    *counts.entry(e.to_string()).or_default() += 1;
+    match e {
+        Expr::Neg(inner) | Expr::Call(_, inner) => count_subexpressions(inner, counts),
+        Expr::Binary(_, a, b) => {
+            count_subexpressions(a, counts);
+            count_subexpressions(b, counts);
+        }
+        _ => {}
+    }
+}
+
+struct Eliminator<'a> {
+    counts: HashMap<String, usize>,
+    names: HashMap<String, String>,
+    definitions: Vec<(String, Expr)>,
+    fresh: Box<dyn FnMut() -> String + 'a>,
+}
+
+impl Eliminator<'_> {
+    fn rewrite(&mut self, e: &Expr) -> Expr {
+        if !e.is_compound() {
+            return e.clone();
+        }
+        let key = e.to_string();
+        if let Some(name) = self.names.get(&key) {
+            return Expr::Symbol(name.clone());
+        }
+        let rewritten = match e {
+            Expr::Neg(inner) => Expr::Neg(Box::new(self.rewrite(inner))),
+            Expr::Call(f, inner) => Expr::Call(*f, Box::new(self.rewrite(inner))),
+            Expr::Binary(op, a, b) => Expr::binary(*op, self.rewrite(a), self.rewrite(b)),
+            e => e.clone(),
+        };
+        if self.counts[&key] < 2 {
+            return rewritten;
+        }
+        let name = (self.fresh)();
+        self.names.insert(key, name.clone());
+        self.definitions.push((name.clone(), rewritten));
+        Expr::Symbol(name)
+    }
+}
+
+// name the subexpressions occurring more than once among `expressions`;
+// temporaries are called `{prefix}1`, `{prefix}2`, ..., skipping names
+// for which `taken` holds and symbols of the expressions
+pub fn eliminate_common_subexpressions(
+    expressions: &[Expr],
+    prefix: &str,
+    taken: impl Fn(&str) -> bool,
+) -> Subexpressions {
+    let mut counts = HashMap::new();
+    let mut symbols = BTreeSet::new();
+    for e in expressions {
+        count_subexpressions(e, &mut counts);
+        symbols.extend(e.symbols());
+    }
+    let mut index = 0;
+    let fresh = move || loop {
+        index += 1;
+        let name = format!("{prefix}{index}");
+        if !taken(&name) && !symbols.contains(&name) {
+            return name;
+        }
+    };
+    let mut eliminator = Eliminator {
+        counts,
+        names: HashMap::new(),
+        definitions: Vec::new(),
+        fresh: Box::new(fresh),
+    };
+    let mut rewritten: Vec<Expr> = expressions.iter().map(|e| eliminator.rewrite(e)).collect();
+    let mut definitions = eliminator.definitions;
+
+    // a repeated subexpression nested in another repeated one may be left
+    // with a single use; put it back in place
+    let uses = |name: &str, definitions: &[(String, Expr)], rewritten: &[Expr]| {
+        definitions
+            .iter()
+            .map(|(_, e)| e)
+            .chain(rewritten)
+            .map(|e| count_uses(e, name))
+            .sum::<usize>()
+    };
+    let mut k = 0;
+    while k < definitions.len() {
+        let name = definitions[k].0.clone();
+        if uses(&name, &definitions, &rewritten) == 1 {
+            let (_, value) = definitions.remove(k);
+            for (_, e) in definitions.iter_mut() {
+                *e = e.substitute(&name, &value);
+            }
+            for e in rewritten.iter_mut() {
+                *e = e.substitute(&name, &value);
+            }
+        } else {
+            k += 1;
+        }
+    }
+    Subexpressions {
+        definitions,
+        expressions: rewritten,
+    }
+}
+
+fn count_uses(e: &Expr, name: &str) -> usize {
+    match e {
+        Expr::Number(_) => 0,
+        Expr::Symbol(s) => usize::from(s == name),
+        Expr::Neg(inner) | Expr::Call(_, inner) => count_uses(inner, name),
+        Expr::Binary(_, a, b) => count_uses(a, name) + count_uses(b, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn expr(text: &str) -> Expr {
+        let text = format!("observable f = {text}\n");
+        let network = Parser::new(Scanner::scan(&text)).parse().unwrap();
+        network.definitions()[0].value.clone()
+    }
+
+    fn derivative(text: &str, name: &str) -> String {
+        expr(text).derivative(name).simplify().to_string()
+    }
+
+    #[test]
+    fn differentiates_products_and_quotients() {
+        assert_eq!(derivative("a * x * x", "x"), "2 * a * x");
+        assert_eq!(derivative("x / (k + x)", "x"), "k / (k + x) ^ 2");
+        assert_eq!(derivative("k * y", "x"), "0");
+    }
+
+    #[test]
+    fn differentiates_powers_and_calls() {
+        assert_eq!(derivative("x ^ 3", "x"), "3 * x ^ 2");
+        assert_eq!(derivative("a ^ x", "x"), "a ^ x * log(a)");
+        assert_eq!(derivative("exp(2 * x)", "x"), "2 * exp(2 * x)");
+        assert_eq!(derivative("log(x)", "x"), "1 / x");
+        assert_eq!(derivative("sqrt(x)", "x"), "0.5 / sqrt(x)");
+    }
+
+    #[test]
+    fn simplifies_identities() {
+        let simplified = |text| expr(text).simplify().to_string();
+        assert_eq!(simplified("0 * x"), "0");
+        assert_eq!(simplified("x + 0"), "x");
+        assert_eq!(simplified("x / x"), "1");
+        assert_eq!(simplified("1 * x ^ 1"), "x");
+        assert_eq!(simplified("2 * x - x"), "x");
+        assert_eq!(simplified("(a + b) * x / (a + b)"), "x");
+    }
+
+    #[test]
+    fn shares_repeated_subexpressions() {
+        let expressions = [expr("k * x / (k + x)"), expr("(k + x) ^ 2")];
+        // the first temporary name is taken
+        let shared = eliminate_common_subexpressions(&expressions, "t", |name| name == "t1");
+        assert_eq!(shared.definitions.len(), 1);
+        let (name, value) = &shared.definitions[0];
+        assert_eq!(name, "t2");
+        assert_eq!(value.to_string(), "k + x");
+        let rewritten: Vec<String> = shared.expressions.iter().map(|e| e.to_string()).collect();
+        assert_eq!(rewritten, ["k * x / t2", "t2 ^ 2"]);
+    }
+}
@@ -0,0 +1,14 @@
+// per-compartment attributes declared with `compartment`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CompartmentAttributes {
+    pub size: Option<f64>,
+    pub unit: Option<String>,
+    pub description: Option<String>,
+}
+
+impl CompartmentAttributes {
+    // compartments without a declared size have unit volume
+    pub fn volume(&self) -> f64 {
+        self.size.unwrap_or(1.0)
+    }
+}
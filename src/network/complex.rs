@@ -1,7 +1,10 @@
 use std::collections::HashMap;
+
+use crate::data::Rational;
 pub type SpeciesId = usize;
-pub type StoichCoef = u64;
-#[derive(Debug)]
+// stoichiometric coefficients may be fractional, e.g. `1/2 O2` or `0.5 O2`
+pub type StoichCoef = Rational;
+#[derive(Debug, Default, Clone)]
 pub struct Complex {
     terms: HashMap<SpeciesId, StoichCoef>,
 }
@@ -19,4 +22,19 @@ impl Complex {
             .and_modify(|x| *x += coef)
             .or_insert(coef);
     }
+
+    pub fn coefficient(&self, id: SpeciesId) -> StoichCoef {
+        self.terms.get(&id).copied().unwrap_or(Rational::ZERO)
+    }
+
+    // terms ordered by species id
+    pub fn terms(&self) -> Vec<(SpeciesId, StoichCoef)> {
+        let mut terms: Vec<_> = self.terms.iter().map(|(&id, &c)| (id, c)).collect();
+        terms.sort_unstable_by_key(|&(id, _)| id);
+        terms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
 }
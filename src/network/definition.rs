@@ -0,0 +1,152 @@
+// derived quantities: `rule Keq = kf / kr` names an expression that rate
+// laws may use in place of writing it out, and `observable total_E = E + ES`
+// is additionally recorded in simulation output and generated code
+use std::error::Error;
+use std::fmt;
+
+use super::expression::Expr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub value: Expr,
+    pub observable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefinitionError {
+    Duplicate(String),
+    // the name is already a species or declared parameter
+    Conflict(String),
+    // definitions that depend on each other, with the first repeated
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DefinitionError::Duplicate(name) => write!(f, "'{name}' is defined more than once"),
+            DefinitionError::Conflict(name) => write!(
+                f,
+                "'{name}' is defined by a rule but is also a species or parameter"
+            ),
+            DefinitionError::Cycle(names) => {
+                write!(f, "Rules depend on each other: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for DefinitionError {}
+
+// definitions reordered so that each comes after those it uses
+pub fn dependency_order(definitions: &[Definition]) -> Result<Vec<Definition>, DefinitionError> {
+    let index = |name: &str| definitions.iter().position(|d| d.name == name);
+    let uses: Vec<Vec<usize>> = definitions
+        .iter()
+        .map(|d| d.value.symbols().iter().filter_map(|s| index(s)).collect())
+        .collect();
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        New,
+        Open,
+        Done,
+    }
+    // depth-first search; reaching an open definition closes a cycle
+    fn visit(
+        k: usize,
+        uses: &[Vec<usize>],
+        marks: &mut [Mark],
+        path: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> Result<(), Vec<usize>> {
+        match marks[k] {
+            Mark::Done => return Ok(()),
+            Mark::Open => {
+                let start = path.iter().position(|&p| p == k).unwrap_or(0);
+                let mut cycle = path[start..].to_vec();
+                cycle.push(k);
+                return Err(cycle);
+            }
+            Mark::New => {}
+        }
+        marks[k] = Mark::Open;
+        path.push(k);
+        for &l in &uses[k] {
+            visit(l, uses, marks, path, order)?;
+        }
+        path.pop();
+        marks[k] = Mark::Done;
+        order.push(k);
+        Ok(())
+    }
+
+    let mut marks = vec![Mark::New; definitions.len()];
+    let mut order = Vec::with_capacity(definitions.len());
+    for k in 0..definitions.len() {
+        visit(k, &uses, &mut marks, &mut Vec::new(), &mut order).map_err(|cycle| {
+            DefinitionError::Cycle(
+                cycle
+                    .into_iter()
+                    .map(|k| definitions[k].name.clone())
+                    .collect(),
+            )
+        })?;
+    }
+    Ok(order.into_iter().map(|k| definitions[k].clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::BinaryOp;
+
+    // a definition whose value is the sum of the given symbols
+    fn definition(name: &str, uses: &[&str]) -> Definition {
+        let value = uses
+            .iter()
+            .map(|&s| Expr::symbol(s))
+            .reduce(|a, b| Expr::binary(BinaryOp::Add, a, b))
+            .unwrap_or(Expr::Number(1.0));
+        Definition {
+            name: name.to_string(),
+            value,
+            observable: false,
+        }
+    }
+
+    #[test]
+    fn definitions_follow_those_they_use() {
+        let definitions = [
+            definition("c", &["a", "b"]),
+            definition("b", &["a", "x"]),
+            definition("a", &[]),
+        ];
+        let order: Vec<String> = dependency_order(&definitions)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(order, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn reports_the_cycle() {
+        let definitions = [
+            definition("a", &["x"]),
+            definition("b", &["c"]),
+            definition("c", &["a", "d"]),
+            definition("d", &["b"]),
+        ];
+        assert_eq!(
+            dependency_order(&definitions),
+            Err(DefinitionError::Cycle(vec![
+                "b".to_string(),
+                "c".to_string(),
+                "d".to_string(),
+                "b".to_string()
+            ]))
+        );
+    }
+}
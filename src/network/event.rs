@@ -0,0 +1,117 @@
+// discrete changes during a simulation, declared as
+// `when ATP < 0.1 then k_syn = 0` or `at t = 50: S += 10`
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::expression::{Expr, quote_symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    pub fn holds(&self, a: f64, b: f64) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterEqual => a >= b,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterEqual => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trigger {
+    // fires whenever the condition turns from false to true
+    When(Expr, Comparison, Expr),
+    // fires at each of the given times
+    At(Vec<f64>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    Set,
+    Add,
+    Subtract,
+}
+
+impl AssignOp {
+    pub fn apply(&self, old: f64, value: f64) -> f64 {
+        match self {
+            AssignOp::Set => value,
+            AssignOp::Add => old + value,
+            AssignOp::Subtract => old - value,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            AssignOp::Set => "=",
+            AssignOp::Add => "+=",
+            AssignOp::Subtract => "-=",
+        }
+    }
+}
+
+// new value of a species or parameter, computed before any is changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub target: String,
+    pub op: AssignOp,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub trigger: Trigger,
+    pub assignments: Vec<Assignment>,
+}
+
+impl Event {
+    // symbols of the condition and of the assignments, targets included
+    pub fn symbols(&self) -> BTreeSet<String> {
+        let mut out = BTreeSet::new();
+        if let Trigger::When(left, _, right) = &self.trigger {
+            out.extend(left.symbols());
+            out.extend(right.symbols());
+        }
+        for a in &self.assignments {
+            out.insert(a.target.clone());
+            out.extend(a.value.symbols());
+        }
+        out
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.trigger {
+            Trigger::When(left, cmp, right) => {
+                write!(f, "when {left} {} {right} then ", cmp.symbol())?
+            }
+            Trigger::At(times) => {
+                let times: Vec<String> = times.iter().map(|t| t.to_string()).collect();
+                write!(f, "at t = {}: ", times.join(", "))?
+            }
+        }
+        let assignments: Vec<String> = self
+            .assignments
+            .iter()
+            .map(|a| format!("{} {} {}", quote_symbol(&a.target), a.op.symbol(), a.value))
+            .collect();
+        write!(f, "{}", assignments.join(", "))
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+// rate-law expressions, e.g. `Vmax * S / (Km + S)`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Symbol(String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Exp,
+    Log,
+    Sqrt,
+    Abs,
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exp" => Some(Function::Exp),
+            "log" | "ln" => Some(Function::Log),
+            "sqrt" => Some(Function::Sqrt),
+            "abs" => Some(Function::Abs),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Exp => "exp",
+            Function::Log => "log",
+            Function::Sqrt => "sqrt",
+            Function::Abs => "abs",
+        }
+    }
+
+    pub fn apply(&self, x: f64) -> f64 {
+        match self {
+            Function::Exp => x.exp(),
+            Function::Log => x.ln(),
+            Function::Sqrt => x.sqrt(),
+            Function::Abs => x.abs(),
+        }
+    }
+}
+
+impl BinaryOp {
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Sub => 1,
+            BinaryOp::Mul | BinaryOp::Div => 2,
+            BinaryOp::Pow => 4,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Pow => "^",
+        }
+    }
+}
+
+impl Expr {
+    pub fn symbol<S: Into<String>>(name: S) -> Self {
+        Expr::Symbol(name.into())
+    }
+
+    pub fn binary(op: BinaryOp, left: Expr, right: Expr) -> Self {
+        Expr::Binary(op, Box::new(left), Box::new(right))
+    }
+
+    // names of all symbols appearing in the expression
+    pub fn symbols(&self) -> BTreeSet<String> {
+        let mut out = BTreeSet::new();
+        self.collect_symbols(&mut out);
+        out
+    }
+
+    fn collect_symbols(&self, out: &mut BTreeSet<String>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Symbol(s) => {
+                out.insert(s.clone());
+            }
+            Expr::Neg(e) | Expr::Call(_, e) => e.collect_symbols(out),
+            Expr::Binary(_, a, b) => {
+                a.collect_symbols(out);
+                b.collect_symbols(out);
+            }
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary(op, _, _) => op.precedence(),
+            Expr::Neg(_) => 3,
+            Expr::Number(x) if *x < 0.0 => 3,
+            _ => 5,
+        }
+    }
+}
+
+fn is_plain(s: &str) -> bool {
+    s.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && s.chars().next().is_some_and(|c| !c.is_ascii_digit())
+}
+
+// symbols that are not plain identifiers are quoted as in the language;
+// `name@compartment` is written unquoted when both parts are plain
+pub fn quote_symbol(s: &str) -> String {
+    let plain = match s.split_once('@') {
+        Some((name, compartment)) => is_plain(name) && is_plain(compartment),
+        None => is_plain(s),
+    };
+    if plain {
+        s.to_string()
+    } else {
+        format!("\"{s}\"")
+    }
+}
+
+// spelling of symbols, powers and functions when rendering an expression,
+// e.g. as python or R code
+pub trait Notation {
+    fn symbol(&self, name: &str) -> String;
+
+    fn power(&self) -> &'static str {
+        "^"
+    }
+
+    fn function(&self, f: Function) -> String {
+        f.name().to_string()
+    }
+}
+
+// the notation of the model language itself
+struct Language;
+
+impl Notation for Language {
+    fn symbol(&self, name: &str) -> String {
+        quote_symbol(name)
+    }
+}
+
+impl Expr {
+    pub fn render(&self, n: &dyn Notation) -> String {
+        match self {
+            Expr::Number(x) => format!("{x}"),
+            Expr::Symbol(s) => n.symbol(s),
+            Expr::Neg(e) => {
+                if e.precedence() <= 3 {
+                    format!("-({})", e.render(n))
+                } else {
+                    format!("-{}", e.render(n))
+                }
+            }
+            Expr::Call(func, e) => format!("{}({})", n.function(*func), e.render(n)),
+            Expr::Binary(op, a, b) => {
+                let p = op.precedence();
+                // `^` is right associative, the others are left associative
+                let (left_paren, right_paren) = match op {
+                    BinaryOp::Pow => (a.precedence() <= p, b.precedence() < p),
+                    BinaryOp::Sub | BinaryOp::Div => (a.precedence() < p, b.precedence() <= p),
+                    _ => (a.precedence() < p, b.precedence() < p),
+                };
+                let left = a.render(n);
+                let right = b.render(n);
+                let symbol = match op {
+                    BinaryOp::Pow => n.power(),
+                    _ => op.symbol(),
+                };
+                format!(
+                    "{} {symbol} {}",
+                    if left_paren {
+                        format!("({left})")
+                    } else {
+                        left
+                    },
+                    if right_paren {
+                        format!("({right})")
+                    } else {
+                        right
+                    }
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(&Language))
+    }
+}
@@ -0,0 +1,298 @@
+// chemical formulas and charges of species, for checking that reactions
+// conserve elements and charge
+//
+// A formula is declared as `formula "NH4(+)" : N H4 +1`, or inferred from a
+// species name that reads as one, such as `H2O`, `CO2` or `Ca(2+)`. Names of
+// a single atom such as `S` or `P` are more often abbreviations (substrate,
+// product) and are not inferred.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::data::Rational;
+
+static ELEMENTS: &[&str] = &[
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+    "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+    "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk",
+    "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh",
+    "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+pub fn is_element(symbol: &str) -> bool {
+    ELEMENTS.contains(&symbol)
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChemicalFormula {
+    // atoms of each element
+    pub elements: BTreeMap<String, u64>,
+    pub charge: i64,
+}
+
+impl ChemicalFormula {
+    // a formula such as `C6H12O6`, `Ca(OH)2`, `NH4(+)`, `SO4(2-)` or `Fe+3`
+    pub fn parse(text: &str) -> Option<Self> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut formula = ChemicalFormula::default();
+        let mut reader = Reader { chars, at: 0 };
+        reader.group(&mut formula.elements, 1)?;
+        if !reader.done() {
+            formula.charge = reader.charge()?;
+        }
+        reader.done().then_some(formula)
+    }
+
+    // formula read from a species name, without its `@compartment`, if the
+    // name is unambiguously one
+    pub fn inferred(name: &str) -> Option<Self> {
+        let name = name.split('@').next().unwrap_or(name);
+        let formula = Self::parse(name)?;
+        let atoms: u64 = formula.elements.values().sum();
+        (atoms > 1 || formula.charge != 0).then_some(formula)
+    }
+
+    // combine another formula's atoms and charge into this one
+    pub fn add(&mut self, other: &ChemicalFormula) {
+        for (element, n) in &other.elements {
+            *self.elements.entry(element.clone()).or_insert(0) += n;
+        }
+        self.charge += other.charge;
+    }
+
+    // elements in Hill order: carbon, hydrogen, then alphabetical, or all
+    // alphabetical without carbon
+    pub fn hill_order(&self) -> Vec<(&str, u64)> {
+        let mut order: Vec<(&str, u64)> = self
+            .elements
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(e, n)| (e.as_str(), *n))
+            .collect();
+        if self.elements.contains_key("C") {
+            order.sort_by_key(|(e, _)| match *e {
+                "C" => (0, *e),
+                "H" => (1, *e),
+                _ => (2, *e),
+            });
+        }
+        order
+    }
+}
+
+// elements then a signed charge, as declared in the language, e.g. `H4N +1`
+impl fmt::Display for ChemicalFormula {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (element, n) in self.hill_order() {
+            if n == 1 {
+                write!(f, "{element}")?;
+            } else {
+                write!(f, "{element}{n}")?;
+            }
+        }
+        if self.charge != 0 {
+            write!(f, " {:+}", self.charge)?;
+        }
+        Ok(())
+    }
+}
+
+struct Reader {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Reader {
+    fn done(&self) -> bool {
+        self.at == self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn count(&mut self) -> Option<u64> {
+        let start = self.at;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.at += 1;
+        }
+        if self.at == start {
+            return None;
+        }
+        self.chars[start..self.at]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    // elements and parenthesized groups, each multiplied by `factor`, up to
+    // a closing parenthesis, a charge or the end
+    fn group(&mut self, elements: &mut BTreeMap<String, u64>, factor: u64) -> Option<()> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_uppercase() => {
+                    let mut symbol = c.to_string();
+                    self.at += 1;
+                    if let Some(d) = self.peek().filter(|d| d.is_ascii_lowercase()) {
+                        symbol.push(d);
+                        self.at += 1;
+                    }
+                    if !is_element(&symbol) {
+                        return None;
+                    }
+                    let n = self.count().unwrap_or(1);
+                    *elements.entry(symbol).or_insert(0) += n * factor;
+                }
+                Some('(') if !self.at_charge() => {
+                    self.at += 1;
+                    let mut inner = BTreeMap::new();
+                    self.group(&mut inner, 1)?;
+                    if self.peek() != Some(')') {
+                        return None;
+                    }
+                    self.at += 1;
+                    let n = self.count().unwrap_or(1);
+                    for (element, m) in inner {
+                        *elements.entry(element).or_insert(0) += m * n * factor;
+                    }
+                }
+                _ => return Some(()),
+            }
+        }
+    }
+
+    // whether the rest is a charge in parentheses, e.g. `(2+)`
+    fn at_charge(&self) -> bool {
+        let rest = &self.chars[self.at..];
+        rest.last() == Some(&')')
+            && rest[1..rest.len() - 1]
+                .iter()
+                .all(|c| c.is_ascii_digit() || *c == '+' || *c == '-')
+    }
+
+    // `(+)`, `(2-)`, `+`, `2+`, `+2` or `++`
+    fn charge(&mut self) -> Option<i64> {
+        let parenthesized = self.peek() == Some('(');
+        if parenthesized {
+            self.at += 1;
+        }
+        let leading = self.count();
+        let sign = match self.peek()? {
+            '+' => 1,
+            '-' => -1,
+            _ => return None,
+        };
+        let mut repeats = 0;
+        while self.peek() == Some(if sign > 0 { '+' } else { '-' }) {
+            self.at += 1;
+            repeats += 1;
+        }
+        let trailing = if leading.is_none() && repeats == 1 {
+            self.count()
+        } else {
+            None
+        };
+        if parenthesized {
+            if self.peek() != Some(')') {
+                return None;
+            }
+            self.at += 1;
+        }
+        let magnitude = match (leading, trailing) {
+            (Some(n), None) if repeats == 1 => n,
+            (None, Some(n)) => n,
+            (None, None) => repeats,
+            _ => return None,
+        };
+        Some(sign * magnitude as i64)
+    }
+}
+
+// atoms and charge that a reaction creates, products minus reactants
+#[derive(Debug, Clone, PartialEq)]
+pub struct Imbalance {
+    pub reaction: String,
+    pub elements: Vec<(String, Rational)>,
+    pub charge: Rational,
+}
+
+impl fmt::Display for Imbalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let signed = |x: Rational| {
+            if x > Rational::ZERO {
+                format!("+{x}")
+            } else {
+                x.to_string()
+            }
+        };
+        let mut parts: Vec<String> = self
+            .elements
+            .iter()
+            .map(|(element, n)| format!("{element} {}", signed(*n)))
+            .collect();
+        if !self.charge.is_zero() {
+            parts.push(format!("charge {}", signed(self.charge)));
+        }
+        write!(
+            f,
+            "reaction '{}' is not balanced: {} (products minus reactants)",
+            self.reaction,
+            parts.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula(elements: &[(&str, u64)], charge: i64) -> ChemicalFormula {
+        ChemicalFormula {
+            elements: elements.iter().map(|&(e, n)| (e.to_string(), n)).collect(),
+            charge,
+        }
+    }
+
+    #[test]
+    fn parses_groups_and_charges() {
+        assert_eq!(
+            ChemicalFormula::parse("Ca(OH)2"),
+            Some(formula(&[("Ca", 1), ("O", 2), ("H", 2)], 0))
+        );
+        assert_eq!(
+            ChemicalFormula::parse("NH4(+)"),
+            Some(formula(&[("N", 1), ("H", 4)], 1))
+        );
+        for text in ["SO4(2-)", "SO4--", "SO4-2"] {
+            assert_eq!(
+                ChemicalFormula::parse(text),
+                Some(formula(&[("S", 1), ("O", 4)], -2))
+            );
+        }
+        assert_eq!(ChemicalFormula::parse("Fe+3").unwrap().charge, 3);
+        assert_eq!(ChemicalFormula::parse("Xy2"), None);
+        assert_eq!(ChemicalFormula::parse("C6(H"), None);
+    }
+
+    #[test]
+    fn infers_only_unambiguous_names() {
+        assert!(ChemicalFormula::inferred("H2O@cytosol").is_some());
+        assert!(ChemicalFormula::inferred("Ca(2+)").is_some());
+        // single atoms usually abbreviate substrate and product
+        assert!(ChemicalFormula::inferred("S").is_none());
+        assert!(ChemicalFormula::inferred("ATP").is_none());
+    }
+
+    #[test]
+    fn displays_in_hill_order() {
+        let glucose = ChemicalFormula::parse("O6H12C6").unwrap();
+        assert_eq!(glucose.to_string(), "C6H12O6");
+        assert_eq!(
+            ChemicalFormula::parse("NH4+").unwrap().to_string(),
+            "H4N +1"
+        );
+    }
+}
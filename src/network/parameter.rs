@@ -0,0 +1,82 @@
+use std::fmt;
+
+// prior distribution of a parameter, e.g. `~ lognormal(0, 1)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prior {
+    Normal { mean: f64, sd: f64 },
+    LogNormal { mu: f64, sigma: f64 },
+    Uniform { lower: f64, upper: f64 },
+}
+
+impl Prior {
+    pub fn from_name(name: &str, a: f64, b: f64) -> Option<Self> {
+        match name {
+            "normal" => Some(Prior::Normal { mean: a, sd: b }),
+            "lognormal" => Some(Prior::LogNormal { mu: a, sigma: b }),
+            "uniform" => Some(Prior::Uniform { lower: a, upper: b }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Prior {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Prior::Normal { mean, sd } => write!(f, "normal({mean}, {sd})"),
+            Prior::LogNormal { mu, sigma } => write!(f, "lognormal({mu}, {sigma})"),
+            Prior::Uniform { lower, upper } => write!(f, "uniform({lower}, {upper})"),
+        }
+    }
+}
+
+// how a parameter entered the parameter table
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Declaration {
+    // used in a rate law, definition or event without a `param` statement
+    #[default]
+    Undeclared,
+    // rate constant of a default mass-action rate law
+    Implicit,
+    Explicit,
+}
+
+// per-parameter attributes declared with `param`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParameterAttributes {
+    pub value: Option<f64>,
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+    pub unit: Option<String>,
+    pub prior: Option<Prior>,
+    pub description: Option<String>,
+    pub declaration: Declaration,
+}
+
+// parameters without a value default to one
+pub const DEFAULT_PARAMETER_VALUE: f64 = 1.0;
+
+impl ParameterAttributes {
+    pub fn value_or_default(&self) -> f64 {
+        self.value.unwrap_or(DEFAULT_PARAMETER_VALUE)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterWarning {
+    Undeclared(String),
+    Unused(String),
+}
+
+impl fmt::Display for ParameterWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParameterWarning::Undeclared(p) => write!(
+                f,
+                "parameter '{p}' is used but never declared (defaults to {DEFAULT_PARAMETER_VALUE})"
+            ),
+            ParameterWarning::Unused(p) => {
+                write!(f, "parameter '{p}' is declared but never used")
+            }
+        }
+    }
+}
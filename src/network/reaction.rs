@@ -1,10 +1,14 @@
 use super::complex::Complex;
-#[derive(Debug)]
+use super::expression::Expr;
+#[derive(Debug, Default, Clone)]
 pub struct Reaction {
     name: Option<String>,
     reversible: bool,
     reactants: Complex,
     products: Complex,
+    kinetics: Option<Expr>,
+    // formula as first written, kept when species are eliminated
+    original: Option<String>,
 }
 
 impl Reaction {
@@ -14,6 +18,8 @@ impl Reaction {
             reversible: false,
             reactants: Complex::new(),
             products: Complex::new(),
+            kinetics: None,
+            original: None,
         }
     }
 
@@ -23,6 +29,8 @@ impl Reaction {
             reversible: true,
             reactants,
             products,
+            kinetics: None,
+            original: None,
         }
     }
 
@@ -32,6 +40,8 @@ impl Reaction {
             reversible: false,
             reactants,
             products,
+            kinetics: None,
+            original: None,
         }
     }
 
@@ -41,6 +51,8 @@ impl Reaction {
             reversible: true,
             reactants,
             products,
+            kinetics: None,
+            original: None,
         }
     }
 
@@ -50,6 +62,8 @@ impl Reaction {
             reversible: false,
             reactants,
             products,
+            kinetics: None,
+            original: None,
         }
     }
 
@@ -57,10 +71,36 @@ impl Reaction {
         self.name = Some(name);
     }
 
+    pub fn set_kinetics(&mut self, rate: Expr) {
+        self.kinetics = Some(rate);
+    }
+
+    // explicit rate law; None means mass-action kinetics
+    pub fn get_kinetics(&self) -> Option<&Expr> {
+        self.kinetics.as_ref()
+    }
+
+    pub fn set_original_formula(&mut self, formula: String) {
+        self.original = Some(formula);
+    }
+
+    pub fn get_original_formula(&self) -> Option<&str> {
+        self.original.as_deref()
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     pub fn is_reversible(&self) -> bool {
         self.reversible
     }
 
+    // boundary reaction with an empty reactant or product complex, e.g. `0 -> A`
+    pub fn is_exchange(&self) -> bool {
+        self.reactants.is_empty() || self.products.is_empty()
+    }
+
     pub fn get_reactants(&self) -> &Complex {
         &self.reactants
     }
@@ -0,0 +1,37 @@
+use super::complex::StoichCoef;
+use super::formula::ChemicalFormula;
+
+// per-species attributes declared in a `species { ... }` block
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpeciesAttributes {
+    pub initial: Option<f64>,
+    pub unit: Option<String>,
+    // concentration never changes, e.g. a buffered metabolite
+    pub constant: bool,
+    // set by the environment rather than by the reactions
+    pub boundary: bool,
+    pub description: Option<String>,
+    // set by the `name@compartment` notation
+    pub compartment: Option<usize>,
+    // declared with `formula`
+    pub formula: Option<ChemicalFormula>,
+}
+
+impl SpeciesAttributes {
+    // boundary and constant species are not integrated
+    pub fn is_fixed(&self) -> bool {
+        self.constant || self.boundary
+    }
+}
+
+// a species replaced by an observable of the same name, as by
+// `analysis::conservation::eliminate_dependent`; exported models keep it in
+// the reactions it takes part in
+#[derive(Debug, Clone)]
+pub struct EliminatedSpecies {
+    pub observable: String,
+    pub attributes: SpeciesAttributes,
+    // (reaction, coefficient) for each reaction consuming or producing it
+    pub reactants: Vec<(usize, StoichCoef)>,
+    pub products: Vec<(usize, StoichCoef)>,
+}
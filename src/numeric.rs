@@ -0,0 +1,13 @@
+pub mod eigen;
+pub mod exact;
+pub mod linalg;
+pub mod ode;
+pub mod random;
+pub mod sampling;
+pub mod simplex;
+pub use eigen::Complex;
+pub use linalg::Lu;
+pub use ode::{Integrator, Method, OdeError, OdeOptions, OdeSystem};
+pub use random::Rng;
+pub use sampling::{Sobol, latin_hypercube};
+pub use simplex::{LinearProgram, LpError, LpSolution, Relation};
@@ -0,0 +1,449 @@
+// eigenvalues and eigenvectors of real nonsymmetric matrices
+//
+// Eigenvalues come from reduction to Hessenberg form followed by the
+// shifted double-step QR algorithm (after EISPACK's elmhes and hqr).
+// Eigenvectors are found by inverse iteration at each eigenvalue.
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::data::Matrix;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn conj(&self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im == 0.0
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, o: Complex) -> Complex {
+        Complex::new(self.re + o.re, self.im + o.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, o: Complex) -> Complex {
+        Complex::new(self.re - o.re, self.im - o.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, o: Complex) -> Complex {
+        Complex::new(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    fn div(self, o: Complex) -> Complex {
+        let d = o.re * o.re + o.im * o.im;
+        Complex::new(
+            (self.re * o.re + self.im * o.im) / d,
+            (self.im * o.re - self.re * o.im) / d,
+        )
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.im == 0.0 {
+            write!(f, "{}", self.re)
+        } else if self.im < 0.0 {
+            write!(f, "{}-{}i", self.re, -self.im)
+        } else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoConvergence;
+
+impl fmt::Display for NoConvergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "QR iteration did not converge")
+    }
+}
+
+impl std::error::Error for NoConvergence {}
+
+// similarity reduction to upper Hessenberg form by stabilized elimination
+fn hessenberg(a: &mut Matrix) {
+    let n = a.rows();
+    for m in 1..n.saturating_sub(1) {
+        let mut x = 0.0_f64;
+        let mut pivot = m;
+        for j in m..n {
+            if a[(j, m - 1)].abs() > x.abs() {
+                x = a[(j, m - 1)];
+                pivot = j;
+            }
+        }
+        if pivot != m {
+            for j in m - 1..n {
+                let t = a[(pivot, j)];
+                a[(pivot, j)] = a[(m, j)];
+                a[(m, j)] = t;
+            }
+            for j in 0..n {
+                let t = a[(j, pivot)];
+                a[(j, pivot)] = a[(j, m)];
+                a[(j, m)] = t;
+            }
+        }
+        if x != 0.0 {
+            for i in m + 1..n {
+                let y = a[(i, m - 1)] / x;
+                if y != 0.0 {
+                    a[(i, m - 1)] = 0.0;
+                    for j in m..n {
+                        a[(i, j)] -= y * a[(m, j)];
+                    }
+                    for j in 0..n {
+                        a[(j, m)] += y * a[(j, i)];
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn sign(a: f64, b: f64) -> f64 {
+    if b >= 0.0 { a.abs() } else { -a.abs() }
+}
+
+// eigenvalues of a real square matrix; complex pairs appear together
+pub fn eigenvalues(matrix: &Matrix) -> Result<Vec<Complex>, NoConvergence> {
+    let n = matrix.rows();
+    assert_eq!(n, matrix.cols(), "eigenvalues need a square matrix");
+    let mut a = matrix.clone();
+    hessenberg(&mut a);
+    let mut wr = vec![0.0; n];
+    let mut wi = vec![0.0; n];
+
+    let at = |i: isize, j: isize| (i as usize, j as usize);
+    let mut anorm = 0.0;
+    for i in 0..n {
+        for j in i.saturating_sub(1)..n {
+            anorm += a[(i, j)].abs();
+        }
+    }
+
+    let mut nn = n as isize - 1;
+    let mut t = 0.0;
+    while nn >= 0 {
+        let mut its = 0;
+        loop {
+            // look for a single small subdiagonal element
+            let mut l = nn;
+            while l >= 1 {
+                let mut s = a[at(l - 1, l - 1)].abs() + a[at(l, l)].abs();
+                if s == 0.0 {
+                    s = anorm;
+                }
+                if a[at(l, l - 1)].abs() + s == s {
+                    a[at(l, l - 1)] = 0.0;
+                    break;
+                }
+                l -= 1;
+            }
+            let mut x = a[at(nn, nn)];
+            if l == nn {
+                // one root found
+                wr[nn as usize] = x + t;
+                wi[nn as usize] = 0.0;
+                nn -= 1;
+            } else {
+                let mut y = a[at(nn - 1, nn - 1)];
+                let mut w = a[at(nn, nn - 1)] * a[at(nn - 1, nn)];
+                if l == nn - 1 {
+                    // two roots found
+                    let p = 0.5 * (y - x);
+                    let q = p * p + w;
+                    let mut z = q.abs().sqrt();
+                    x += t;
+                    let (i, k) = ((nn - 1) as usize, nn as usize);
+                    if q >= 0.0 {
+                        z = p + sign(z, p);
+                        wr[i] = x + z;
+                        wr[k] = if z != 0.0 { x - w / z } else { x + z };
+                        wi[i] = 0.0;
+                        wi[k] = 0.0;
+                    } else {
+                        wr[i] = x + p;
+                        wr[k] = x + p;
+                        wi[i] = -z;
+                        wi[k] = z;
+                    }
+                    nn -= 2;
+                } else {
+                    if its == 60 {
+                        return Err(NoConvergence);
+                    }
+                    if its == 10 || its == 20 {
+                        // exceptional shift
+                        t += x;
+                        for i in 0..=nn {
+                            a[at(i, i)] -= x;
+                        }
+                        let s = a[at(nn, nn - 1)].abs() + a[at(nn - 1, nn - 2)].abs();
+                        x = 0.75 * s;
+                        y = x;
+                        w = -0.4375 * s * s;
+                    }
+                    its += 1;
+
+                    // form the shift and look for two consecutive small
+                    // subdiagonal elements
+                    let mut m = nn - 2;
+                    let (mut p, mut q, mut r, mut z);
+                    loop {
+                        z = a[at(m, m)];
+                        r = x - z;
+                        let s = y - z;
+                        p = (r * s - w) / a[at(m + 1, m)] + a[at(m, m + 1)];
+                        q = a[at(m + 1, m + 1)] - z - r - s;
+                        r = a[at(m + 2, m + 1)];
+                        let s = p.abs() + q.abs() + r.abs();
+                        p /= s;
+                        q /= s;
+                        r /= s;
+                        if m == l {
+                            break;
+                        }
+                        let u = a[at(m, m - 1)].abs() * (q.abs() + r.abs());
+                        let v = p.abs()
+                            * (a[at(m - 1, m - 1)].abs() + z.abs() + a[at(m + 1, m + 1)].abs());
+                        if u + v == v {
+                            break;
+                        }
+                        m -= 1;
+                    }
+                    for i in m + 2..=nn {
+                        a[at(i, i - 2)] = 0.0;
+                        if i != m + 2 {
+                            a[at(i, i - 3)] = 0.0;
+                        }
+                    }
+
+                    // double QR step on rows l..=nn and columns m..=nn
+                    for k in m..nn {
+                        if k != m {
+                            p = a[at(k, k - 1)];
+                            q = a[at(k + 1, k - 1)];
+                            r = if k != nn - 1 {
+                                a[at(k + 2, k - 1)]
+                            } else {
+                                0.0
+                            };
+                            x = p.abs() + q.abs() + r.abs();
+                            if x != 0.0 {
+                                p /= x;
+                                q /= x;
+                                r /= x;
+                            }
+                        }
+                        let s = sign((p * p + q * q + r * r).sqrt(), p);
+                        if s == 0.0 {
+                            continue;
+                        }
+                        if k == m {
+                            if l != m {
+                                a[at(k, k - 1)] = -a[at(k, k - 1)];
+                            }
+                        } else {
+                            a[at(k, k - 1)] = -s * x;
+                        }
+                        p += s;
+                        x = p / s;
+                        y = q / s;
+                        z = r / s;
+                        q /= p;
+                        r /= p;
+                        for j in k..=nn {
+                            p = a[at(k, j)] + q * a[at(k + 1, j)];
+                            if k != nn - 1 {
+                                p += r * a[at(k + 2, j)];
+                                a[at(k + 2, j)] -= p * z;
+                            }
+                            a[at(k + 1, j)] -= p * y;
+                            a[at(k, j)] -= p * x;
+                        }
+                        let mmin = nn.min(k + 3);
+                        for i in l..=mmin {
+                            p = x * a[at(i, k)] + y * a[at(i, k + 1)];
+                            if k != nn - 1 {
+                                p += z * a[at(i, k + 2)];
+                                a[at(i, k + 2)] -= p * r;
+                            }
+                            a[at(i, k + 1)] -= p * q;
+                            a[at(i, k)] -= p;
+                        }
+                    }
+                }
+            }
+            if l >= nn - 1 {
+                break;
+            }
+        }
+    }
+    Ok(wr
+        .into_iter()
+        .zip(wi)
+        .map(|(re, im)| Complex::new(re, im))
+        .collect())
+}
+
+// solve the complex system m x = b by Gaussian elimination with partial
+// pivoting; exactly singular pivots are perturbed, as inverse iteration
+// deliberately solves nearly singular systems
+fn complex_solve(mut m: Vec<Vec<Complex>>, mut b: Vec<Complex>) -> Vec<Complex> {
+    let n = b.len();
+    let tiny = f64::EPSILON * m.iter().flatten().fold(1e-300_f64, |s, z| s.max(z.abs()));
+    for k in 0..n {
+        let p = (k..n)
+            .max_by(|&i, &j| m[i][k].abs().total_cmp(&m[j][k].abs()))
+            .expect("non-empty pivot range");
+        m.swap(k, p);
+        b.swap(k, p);
+        if m[k][k].abs() < tiny {
+            m[k][k] = Complex::new(tiny, 0.0);
+        }
+        for i in k + 1..n {
+            let f = m[i][k] / m[k][k];
+            let (upper, lower) = m.split_at_mut(i);
+            for (a, &b) in lower[0][k..].iter_mut().zip(&upper[k][k..]) {
+                *a = *a - f * b;
+            }
+            let v = b[k];
+            b[i] = b[i] - f * v;
+        }
+    }
+    let mut x = vec![Complex::default(); n];
+    for i in (0..n).rev() {
+        let mut s = b[i];
+        for j in i + 1..n {
+            s = s - m[i][j] * x[j];
+        }
+        x[i] = s / m[i][i];
+    }
+    x
+}
+
+// eigenvector of `matrix` for the eigenvalue `lambda`, normalized to unit
+// length, by inverse iteration
+pub fn eigenvector(matrix: &Matrix, lambda: Complex) -> Vec<Complex> {
+    let n = matrix.rows();
+    let shift = lambda + Complex::new(1e-10 * (1.0 + lambda.abs()), 0.0);
+    let shifted: Vec<Vec<Complex>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    let d = if i == j { shift } else { Complex::default() };
+                    Complex::new(matrix[(i, j)], 0.0) - d
+                })
+                .collect()
+        })
+        .collect();
+    let mut v = vec![Complex::new(1.0, 0.0); n];
+    for _ in 0..3 {
+        v = complex_solve(shifted.clone(), v);
+        let norm = v.iter().map(|z| z.abs().powi(2)).sum::<f64>().sqrt();
+        if norm == 0.0 || !norm.is_finite() {
+            break;
+        }
+        for z in v.iter_mut() {
+            *z = *z / Complex::new(norm, 0.0);
+        }
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut values: Vec<Complex>) -> Vec<Complex> {
+        values.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        values
+    }
+
+    fn close(a: Complex, b: Complex) -> bool {
+        (a - b).abs() < 1e-8
+    }
+
+    #[test]
+    fn triangular_matrix_has_its_diagonal() {
+        let m = Matrix::from_rows(&[
+            vec![3.0, 1.0, -2.0],
+            vec![0.0, -1.0, 4.0],
+            vec![0.0, 0.0, 0.5],
+        ]);
+        let values = sorted(eigenvalues(&m).unwrap());
+        for (z, expected) in values.iter().zip([-1.0, 0.5, 3.0]) {
+            assert!(close(*z, Complex::new(expected, 0.0)), "{z:?}");
+        }
+    }
+
+    #[test]
+    fn rotation_has_conjugate_pair() {
+        let m = Matrix::from_rows(&[vec![1.0, -2.0], vec![2.0, 1.0]]);
+        let values = sorted(eigenvalues(&m).unwrap());
+        assert!(close(values[0], Complex::new(1.0, -2.0)));
+        assert!(close(values[1], Complex::new(1.0, 2.0)));
+        assert!(!values[0].is_real());
+    }
+
+    #[test]
+    fn companion_matrix_has_polynomial_roots() {
+        // x^3 - 6x^2 + 11x - 6 = (x - 1)(x - 2)(x - 3)
+        let m = Matrix::from_rows(&[
+            vec![6.0, -11.0, 6.0],
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+        ]);
+        let values = sorted(eigenvalues(&m).unwrap());
+        for (z, expected) in values.iter().zip([1.0, 2.0, 3.0]) {
+            assert!(close(*z, Complex::new(expected, 0.0)), "{z:?}");
+        }
+    }
+
+    #[test]
+    fn eigenvectors_satisfy_the_eigen_equation() {
+        let m = Matrix::from_rows(&[vec![2.0, 1.0], vec![1.0, 3.0]]);
+        for lambda in eigenvalues(&m).unwrap() {
+            let v = eigenvector(&m, lambda);
+            let norm = v.iter().map(|z| z.abs().powi(2)).sum::<f64>().sqrt();
+            assert!((norm - 1.0).abs() < 1e-10);
+            for i in 0..2 {
+                let mv = (0..2).fold(Complex::default(), |acc, j| {
+                    acc + Complex::new(m[(i, j)], 0.0) * v[j]
+                });
+                assert!(close(mv, lambda * v[i]));
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+// exact linear algebra over the rationals for structural analysis
+//
+// Entries can outgrow 128 bits on models with many decimal coefficients, in
+// which case these return `Overflow` rather than a wrong answer.
+use crate::data::rational::gcd;
+use crate::data::{Matrix, Overflow, Rational};
+
+fn sub_product(x: Rational, f: Rational, y: Rational) -> Result<Rational, Overflow> {
+    x.checked_sub(f.checked_mul(y).ok_or(Overflow)?)
+        .ok_or(Overflow)
+}
+
+// reduced row echelon form and the pivot column of each nonzero row
+pub fn rref(m: &Matrix<Rational>) -> Result<(Matrix<Rational>, Vec<usize>), Overflow> {
+    let mut a = m.clone();
+    let mut pivots = Vec::new();
+    let mut r = 0;
+    for c in 0..a.cols() {
+        if r == a.rows() {
+            break;
+        }
+        let Some(p) = (r..a.rows()).find(|&i| !a[(i, c)].is_zero()) else {
+            continue;
+        };
+        a.swap_rows(r, p);
+        let pivot = a[(r, c)];
+        for j in 0..a.cols() {
+            a[(r, j)] = a[(r, j)].checked_div(pivot).ok_or(Overflow)?;
+        }
+        for i in 0..a.rows() {
+            if i == r || a[(i, c)].is_zero() {
+                continue;
+            }
+            let f = a[(i, c)];
+            for j in 0..a.cols() {
+                a[(i, j)] = sub_product(a[(i, j)], f, a[(r, j)])?;
+            }
+        }
+        pivots.push(c);
+        r += 1;
+    }
+    Ok((a, pivots))
+}
+
+pub fn rank(m: &Matrix<Rational>) -> Result<usize, Overflow> {
+    Ok(rref(m)?.1.len())
+}
+
+// basis of {x : m x = 0}, one vector per free column
+pub fn nullspace(m: &Matrix<Rational>) -> Result<Vec<Vec<Rational>>, Overflow> {
+    let (a, pivots) = rref(m)?;
+    let n = m.cols();
+    let mut basis = Vec::new();
+    for free in (0..n).filter(|c| !pivots.contains(c)) {
+        let mut v = vec![Rational::ZERO; n];
+        v[free] = Rational::ONE;
+        for (row, &p) in pivots.iter().enumerate() {
+            v[p] = a[(row, free)].checked_neg().ok_or(Overflow)?;
+        }
+        basis.push(v);
+    }
+    Ok(basis)
+}
+
+// basis of {y : y^T m = 0}, e.g. conservation laws of a stoichiometric matrix
+pub fn left_nullspace(m: &Matrix<Rational>) -> Result<Vec<Vec<Rational>>, Overflow> {
+    nullspace(&m.transpose())
+}
+
+// the vector scaled to coprime integers with the sign of its first nonzero
+// entry unchanged
+fn primitive(v: &mut [Rational]) -> Result<(), Overflow> {
+    let mut denominator: i128 = 1;
+    for x in v.iter().filter(|x| !x.is_zero()) {
+        denominator = (denominator / gcd(denominator, x.denom()))
+            .checked_mul(x.denom())
+            .ok_or(Overflow)?;
+    }
+    let denominator = Rational::checked_new(denominator, 1).ok_or(Overflow)?;
+    let mut numerator = 0;
+    for x in v.iter() {
+        numerator = gcd(
+            numerator,
+            x.checked_mul(denominator).ok_or(Overflow)?.numer(),
+        );
+    }
+    if numerator == 0 {
+        return Ok(());
+    }
+    let numerator = Rational::checked_new(numerator, 1).ok_or(Overflow)?;
+    let scale = denominator.checked_div(numerator).ok_or(Overflow)?;
+    for x in v.iter_mut() {
+        *x = x.checked_mul(scale).ok_or(Overflow)?;
+    }
+    Ok(())
+}
+
+// minimal-support semi-positive vectors y >= 0 with y^T m = 0, as coprime
+// integers, e.g. P-invariants of a Petri net with incidence matrix m, or
+// T-invariants with its transpose. Farkas' algorithm: the rows of [m | I]
+// are combined with nonnegative weights to cancel one column of m at a
+// time, dropping rows whose support contains another's.
+pub fn semiflows(m: &Matrix<Rational>) -> Result<Vec<Vec<Rational>>, Overflow> {
+    let n = m.rows();
+    // residual of m and the combination of its rows
+    let mut rows: Vec<(Vec<Rational>, Vec<Rational>)> = (0..n)
+        .map(|i| {
+            let mut e = vec![Rational::ZERO; n];
+            e[i] = Rational::ONE;
+            (m.row(i).to_vec(), e)
+        })
+        .collect();
+    let support = |y: &[Rational]| -> Vec<bool> { y.iter().map(|x| !x.is_zero()).collect() };
+    let contains = |a: &[bool], b: &[bool]| a.iter().zip(b).all(|(&a, &b)| a || !b);
+    for c in 0..m.cols() {
+        let (mut next, rest): (Vec<_>, Vec<_>) =
+            rows.into_iter().partition(|(r, _)| r[c].is_zero());
+        let positive: Vec<_> = rest.iter().filter(|(r, _)| r[c] > Rational::ZERO).collect();
+        let negative: Vec<_> = rest.iter().filter(|(r, _)| r[c] < Rational::ZERO).collect();
+        for (p, py) in &positive {
+            for (q, qy) in &negative {
+                let (a, b) = (q[c].checked_neg().ok_or(Overflow)?, p[c]);
+                let combine = |x: &[Rational], z: &[Rational]| -> Result<Vec<Rational>, Overflow> {
+                    x.iter()
+                        .zip(z)
+                        .map(|(&x, &z)| {
+                            let ax = a.checked_mul(x).ok_or(Overflow)?;
+                            ax.checked_add(b.checked_mul(z).ok_or(Overflow)?)
+                                .ok_or(Overflow)
+                        })
+                        .collect()
+                };
+                let mut r = combine(p, q)?;
+                let mut y = combine(py, qy)?;
+                let mut both = r.clone();
+                both.extend_from_slice(&y);
+                primitive(&mut both)?;
+                y = both.split_off(r.len());
+                r = both;
+                next.push((r, y));
+            }
+        }
+        // keep rows whose support is minimal; of rows with equal supports
+        // all are kept until the last column, since their residuals differ
+        let supports: Vec<Vec<bool>> = next.iter().map(|(_, y)| support(y)).collect();
+        rows = next
+            .into_iter()
+            .enumerate()
+            .filter(|(k, _)| {
+                !supports
+                    .iter()
+                    .any(|s| s != &supports[*k] && contains(&supports[*k], s))
+            })
+            .map(|(_, row)| row)
+            .collect();
+        rows.sort();
+        rows.dedup();
+    }
+    let mut out: Vec<Vec<Rational>> = Vec::new();
+    for (_, mut y) in rows {
+        primitive(&mut y)?;
+        if !out.iter().any(|z| support(z) == support(&y)) {
+            out.push(y);
+        }
+    }
+    // vectors involving earlier rows first
+    out.sort_by_key(|y| support(y).iter().map(|s| !s).collect::<Vec<bool>>());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[i64]]) -> Matrix<Rational> {
+        let rows: Vec<Vec<Rational>> = rows
+            .iter()
+            .map(|r| r.iter().map(|&x| Rational::integer(x)).collect())
+            .collect();
+        Matrix::from_rows(&rows)
+    }
+
+    #[test]
+    fn row_reduces_with_pivots() {
+        let m = matrix(&[&[2, 4, 2], &[1, 3, 2], &[3, 7, 4]]);
+        let (r, pivots) = rref(&m).unwrap();
+        assert_eq!(pivots, vec![0, 1]);
+        assert_eq!(r, matrix(&[&[1, 0, -1], &[0, 1, 1], &[0, 0, 0]]));
+        assert_eq!(rank(&m), Ok(2));
+    }
+
+    #[test]
+    fn nullspace_is_annihilated() {
+        let m = matrix(&[&[1, -1, 0], &[0, 1, -1]]);
+        let basis = nullspace(&m).unwrap();
+        assert_eq!(basis.len(), 1);
+        for i in 0..m.rows() {
+            let dot: Rational = m.row(i).iter().zip(&basis[0]).map(|(&a, &b)| a * b).sum();
+            assert!(dot.is_zero());
+        }
+    }
+
+    #[test]
+    fn semiflows_are_minimal_and_nonnegative() {
+        // A -> B, B -> A: one P-semiflow A + B
+        let m = matrix(&[&[-1, 1], &[1, -1]]);
+        let flows = semiflows(&m).unwrap();
+        assert_eq!(flows, vec![vec![Rational::ONE, Rational::ONE]]);
+    }
+
+    #[test]
+    fn elimination_reports_overflow() {
+        let big = Rational::checked_new(i128::MAX / 3, 1).unwrap();
+        let m = Matrix::from_rows(&[
+            vec![Rational::new(1, 3), big],
+            vec![big, Rational::new(1, 7)],
+        ]);
+        assert_eq!(rref(&m), Err(Overflow));
+    }
+}
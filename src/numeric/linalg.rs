@@ -0,0 +1,63 @@
+// dense linear algebra on f64 matrices
+use crate::data::Matrix;
+
+// LU factorization with partial pivoting, P A = L U, stored in one matrix
+#[derive(Debug, Clone)]
+pub struct Lu {
+    lu: Matrix,
+    perm: Vec<usize>,
+}
+
+impl Lu {
+    // None if the matrix is singular to working precision
+    pub fn factor(mut a: Matrix) -> Option<Self> {
+        let n = a.rows();
+        assert_eq!(n, a.cols(), "LU factorization needs a square matrix");
+        let scale = (0..n)
+            .flat_map(|i| a.row(i).to_vec())
+            .fold(0.0_f64, |m, x| m.max(x.abs()));
+        let tiny = scale * f64::EPSILON * n as f64;
+        let mut perm: Vec<usize> = (0..n).collect();
+
+        for k in 0..n {
+            let p = (k..n)
+                .max_by(|&i, &j| a[(i, k)].abs().total_cmp(&a[(j, k)].abs()))
+                .expect("non-empty pivot range");
+            let pivot = a[(p, k)];
+            if !pivot.is_finite() || pivot.abs() <= tiny || pivot == 0.0 {
+                return None;
+            }
+            if p != k {
+                a.swap_rows(p, k);
+                perm.swap(p, k);
+            }
+            for i in k + 1..n {
+                let factor = a[(i, k)] / pivot;
+                a[(i, k)] = factor;
+                if factor != 0.0 {
+                    for j in k + 1..n {
+                        a[(i, j)] -= factor * a[(k, j)];
+                    }
+                }
+            }
+        }
+        Some(Self { lu: a, perm })
+    }
+
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.perm.len();
+        let mut x: Vec<f64> = self.perm.iter().map(|&p| b[p]).collect();
+        for i in 0..n {
+            for j in 0..i {
+                x[i] -= self.lu[(i, j)] * x[j];
+            }
+        }
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                x[i] -= self.lu[(i, j)] * x[j];
+            }
+            x[i] /= self.lu[(i, i)];
+        }
+        x
+    }
+}
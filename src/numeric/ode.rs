@@ -0,0 +1,510 @@
+// adaptive integration of dy/dt = f(t, y)
+//
+// `DormandPrince` is the explicit 5(4) pair for non-stiff problems.
+// `Rosenbrock` is the linearly implicit 2(3) method of Shampine and Reichelt
+// (ode23s); it needs the Jacobian but takes large steps on stiff problems.
+// Between steps the solution is sampled by cubic Hermite interpolation.
+use std::error::Error;
+use std::fmt;
+
+use super::linalg::Lu;
+use crate::data::Matrix;
+
+pub trait OdeSystem {
+    fn dim(&self) -> usize;
+
+    fn rhs(&self, t: f64, y: &[f64], dy: &mut [f64]);
+
+    // df/dy; the Rosenbrock method treats the system as autonomous
+    fn jacobian(&self, t: f64, y: &[f64]) -> Matrix;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    DormandPrince,
+    Rosenbrock,
+}
+
+impl Method {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "rk45" | "dopri5" | "dormand-prince" => Some(Method::DormandPrince),
+            "stiff" | "rosenbrock" | "ode23s" => Some(Method::Rosenbrock),
+            _ => None,
+        }
+    }
+
+    // order of the error estimate, used by the step size controller
+    fn error_order(&self) -> f64 {
+        match self {
+            Method::DormandPrince => 5.0,
+            Method::Rosenbrock => 3.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OdeOptions {
+    pub method: Method,
+    pub rtol: f64,
+    pub atol: f64,
+    pub max_steps: usize,
+    pub max_step: f64,
+}
+
+impl Default for OdeOptions {
+    fn default() -> Self {
+        Self {
+            method: Method::DormandPrince,
+            rtol: 1e-6,
+            atol: 1e-9,
+            max_steps: 100_000,
+            max_step: f64::INFINITY,
+        }
+    }
+}
+
+// each variant carries the time at which integration stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum OdeError {
+    StepSizeTooSmall(f64),
+    TooManySteps(f64),
+    SingularMatrix(f64),
+}
+
+impl fmt::Display for OdeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OdeError::StepSizeTooSmall(t) => write!(
+                f,
+                "Step size became too small at t = {t}; the problem may be stiff (try --method stiff)"
+            ),
+            OdeError::TooManySteps(t) => write!(f, "Too many integration steps before t = {t}"),
+            OdeError::SingularMatrix(t) => write!(f, "Singular iteration matrix at t = {t}"),
+        }
+    }
+}
+
+impl Error for OdeError {}
+
+// one accepted step, with derivatives at both ends for interpolation
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub t0: f64,
+    pub t1: f64,
+    pub y0: Vec<f64>,
+    pub y1: Vec<f64>,
+    f0: Vec<f64>,
+    f1: Vec<f64>,
+}
+
+impl Step {
+    // cubic Hermite interpolant, exact at both ends of the step
+    pub fn interpolate(&self, t: f64) -> Vec<f64> {
+        let h = self.t1 - self.t0;
+        if h == 0.0 {
+            return self.y1.clone();
+        }
+        let s = (t - self.t0) / h;
+        (0..self.y0.len())
+            .map(|i| {
+                let dy = self.y1[i] - self.y0[i];
+                self.y0[i]
+                    + s * dy
+                    + s * (s - 1.0)
+                        * ((1.0 - 2.0 * s) * dy + (s - 1.0) * h * self.f0[i] + s * h * self.f1[i])
+            })
+            .collect()
+    }
+}
+
+// y + h * sum(c_k * k_k)
+fn combine(y: &[f64], h: f64, terms: &[(f64, &[f64])]) -> Vec<f64> {
+    let mut out = y.to_vec();
+    for &(c, k) in terms {
+        if c != 0.0 {
+            for (o, &ki) in out.iter_mut().zip(k) {
+                *o += h * c * ki;
+            }
+        }
+    }
+    out
+}
+
+// proposed y1, f(y1) and local error estimate of a step attempt
+type Trial = (Vec<f64>, Vec<f64>, Vec<f64>);
+
+pub struct Integrator<'a, S: OdeSystem + ?Sized> {
+    system: &'a S,
+    options: OdeOptions,
+    t: f64,
+    y: Vec<f64>,
+    f: Vec<f64>,
+    h: f64,
+    steps: usize,
+}
+
+impl<'a, S: OdeSystem + ?Sized> Integrator<'a, S> {
+    pub fn new(system: &'a S, t0: f64, y0: Vec<f64>, options: OdeOptions) -> Self {
+        let f = system_rhs(system, t0, &y0);
+        let mut integrator = Self {
+            system,
+            options,
+            t: t0,
+            y: y0,
+            f,
+            h: 0.0,
+            steps: 0,
+        };
+        integrator.h = integrator.initial_step();
+        integrator
+    }
+
+    pub fn time(&self) -> f64 {
+        self.t
+    }
+
+    pub fn state(&self) -> &[f64] {
+        &self.y
+    }
+
+    // scaled RMS norm used for error control
+    fn norm(&self, y0: &[f64], y1: &[f64], e: &[f64]) -> f64 {
+        if e.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = e
+            .iter()
+            .enumerate()
+            .map(|(i, ei)| {
+                let scale = self.options.atol + self.options.rtol * y0[i].abs().max(y1[i].abs());
+                (ei / scale).powi(2)
+            })
+            .sum();
+        (sum / e.len() as f64).sqrt()
+    }
+
+    // starting step size following Hairer, Norsett and Wanner
+    fn initial_step(&self) -> f64 {
+        let zeros = vec![0.0; self.y.len()];
+        let d0 = self.norm(&self.y, &self.y, &self.y);
+        let d1 = self.norm(&self.y, &self.y, &self.f);
+        let h0 = if d0 < 1e-5 || d1 < 1e-5 {
+            1e-6
+        } else {
+            0.01 * d0 / d1
+        };
+        let y1 = combine(&self.y, h0, &[(1.0, &self.f)]);
+        let f1 = system_rhs(self.system, self.t + h0, &y1);
+        let df: Vec<f64> = f1.iter().zip(&self.f).map(|(a, b)| a - b).collect();
+        let d2 = self.norm(&self.y, &zeros, &df) / h0;
+        let h1 = if d1.max(d2) <= 1e-15 {
+            (h0 * 1e-3).max(1e-6)
+        } else {
+            (0.01 / d1.max(d2)).powf(1.0 / self.options.method.error_order())
+        };
+        (100.0 * h0).min(h1).min(self.options.max_step)
+    }
+
+    // advance by one accepted step without passing `t_max`
+    pub fn step(&mut self, t_max: f64) -> Result<Step, OdeError> {
+        let exponent = -1.0 / self.options.method.error_order();
+        let mut jacobian = None;
+        loop {
+            let remaining = t_max - self.t;
+            let h_min = 16.0 * f64::EPSILON * self.t.abs().max(remaining.abs());
+            if remaining <= h_min {
+                // too close to the end for another step; snap to it
+                let step = Step {
+                    t0: self.t,
+                    t1: t_max,
+                    y0: self.y.clone(),
+                    y1: self.y.clone(),
+                    f0: self.f.clone(),
+                    f1: self.f.clone(),
+                };
+                self.t = t_max;
+                return Ok(step);
+            }
+            if self.steps >= self.options.max_steps {
+                return Err(OdeError::TooManySteps(self.t));
+            }
+            if self.h < h_min {
+                return Err(OdeError::StepSizeTooSmall(self.t));
+            }
+            let h = self.h.min(remaining).min(self.options.max_step);
+
+            let (y1, f1, err) = match self.options.method {
+                Method::DormandPrince => self.dormand_prince(h),
+                Method::Rosenbrock => {
+                    let j = jacobian.get_or_insert_with(|| self.system.jacobian(self.t, &self.y));
+                    self.rosenbrock(h, j)?
+                }
+            };
+            self.steps += 1;
+
+            let error = self.norm(&self.y, &y1, &err);
+            let finite = error.is_finite() && y1.iter().all(|x| x.is_finite());
+            if finite && error <= 1.0 {
+                let factor = if error == 0.0 {
+                    5.0
+                } else {
+                    (0.9 * error.powf(exponent)).clamp(0.2, 5.0)
+                };
+                let step = Step {
+                    t0: self.t,
+                    t1: if h == remaining { t_max } else { self.t + h },
+                    y0: std::mem::replace(&mut self.y, y1.clone()),
+                    y1,
+                    f0: std::mem::replace(&mut self.f, f1.clone()),
+                    f1,
+                };
+                self.t = step.t1;
+                self.h = h * factor;
+                return Ok(step);
+            }
+            self.h = if finite {
+                h * (0.9 * error.powf(exponent)).clamp(0.2, 1.0)
+            } else {
+                h * 0.25
+            };
+        }
+    }
+
+    fn dormand_prince(&self, h: f64) -> Trial {
+        let (t, y) = (self.t, &self.y);
+        let k1 = &self.f;
+        let k2 = system_rhs(self.system, t + h / 5.0, &combine(y, h, &[(1.0 / 5.0, k1)]));
+        let k3 = system_rhs(
+            self.system,
+            t + 3.0 * h / 10.0,
+            &combine(y, h, &[(3.0 / 40.0, k1), (9.0 / 40.0, &k2)]),
+        );
+        let k4 = system_rhs(
+            self.system,
+            t + 4.0 * h / 5.0,
+            &combine(
+                y,
+                h,
+                &[(44.0 / 45.0, k1), (-56.0 / 15.0, &k2), (32.0 / 9.0, &k3)],
+            ),
+        );
+        let k5 = system_rhs(
+            self.system,
+            t + 8.0 * h / 9.0,
+            &combine(
+                y,
+                h,
+                &[
+                    (19372.0 / 6561.0, k1),
+                    (-25360.0 / 2187.0, &k2),
+                    (64448.0 / 6561.0, &k3),
+                    (-212.0 / 729.0, &k4),
+                ],
+            ),
+        );
+        let k6 = system_rhs(
+            self.system,
+            t + h,
+            &combine(
+                y,
+                h,
+                &[
+                    (9017.0 / 3168.0, k1),
+                    (-355.0 / 33.0, &k2),
+                    (46732.0 / 5247.0, &k3),
+                    (49.0 / 176.0, &k4),
+                    (-5103.0 / 18656.0, &k5),
+                ],
+            ),
+        );
+        let y1 = combine(
+            y,
+            h,
+            &[
+                (35.0 / 384.0, k1),
+                (500.0 / 1113.0, &k3),
+                (125.0 / 192.0, &k4),
+                (-2187.0 / 6784.0, &k5),
+                (11.0 / 84.0, &k6),
+            ],
+        );
+        let k7 = system_rhs(self.system, t + h, &y1);
+        // difference between the fifth and fourth order solutions
+        let zeros = vec![0.0; y.len()];
+        let err = combine(
+            &zeros,
+            h,
+            &[
+                (71.0 / 57600.0, k1),
+                (-71.0 / 16695.0, &k3),
+                (71.0 / 1920.0, &k4),
+                (-17253.0 / 339200.0, &k5),
+                (22.0 / 525.0, &k6),
+                (-1.0 / 40.0, &k7),
+            ],
+        );
+        (y1, k7, err)
+    }
+
+    fn rosenbrock(&self, h: f64, jacobian: &Matrix) -> Result<Trial, OdeError> {
+        let n = self.y.len();
+        let d = 1.0 / (2.0 + std::f64::consts::SQRT_2);
+        let e32 = 6.0 + std::f64::consts::SQRT_2;
+
+        // W = I - h d J
+        let mut w = Matrix::identity(n);
+        for i in 0..n {
+            for j in 0..n {
+                w[(i, j)] -= h * d * jacobian[(i, j)];
+            }
+        }
+        let lu = Lu::factor(w).ok_or(OdeError::SingularMatrix(self.t))?;
+
+        let f0 = &self.f;
+        let k1 = lu.solve(f0);
+        let f1 = system_rhs(
+            self.system,
+            self.t + h / 2.0,
+            &combine(&self.y, h, &[(0.5, &k1)]),
+        );
+        let rhs: Vec<f64> = (0..n).map(|i| f1[i] - k1[i]).collect();
+        let k2: Vec<f64> = lu.solve(&rhs).iter().zip(&k1).map(|(a, b)| a + b).collect();
+        let y1 = combine(&self.y, h, &[(1.0, &k2)]);
+        let f2 = system_rhs(self.system, self.t + h, &y1);
+        let rhs: Vec<f64> = (0..n)
+            .map(|i| f2[i] - e32 * (k2[i] - f1[i]) - 2.0 * (k1[i] - f0[i]))
+            .collect();
+        let k3 = lu.solve(&rhs);
+        let err: Vec<f64> = (0..n)
+            .map(|i| h / 6.0 * (k1[i] - 2.0 * k2[i] + k3[i]))
+            .collect();
+        Ok((y1, f2, err))
+    }
+}
+
+fn system_rhs<S: OdeSystem + ?Sized>(system: &S, t: f64, y: &[f64]) -> Vec<f64> {
+    let mut dy = vec![0.0; system.dim()];
+    system.rhs(t, y, &mut dy);
+    dy
+}
+
+// solution at each of the increasing `times`, starting from y0 at times[0]
+pub fn integrate<S: OdeSystem + ?Sized>(
+    system: &S,
+    y0: Vec<f64>,
+    times: &[f64],
+    options: OdeOptions,
+) -> Result<Vec<Vec<f64>>, OdeError> {
+    let Some(&t0) = times.first() else {
+        return Ok(Vec::new());
+    };
+    let t_end = *times.last().expect("times is not empty");
+    let mut out = Vec::with_capacity(times.len());
+    let mut integrator = Integrator::new(system, t0, y0, options);
+    let mut next = 0;
+    while next < times.len() && times[next] <= t0 {
+        out.push(integrator.state().to_vec());
+        next += 1;
+    }
+    while next < times.len() {
+        let step = integrator.step(t_end)?;
+        while next < times.len() && times[next] <= step.t1 {
+            out.push(step.interpolate(times[next]));
+            next += 1;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // y' = -k y, solved by y0 exp(-k t)
+    struct Decay(f64);
+
+    impl OdeSystem for Decay {
+        fn dim(&self) -> usize {
+            1
+        }
+
+        fn rhs(&self, _t: f64, y: &[f64], dy: &mut [f64]) {
+            dy[0] = -self.0 * y[0];
+        }
+
+        fn jacobian(&self, _t: f64, _y: &[f64]) -> Matrix {
+            Matrix::from_rows(&[vec![-self.0]])
+        }
+    }
+
+    // the Robertson problem, a standard stiff test
+    struct Robertson;
+
+    impl OdeSystem for Robertson {
+        fn dim(&self) -> usize {
+            3
+        }
+
+        fn rhs(&self, _t: f64, y: &[f64], dy: &mut [f64]) {
+            let (a, b, c) = (0.04 * y[0], 1e4 * y[1] * y[2], 3e7 * y[1] * y[1]);
+            dy[0] = -a + b;
+            dy[1] = a - b - c;
+            dy[2] = c;
+        }
+
+        fn jacobian(&self, _t: f64, y: &[f64]) -> Matrix {
+            Matrix::from_rows(&[
+                vec![-0.04, 1e4 * y[2], 1e4 * y[1]],
+                vec![0.04, -1e4 * y[2] - 6e7 * y[1], -1e4 * y[1]],
+                vec![0.0, 6e7 * y[1], 0.0],
+            ])
+        }
+    }
+
+    fn options(method: Method) -> OdeOptions {
+        OdeOptions {
+            method,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn both_methods_follow_exponential_decay() {
+        let times = [0.0, 0.5, 1.0, 2.0, 5.0];
+        for method in [Method::DormandPrince, Method::Rosenbrock] {
+            let ys = integrate(&Decay(1.5), vec![2.0], &times, options(method)).unwrap();
+            assert_eq!(ys.len(), times.len());
+            for (t, y) in times.iter().zip(&ys) {
+                let exact = 2.0 * (-1.5 * t).exp();
+                assert!((y[0] - exact).abs() < 1e-4, "{method:?} at t = {t}");
+            }
+        }
+    }
+
+    #[test]
+    fn rosenbrock_handles_stiff_problems() {
+        let times = [0.0, 40.0];
+        let ys = integrate(
+            &Robertson,
+            vec![1.0, 0.0, 0.0],
+            &times,
+            options(Method::Rosenbrock),
+        )
+        .unwrap();
+        let y = &ys[1];
+        assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+        assert!((y[0] - 0.7158).abs() < 1e-3);
+    }
+
+    #[test]
+    fn step_limit_is_reported() {
+        let limited = OdeOptions {
+            max_steps: 3,
+            max_step: 1.0,
+            ..options(Method::DormandPrince)
+        };
+        let result = integrate(&Decay(1.0), vec![1.0], &[0.0, 100.0], limited);
+        assert!(matches!(result, Err(OdeError::TooManySteps(_))));
+    }
+}
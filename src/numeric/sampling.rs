@@ -0,0 +1,113 @@
+// space-filling designs on the unit hypercube for global exploration of
+// parameter space: Latin hypercubes and Sobol low-discrepancy sequences
+use super::random::Rng;
+
+// n points in [0, 1)^dims; each coordinate has exactly one point in each of
+// the n strata of width 1/n
+pub fn latin_hypercube(n: usize, dims: usize, rng: &mut Rng) -> Vec<Vec<f64>> {
+    let mut points = vec![vec![0.0; dims]; n];
+    for d in 0..dims {
+        // Fisher-Yates shuffle of the strata
+        let mut strata: Vec<usize> = (0..n).collect();
+        for k in (1..n).rev() {
+            let l = (rng.next_u64() % (k as u64 + 1)) as usize;
+            strata.swap(k, l);
+        }
+        for (point, stratum) in points.iter_mut().zip(strata) {
+            point[d] = (stratum as f64 + rng.uniform()) / n as f64;
+        }
+    }
+    points
+}
+
+// primitive polynomials and initial direction numbers of dimensions 2 to 16
+// from Joe and Kuo (2008): degree s, coefficients a and m_1..m_s
+static DIRECTIONS: &[(u32, u32, &[u32])] = &[
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+];
+
+const BITS: usize = 32;
+
+// Sobol sequence in Gray code order; the initial point at the origin is
+// skipped
+#[derive(Debug, Clone)]
+pub struct Sobol {
+    directions: Vec<[u32; BITS]>,
+    state: Vec<u32>,
+    index: u32,
+}
+
+impl Sobol {
+    pub const MAX_DIMENSIONS: usize = DIRECTIONS.len() + 1;
+
+    // None beyond MAX_DIMENSIONS
+    pub fn new(dims: usize) -> Option<Self> {
+        if dims > Self::MAX_DIMENSIONS {
+            return None;
+        }
+        let directions = (0..dims)
+            .map(|d| {
+                let mut v = [0u32; BITS];
+                if d == 0 {
+                    for (k, vk) in v.iter_mut().enumerate() {
+                        *vk = 1 << (BITS - 1 - k);
+                    }
+                    return v;
+                }
+                let (s, a, m) = DIRECTIONS[d - 1];
+                let s = s as usize;
+                for k in 0..BITS {
+                    v[k] = if k < s {
+                        m[k] << (BITS - 1 - k)
+                    } else {
+                        let mut x = v[k - s] ^ (v[k - s] >> s);
+                        for i in 1..s {
+                            if (a >> (s - 1 - i)) & 1 == 1 {
+                                x ^= v[k - i];
+                            }
+                        }
+                        x
+                    };
+                }
+                v
+            })
+            .collect();
+        Some(Self {
+            directions,
+            state: vec![0; dims],
+            index: 0,
+        })
+    }
+}
+
+impl Iterator for Sobol {
+    type Item = Vec<f64>;
+
+    fn next(&mut self) -> Option<Vec<f64>> {
+        // flip the direction of the lowest zero bit of the previous index
+        let c = self.index.trailing_ones() as usize;
+        if c >= BITS {
+            return None;
+        }
+        self.index += 1;
+        for (x, v) in self.state.iter_mut().zip(&self.directions) {
+            *x ^= v[c];
+        }
+        let scale = 1.0 / (1u64 << BITS) as f64;
+        Some(self.state.iter().map(|&x| x as f64 * scale).collect())
+    }
+}
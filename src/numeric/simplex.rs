@@ -0,0 +1,503 @@
+// dense two-phase simplex for small and medium linear programs
+//
+//     maximize    c . x
+//     subject to  a_i . x  (<=, =, >=)  b_i
+//                 lower <= x <= upper
+//
+// bounds may be infinite. Bland's rule is used for pivoting, which is slow
+// but never cycles on the degenerate problems typical of flux balance.
+use std::error::Error;
+use std::fmt;
+
+const EPS: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    LessEqual,
+    Equal,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone)]
+pub struct Constraint {
+    pub coefficients: Vec<f64>,
+    pub relation: Relation,
+    pub rhs: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LpError {
+    Infeasible,
+    Unbounded,
+}
+
+impl fmt::Display for LpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LpError::Infeasible => write!(f, "Linear program is infeasible"),
+            LpError::Unbounded => write!(f, "Linear program is unbounded"),
+        }
+    }
+}
+
+impl Error for LpError {}
+
+#[derive(Debug, Clone)]
+pub struct LpSolution {
+    pub objective: f64,
+    pub x: Vec<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LinearProgram {
+    objective: Vec<f64>,
+    constraints: Vec<Constraint>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+}
+
+// how an original variable is expressed by nonnegative tableau columns
+#[derive(Debug, Clone, Copy)]
+enum Substitution {
+    // x = lower + y
+    Shifted(usize, f64),
+    // x = upper - y
+    Reflected(usize, f64),
+    // x = y1 - y2
+    Free(usize, usize),
+}
+
+impl LinearProgram {
+    // nonnegative variables by default
+    pub fn new(num_vars: usize) -> Self {
+        Self {
+            objective: vec![0.0; num_vars],
+            constraints: Vec::new(),
+            lower: vec![0.0; num_vars],
+            upper: vec![f64::INFINITY; num_vars],
+        }
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.objective.len()
+    }
+
+    pub fn set_objective(&mut self, c: Vec<f64>) {
+        assert_eq!(c.len(), self.num_vars());
+        self.objective = c;
+    }
+
+    pub fn set_bounds(&mut self, var: usize, lower: f64, upper: f64) {
+        self.lower[var] = lower;
+        self.upper[var] = upper;
+    }
+
+    pub fn bounds(&self, var: usize) -> (f64, f64) {
+        (self.lower[var], self.upper[var])
+    }
+
+    pub fn add_constraint(&mut self, coefficients: Vec<f64>, relation: Relation, rhs: f64) {
+        assert_eq!(coefficients.len(), self.num_vars());
+        self.constraints.push(Constraint {
+            coefficients,
+            relation,
+            rhs,
+        });
+    }
+
+    pub fn maximize(&self) -> Result<LpSolution, LpError> {
+        self.solve(1.0)
+    }
+
+    pub fn minimize(&self) -> Result<LpSolution, LpError> {
+        self.solve(-1.0)
+    }
+
+    fn solve(&self, sign: f64) -> Result<LpSolution, LpError> {
+        let n = self.num_vars();
+        for j in 0..n {
+            if self.lower[j] > self.upper[j] + EPS {
+                return Err(LpError::Infeasible);
+            }
+        }
+
+        // substitute nonnegative columns for bounded variables
+        let mut subs = Vec::with_capacity(n);
+        let mut cols = 0;
+        let mut rows: Vec<Row> = Vec::new();
+        for j in 0..n {
+            let (lo, hi) = (self.lower[j], self.upper[j]);
+            let sub = if lo.is_finite() {
+                if hi.is_finite() {
+                    rows.push((vec![(cols, 1.0)], Relation::LessEqual, hi - lo));
+                }
+                Substitution::Shifted(cols, lo)
+            } else if hi.is_finite() {
+                Substitution::Reflected(cols, hi)
+            } else {
+                cols += 1;
+                Substitution::Free(cols - 1, cols)
+            };
+            cols += 1;
+            subs.push(sub);
+        }
+
+        for con in &self.constraints {
+            let mut terms = Vec::new();
+            let mut rhs = con.rhs;
+            for (j, &a) in con.coefficients.iter().enumerate() {
+                if a == 0.0 {
+                    continue;
+                }
+                match subs[j] {
+                    Substitution::Shifted(k, lo) => {
+                        terms.push((k, a));
+                        rhs -= a * lo;
+                    }
+                    Substitution::Reflected(k, hi) => {
+                        terms.push((k, -a));
+                        rhs -= a * hi;
+                    }
+                    Substitution::Free(p, q) => {
+                        terms.push((p, a));
+                        terms.push((q, -a));
+                    }
+                }
+            }
+            rows.push((terms, con.relation, rhs));
+        }
+
+        let mut cost = vec![0.0; cols];
+        for (j, &c) in self.objective.iter().enumerate() {
+            let c = sign * c;
+            match subs[j] {
+                Substitution::Shifted(k, _) => cost[k] += c,
+                Substitution::Reflected(k, _) => cost[k] -= c,
+                Substitution::Free(p, q) => {
+                    cost[p] += c;
+                    cost[q] -= c;
+                }
+            }
+        }
+
+        let y = Tableau::build(cols, &rows).optimize(&cost)?;
+
+        let x: Vec<f64> = subs
+            .iter()
+            .map(|s| match *s {
+                Substitution::Shifted(k, lo) => clean(lo + y[k]),
+                Substitution::Reflected(k, hi) => clean(hi - y[k]),
+                Substitution::Free(p, q) => clean(y[p] - y[q]),
+            })
+            .collect();
+        let objective = self.objective.iter().zip(&x).map(|(c, v)| c * v).sum();
+        Ok(LpSolution {
+            objective: clean(objective),
+            x,
+        })
+    }
+}
+
+// snap values within tolerance of zero
+fn clean(x: f64) -> f64 {
+    if x.abs() < EPS { 0.0 } else { x }
+}
+
+// sparse constraint row over tableau columns
+type Row = (Vec<(usize, f64)>, Relation, f64);
+
+// standard form tableau: rows of [A | b], all variables nonnegative
+struct Tableau {
+    rows: Vec<Vec<f64>>,
+    basis: Vec<usize>,
+    num_structural: usize,
+    num_columns: usize,
+    artificial_start: usize,
+}
+
+impl Tableau {
+    fn build(num_structural: usize, rows: &[Row]) -> Self {
+        let num_slack = rows
+            .iter()
+            .filter(|(_, rel, _)| *rel != Relation::Equal)
+            .count();
+        let artificial_start = num_structural + num_slack;
+
+        // every row gets an artificial variable unless its slack can start basic
+        let mut needs_artificial = Vec::with_capacity(rows.len());
+        for (_, rel, rhs) in rows {
+            let flip = *rhs < 0.0;
+            let rel = match (rel, flip) {
+                (Relation::LessEqual, true) => Relation::GreaterEqual,
+                (Relation::GreaterEqual, true) => Relation::LessEqual,
+                (r, _) => *r,
+            };
+            needs_artificial.push(rel != Relation::LessEqual);
+        }
+        let num_artificial = needs_artificial.iter().filter(|&&a| a).count();
+        let num_columns = artificial_start + num_artificial;
+
+        let mut table = Vec::with_capacity(rows.len());
+        let mut basis = Vec::with_capacity(rows.len());
+        let mut slack = num_structural;
+        let mut artificial = artificial_start;
+        for (i, (terms, rel, rhs)) in rows.iter().enumerate() {
+            let mut row = vec![0.0; num_columns + 1];
+            let s = if *rhs < 0.0 { -1.0 } else { 1.0 };
+            for &(k, a) in terms {
+                row[k] += s * a;
+            }
+            row[num_columns] = s * rhs;
+            match rel {
+                Relation::LessEqual => {
+                    row[slack] = s;
+                    if !needs_artificial[i] {
+                        basis.push(slack);
+                    }
+                    slack += 1;
+                }
+                Relation::GreaterEqual => {
+                    row[slack] = -s;
+                    if !needs_artificial[i] {
+                        basis.push(slack);
+                    }
+                    slack += 1;
+                }
+                Relation::Equal => {}
+            }
+            if needs_artificial[i] {
+                row[artificial] = 1.0;
+                basis.push(artificial);
+                artificial += 1;
+            }
+            table.push(row);
+        }
+
+        Self {
+            rows: table,
+            basis,
+            num_structural,
+            num_columns,
+            artificial_start,
+        }
+    }
+
+    fn rhs(&self, i: usize) -> f64 {
+        self.rows[i][self.num_columns]
+    }
+
+    fn pivot(&mut self, r: usize, c: usize) {
+        let p = self.rows[r][c];
+        for v in self.rows[r].iter_mut() {
+            *v /= p;
+        }
+        let pivot_row = self.rows[r].clone();
+        for (i, row) in self.rows.iter_mut().enumerate() {
+            if i == r {
+                continue;
+            }
+            let f = row[c];
+            if f.abs() < f64::EPSILON {
+                continue;
+            }
+            for (v, pv) in row.iter_mut().zip(&pivot_row) {
+                *v -= f * pv;
+            }
+            row[c] = 0.0;
+        }
+        self.basis[r] = c;
+    }
+
+    // maximize cost over columns [0, limit) with Bland's rule
+    fn run(&mut self, cost: &[f64], limit: usize) -> Result<(), LpError> {
+        loop {
+            // reduced costs
+            let mut is_basic = vec![false; self.num_columns];
+            for &b in &self.basis {
+                is_basic[b] = true;
+            }
+            let mut entering = None;
+            for j in 0..limit {
+                if is_basic[j] {
+                    continue;
+                }
+                let mut d = cost[j];
+                for (i, &b) in self.basis.iter().enumerate() {
+                    d -= cost[b] * self.rows[i][j];
+                }
+                if d > EPS {
+                    entering = Some(j);
+                    break;
+                }
+            }
+            let Some(c) = entering else {
+                return Ok(());
+            };
+
+            // ratio test; ties broken by smallest basic index
+            let mut leaving: Option<(usize, f64)> = None;
+            for i in 0..self.rows.len() {
+                let a = self.rows[i][c];
+                if a > EPS {
+                    let ratio = self.rhs(i) / a;
+                    leaving = match leaving {
+                        Some((r, best))
+                            if ratio > best + EPS
+                                || (ratio > best - EPS && self.basis[i] > self.basis[r]) =>
+                        {
+                            Some((r, best))
+                        }
+                        _ => Some((i, ratio)),
+                    };
+                }
+            }
+            match leaving {
+                Some((r, _)) => self.pivot(r, c),
+                None => return Err(LpError::Unbounded),
+            }
+        }
+    }
+
+    fn optimize(mut self, cost: &[f64]) -> Result<Vec<f64>, LpError> {
+        // phase one: drive artificial variables to zero
+        if self.artificial_start < self.num_columns {
+            let mut phase_one = vec![0.0; self.num_columns];
+            for c in phase_one[self.artificial_start..].iter_mut() {
+                *c = -1.0;
+            }
+            self.run(&phase_one, self.num_columns)?;
+            let infeasibility: f64 = self
+                .basis
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| b >= self.artificial_start)
+                .map(|(i, _)| self.rhs(i))
+                .sum();
+            if infeasibility > 1e-7 {
+                return Err(LpError::Infeasible);
+            }
+            // pivot remaining (degenerate) artificials out of the basis
+            for r in 0..self.rows.len() {
+                if self.basis[r] < self.artificial_start {
+                    continue;
+                }
+                let col = (0..self.artificial_start)
+                    .find(|&j| self.rows[r][j].abs() > EPS && !self.basis.contains(&j));
+                if let Some(c) = col {
+                    self.pivot(r, c);
+                }
+            }
+        }
+
+        // phase two over structural and slack columns only
+        let mut phase_two = vec![0.0; self.num_columns];
+        phase_two[..self.num_structural].copy_from_slice(cost);
+        self.run(&phase_two, self.artificial_start)?;
+
+        let mut y = vec![0.0; self.num_structural];
+        for (i, &b) in self.basis.iter().enumerate() {
+            if b < self.num_structural {
+                y[b] = self.rhs(i).max(0.0);
+            }
+        }
+        Ok(y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn maximizes_over_a_polytope() {
+        let mut lp = LinearProgram::new(2);
+        lp.set_objective(vec![3.0, 2.0]);
+        lp.add_constraint(vec![1.0, 1.0], Relation::LessEqual, 4.0);
+        lp.add_constraint(vec![1.0, 3.0], Relation::LessEqual, 6.0);
+        lp.add_constraint(vec![1.0, 0.0], Relation::LessEqual, 3.0);
+        let s = lp.maximize().unwrap();
+        assert!(close(s.objective, 11.0));
+        assert!(close(s.x[0], 3.0) && close(s.x[1], 1.0));
+    }
+
+    #[test]
+    fn minimizes_with_equality_and_lower_constraints() {
+        let mut lp = LinearProgram::new(2);
+        lp.set_objective(vec![1.0, 1.0]);
+        lp.add_constraint(vec![1.0, 2.0], Relation::GreaterEqual, 4.0);
+        lp.add_constraint(vec![1.0, -1.0], Relation::Equal, 1.0);
+        let s = lp.minimize().unwrap();
+        assert!(close(s.objective, 3.0));
+        assert!(close(s.x[0], 2.0) && close(s.x[1], 1.0));
+    }
+
+    #[test]
+    fn reports_infeasible_constraints() {
+        let mut lp = LinearProgram::new(2);
+        lp.add_constraint(vec![1.0, 1.0], Relation::LessEqual, 1.0);
+        lp.add_constraint(vec![1.0, 1.0], Relation::GreaterEqual, 2.0);
+        assert_eq!(lp.maximize().unwrap_err(), LpError::Infeasible);
+    }
+
+    #[test]
+    fn reports_crossed_bounds_as_infeasible() {
+        let mut lp = LinearProgram::new(1);
+        lp.set_bounds(0, 2.0, 1.0);
+        assert_eq!(lp.minimize().unwrap_err(), LpError::Infeasible);
+    }
+
+    #[test]
+    fn reports_unbounded_objective() {
+        let mut lp = LinearProgram::new(2);
+        lp.set_objective(vec![1.0, 0.0]);
+        lp.add_constraint(vec![1.0, -1.0], Relation::LessEqual, 1.0);
+        assert_eq!(lp.maximize().unwrap_err(), LpError::Unbounded);
+    }
+
+    #[test]
+    fn shifts_variables_with_finite_lower_bounds() {
+        let mut lp = LinearProgram::new(1);
+        lp.set_objective(vec![1.0]);
+        lp.set_bounds(0, 2.0, 5.0);
+        assert!(close(lp.minimize().unwrap().x[0], 2.0));
+        assert!(close(lp.maximize().unwrap().x[0], 5.0));
+    }
+
+    #[test]
+    fn reflects_variables_with_only_an_upper_bound() {
+        let mut lp = LinearProgram::new(1);
+        lp.set_objective(vec![1.0]);
+        lp.set_bounds(0, f64::NEG_INFINITY, -3.0);
+        assert!(close(lp.maximize().unwrap().x[0], -3.0));
+        assert_eq!(lp.minimize().unwrap_err(), LpError::Unbounded);
+    }
+
+    #[test]
+    fn splits_free_variables() {
+        let mut lp = LinearProgram::new(2);
+        lp.set_objective(vec![1.0, 0.0]);
+        lp.set_bounds(0, f64::NEG_INFINITY, f64::INFINITY);
+        lp.set_bounds(1, f64::NEG_INFINITY, f64::INFINITY);
+        lp.add_constraint(vec![1.0, 0.0], Relation::GreaterEqual, -7.0);
+        lp.add_constraint(vec![0.0, 1.0], Relation::Equal, -4.0);
+        let s = lp.minimize().unwrap();
+        assert!(close(s.x[0], -7.0) && close(s.x[1], -4.0));
+    }
+
+    // Beale's example cycles under the largest-coefficient rule
+    #[test]
+    fn terminates_on_degenerate_cycling_example() {
+        let mut lp = LinearProgram::new(4);
+        lp.set_objective(vec![0.75, -20.0, 0.5, -6.0]);
+        lp.add_constraint(vec![0.25, -8.0, -1.0, 9.0], Relation::LessEqual, 0.0);
+        lp.add_constraint(vec![0.5, -12.0, -0.5, 3.0], Relation::LessEqual, 0.0);
+        lp.add_constraint(vec![0.0, 0.0, 1.0, 0.0], Relation::LessEqual, 1.0);
+        let s = lp.maximize().unwrap();
+        assert!(close(s.objective, 1.25));
+        assert!(close(s.x[0], 1.0) && close(s.x[2], 1.0));
+    }
+}
@@ -0,0 +1,184 @@
+// time courses of the network, evaluated directly from its rate laws
+pub mod approximate;
+pub mod deterministic;
+pub mod events;
+pub mod kinetics;
+pub mod stochastic;
+
+pub use deterministic::{RateEquations, SimulationOptions, simulate};
+pub use events::Events;
+pub use kinetics::{Formula, Kinetics, Observables};
+pub use stochastic::{
+    StochasticMethod, StochasticModel, StochasticOptions, ensemble, simulate_stochastic,
+};
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::data::Overflow;
+use crate::numeric::OdeError;
+
+#[derive(Debug)]
+pub enum SimulationError {
+    UnknownSymbol(String),
+    Ode(OdeError),
+    // reactions that cannot fire a whole number of times
+    NonIntegerStoichiometry(String),
+    TooManyEvents(f64),
+    // events may only change species and parameters
+    EventTarget(String),
+    // events that keep triggering each other at one time
+    EventCascade(f64),
+    // analyses that cannot account for discontinuities
+    UnsupportedEvents(&'static str),
+    Overflow(Overflow),
+    // options out of range, with the reason
+    InvalidOption(String),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationError::UnknownSymbol(s) => write!(f, "Unknown symbol '{s}' in rate law"),
+            SimulationError::Ode(e) => write!(f, "{e}"),
+            SimulationError::NonIntegerStoichiometry(r) => write!(
+                f,
+                "Reaction '{r}' has fractional stoichiometry and cannot be simulated stochastically"
+            ),
+            SimulationError::TooManyEvents(t) => {
+                write!(f, "Too many reaction events before t = {t}")
+            }
+            SimulationError::EventTarget(s) => {
+                write!(
+                    f,
+                    "Event cannot assign to '{s}', which is not a species or parameter"
+                )
+            }
+            SimulationError::EventCascade(t) => {
+                write!(f, "Events keep triggering each other at t = {t}")
+            }
+            SimulationError::UnsupportedEvents(what) => {
+                write!(f, "Events are not supported in {what}")
+            }
+            SimulationError::Overflow(e) => write!(f, "{e}"),
+            SimulationError::InvalidOption(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl Error for SimulationError {}
+
+impl From<OdeError> for SimulationError {
+    fn from(e: OdeError) -> Self {
+        SimulationError::Ode(e)
+    }
+}
+
+impl From<Overflow> for SimulationError {
+    fn from(e: Overflow) -> Self {
+        SimulationError::Overflow(e)
+    }
+}
+
+// sampled trajectory; states[k][i] is species i at times[k], and
+// observed[k][l] is observable l
+#[derive(Debug, Clone)]
+pub struct TimeCourse {
+    pub species: Vec<String>,
+    pub times: Vec<f64>,
+    pub states: Vec<Vec<f64>>,
+    pub observables: Vec<String>,
+    pub observed: Vec<Vec<f64>>,
+}
+
+impl TimeCourse {
+    pub fn new(
+        species: Vec<String>,
+        times: Vec<f64>,
+        states: Vec<Vec<f64>>,
+        observables: &Observables,
+        parameters: &[f64],
+    ) -> Self {
+        let observed = states
+            .iter()
+            .map(|x| observables.eval(x, parameters))
+            .collect();
+        Self {
+            species,
+            times,
+            states,
+            observables: observables.names().to_vec(),
+            observed,
+        }
+    }
+
+    // species or observable
+    pub fn column(&self, name: &str) -> Option<Vec<f64>> {
+        if let Some(i) = self.species.iter().position(|s| s == name) {
+            return Some(self.states.iter().map(|x| x[i]).collect());
+        }
+        let l = self.observables.iter().position(|s| s == name)?;
+        Some(self.observed.iter().map(|y| y[l]).collect())
+    }
+
+    fn header(&self) -> impl Iterator<Item = String> + '_ {
+        self.species
+            .iter()
+            .chain(&self.observables)
+            .map(|s| csv_field(s))
+    }
+
+    fn rows(&self) -> impl Iterator<Item = String> + '_ {
+        self.times
+            .iter()
+            .zip(self.states.iter().zip(&self.observed))
+            .map(|(t, (x, y))| {
+                let row: Vec<String> = std::iter::once(t)
+                    .chain(x)
+                    .chain(y)
+                    .map(|v| v.to_string())
+                    .collect();
+                row.join(",")
+            })
+    }
+
+    // header `time,<species...>,<observables...>`, then one row per sample
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        let header: Vec<String> = std::iter::once("time".to_string())
+            .chain(self.header())
+            .collect();
+        writeln!(out, "{}", header.join(","))?;
+        for row in self.rows() {
+            writeln!(out, "{row}")?;
+        }
+        Ok(())
+    }
+}
+
+// runs of an ensemble stacked into one table with a leading `run` column
+pub fn write_ensemble_csv(runs: &[TimeCourse], out: &mut impl Write) -> io::Result<()> {
+    let Some(first) = runs.first() else {
+        return Ok(());
+    };
+    let header: Vec<String> = ["run".to_string(), "time".to_string()]
+        .into_iter()
+        .chain(first.header())
+        .collect();
+    writeln!(out, "{}", header.join(","))?;
+    for (r, course) in runs.iter().enumerate() {
+        for row in course.rows() {
+            writeln!(out, "{},{row}", r + 1)?;
+        }
+    }
+    Ok(())
+}
+
+// quote fields containing separators, as in RFC 4180
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
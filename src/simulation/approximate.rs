@@ -0,0 +1,287 @@
+// approximate stochastic simulation for networks where exact event-by-event
+// simulation is too slow: tau-leaping fires many events per step, the
+// chemical Langevin equation replaces them by Gaussian noise, and the hybrid
+// method uses Langevin steps for fast channels while firing slow channels
+// exactly.
+use super::SimulationError;
+use super::stochastic::{Recorder, StochasticModel, StochasticOptions};
+use crate::numeric::random::Rng;
+
+// exact steps taken when a leap would cover only a few events
+const EXACT_STEPS: usize = 100;
+
+// Cao, Gillespie and Petzold's g_i: the highest order of reaction species i
+// takes part in, corrected for reactions consuming several of it
+fn highest_order(order: u64, coefficient: u64, x: f64) -> f64 {
+    let inverse = |k: f64| 1.0 / (x - k).max(1.0);
+    match (order, coefficient) {
+        (1, _) => 1.0,
+        (2, 1) => 2.0,
+        (2, _) => 2.0 + inverse(1.0),
+        (3, 1) => 3.0,
+        (3, 2) => 1.5 * (2.0 + inverse(1.0)),
+        (3, _) => 3.0 + inverse(1.0) + 2.0 * inverse(2.0),
+        (order, _) => order as f64,
+    }
+}
+
+// pick a channel with probability proportional to its propensity
+fn choose(a: &[f64], eligible: impl Fn(usize) -> bool, rng: &mut Rng) -> Option<usize> {
+    let total: f64 = (0..a.len()).filter(|&c| eligible(c)).map(|c| a[c]).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let target = rng.uniform() * total;
+    let mut cumulative = 0.0;
+    let mut last = None;
+    for c in (0..a.len()).filter(|&c| eligible(c) && a[c] > 0.0) {
+        cumulative += a[c];
+        last = Some(c);
+        if target < cumulative {
+            break;
+        }
+    }
+    last
+}
+
+fn clamp_negative(x: &mut [f64]) {
+    for xi in x.iter_mut() {
+        *xi = xi.max(0.0);
+    }
+}
+
+impl StochasticModel {
+    fn propensities(&self, x: &[f64], a: &mut [f64]) {
+        for (c, ac) in a.iter_mut().enumerate() {
+            *ac = self.propensity(c, x);
+        }
+    }
+
+    // fixed step of the Langevin and hybrid methods
+    fn step_size(options: &StochasticOptions) -> Result<f64, SimulationError> {
+        match options.step {
+            Some(dt) if !(dt.is_finite() && dt > 0.0) => Err(SimulationError::InvalidOption(
+                format!("Time step must be finite and positive, not {dt}"),
+            )),
+            Some(dt) => Ok(dt),
+            None => {
+                let intervals = options.points.saturating_sub(1).max(1);
+                Ok(options.t_end / intervals as f64 / 10.0)
+            }
+        }
+    }
+
+    // largest leap keeping the expected relative change of every propensity
+    // below epsilon, over the non-critical channels
+    fn leap_size(&self, epsilon: f64, x: &[f64], a: &[f64], critical: &[bool]) -> f64 {
+        let n = x.len();
+        let mut mean = vec![0.0; n];
+        let mut variance = vec![0.0; n];
+        let mut g = vec![0.0_f64; n];
+        for (c, channel) in self.channels.iter().enumerate() {
+            let reactants = channel.reactants();
+            let order: u64 = reactants.iter().map(|(_, k)| k).sum();
+            for &(i, k) in &reactants {
+                g[i] = g[i].max(highest_order(order, k, x[i]));
+            }
+            if critical[c] {
+                continue;
+            }
+            for &(i, v) in &channel.change {
+                mean[i] += v as f64 * a[c];
+                variance[i] += (v * v) as f64 * a[c];
+            }
+        }
+        let mut tau = f64::INFINITY;
+        for i in (0..n).filter(|&i| g[i] > 0.0) {
+            let bound = (epsilon * x[i] / g[i]).max(1.0);
+            if mean[i] != 0.0 {
+                tau = tau.min(bound / mean[i].abs());
+            }
+            if variance[i] > 0.0 {
+                tau = tau.min(bound * bound / variance[i]);
+            }
+        }
+        tau
+    }
+
+    pub(super) fn tau_leaping(
+        &self,
+        options: &StochasticOptions,
+        rng: &mut Rng,
+        x: &mut [f64],
+        recorder: &mut Recorder,
+    ) -> Result<(), SimulationError> {
+        let m = self.num_channels();
+        let mut a = vec![0.0; m];
+        let mut t = 0.0;
+        let mut events = 0;
+        while t < options.t_end {
+            if events > options.max_events {
+                return Err(SimulationError::TooManyEvents(t));
+            }
+            recorder.record_through(t, x);
+            let horizon = recorder.next_time().min(options.t_end);
+            self.propensities(x, &mut a);
+            let total: f64 = a.iter().sum();
+            if total <= 0.0 {
+                return Ok(());
+            }
+
+            // channels that could exhaust one of their reactants in a few firings
+            let critical: Vec<bool> = (0..m)
+                .map(|c| {
+                    a[c] > 0.0
+                        && self.channels[c].change.iter().any(|&(i, v)| {
+                            v < 0
+                                && (x[i] / v.unsigned_abs() as f64).floor()
+                                    < options.critical as f64
+                        })
+                })
+                .collect();
+            let mut tau_leap = self.leap_size(options.epsilon, x, &a, &critical);
+
+            if tau_leap < 10.0 / total {
+                // a leap would cover only a few events; simulate them exactly
+                for _ in 0..EXACT_STEPS {
+                    let total: f64 = a.iter().sum();
+                    if total <= 0.0 {
+                        return Ok(());
+                    }
+                    let dt = rng.exponential(total);
+                    if t + dt >= horizon {
+                        t = horizon;
+                        break;
+                    }
+                    t += dt;
+                    let fired = choose(&a, |_| true, rng).expect("positive total propensity");
+                    self.fire(fired, x, 1.0);
+                    for &c in &self.dependents[fired] {
+                        a[c] = self.propensity(c, x);
+                    }
+                    events += 1;
+                }
+                continue;
+            }
+
+            let critical_total: f64 = (0..m).filter(|&c| critical[c]).map(|c| a[c]).sum();
+            loop {
+                let tau_critical = if critical_total > 0.0 {
+                    rng.exponential(critical_total)
+                } else {
+                    f64::INFINITY
+                };
+                let tau = tau_leap.min(tau_critical).min(horizon - t);
+                let mut y = x.to_vec();
+                for c in (0..m).filter(|&c| !critical[c] && a[c] > 0.0) {
+                    let k = rng.poisson(a[c] * tau);
+                    self.fire(c, &mut y, k);
+                    events += k as u64;
+                }
+                if tau == tau_critical {
+                    let c = choose(&a, |c| critical[c], rng).expect("positive critical propensity");
+                    self.fire(c, &mut y, 1.0);
+                    events += 1;
+                }
+                if y.iter().all(|&yi| yi >= 0.0) {
+                    x.copy_from_slice(&y);
+                    t += tau;
+                    break;
+                }
+                // the leap overdrew a reactant; retry with half the step
+                tau_leap /= 2.0;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn langevin(
+        &self,
+        options: &StochasticOptions,
+        rng: &mut Rng,
+        x: &mut [f64],
+        recorder: &mut Recorder,
+    ) -> Result<(), SimulationError> {
+        let dt = Self::step_size(options)?;
+        let mut a = vec![0.0; self.num_channels()];
+        let mut t = 0.0;
+        while t < options.t_end {
+            recorder.record_through(t, x);
+            let h = dt.min(recorder.next_time().min(options.t_end) - t);
+            self.propensities(x, &mut a);
+            for (c, &ac) in a.iter().enumerate() {
+                if ac > 0.0 {
+                    let k = ac * h + (ac * h).sqrt() * rng.normal();
+                    self.fire(c, x, k);
+                }
+            }
+            clamp_negative(x);
+            t += h;
+        }
+        Ok(())
+    }
+
+    pub(super) fn hybrid(
+        &self,
+        options: &StochasticOptions,
+        rng: &mut Rng,
+        x: &mut [f64],
+        recorder: &mut Recorder,
+    ) -> Result<(), SimulationError> {
+        let dt = Self::step_size(options)?;
+        let m = self.num_channels();
+        let mut a = vec![0.0; m];
+        let mut t = 0.0;
+        // integrated slow propensity since the last slow event, and its target
+        let mut elapsed = 0.0;
+        let mut target = rng.exponential(1.0);
+        while t < options.t_end {
+            recorder.record_through(t, x);
+            let h = dt.min(recorder.next_time().min(options.t_end) - t);
+            self.propensities(x, &mut a);
+
+            // fast channels fire often within a step and have ample reactants
+            let fast: Vec<bool> = (0..m)
+                .map(|c| {
+                    a[c] * h >= options.fast
+                        && self.channels[c].change.iter().all(|&(i, v)| {
+                            v >= 0 || x[i] >= options.critical as f64 * v.abs() as f64
+                        })
+                })
+                .collect();
+            let fast_a: Vec<f64> = (0..m).map(|c| if fast[c] { a[c] } else { 0.0 }).collect();
+
+            // slow channels fire exactly when their integrated propensity
+            // reaches an exponentially distributed target
+            let slow_total = |a: &[f64]| (0..m).filter(|&c| !fast[c]).map(|c| a[c]).sum::<f64>();
+            let mut total = slow_total(&a);
+            let mut remaining = total * h;
+            while total > 0.0 && elapsed + remaining >= target {
+                remaining -= target - elapsed;
+                elapsed = 0.0;
+                target = rng.exponential(1.0);
+                let Some(fired) = choose(&a, |c| !fast[c], rng) else {
+                    break;
+                };
+                self.fire(fired, x, 1.0);
+                for &c in &self.dependents[fired] {
+                    a[c] = self.propensity(c, x);
+                }
+                let updated = slow_total(&a);
+                remaining *= if total > 0.0 { updated / total } else { 0.0 };
+                total = updated;
+            }
+            elapsed += remaining;
+
+            for (c, &ac) in fast_a.iter().enumerate() {
+                if ac > 0.0 {
+                    let k = ac * h + (ac * h).sqrt() * rng.normal();
+                    self.fire(c, x, k);
+                }
+            }
+            clamp_negative(x);
+            t += h;
+        }
+        Ok(())
+    }
+}
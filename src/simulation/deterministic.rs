@@ -0,0 +1,195 @@
+// the rate equations dx/dt = N_V r(x, p), with N_V the volume-scaled
+// stoichiometry from `Network::rate_matrix`
+use super::events::{self, Events};
+use super::kinetics::{Kinetics, Observables};
+use super::{SimulationError, TimeCourse};
+use crate::data::Matrix;
+use crate::network::Network;
+use crate::numeric::ode::{self, OdeOptions, OdeSystem};
+
+#[derive(Debug, Clone)]
+pub struct RateEquations {
+    kinetics: Kinetics,
+    stoichiometry: Matrix,
+    parameters: Vec<f64>,
+    events: Events,
+}
+
+impl RateEquations {
+    pub fn new(network: &Network) -> Result<Self, SimulationError> {
+        Ok(Self {
+            kinetics: Kinetics::new(network)?,
+            stoichiometry: network.rate_matrix(),
+            parameters: network.parameter_values(),
+            events: Events::new(network)?,
+        })
+    }
+
+    pub fn kinetics(&self) -> &Kinetics {
+        &self.kinetics
+    }
+
+    pub fn parameters(&self) -> &[f64] {
+        &self.parameters
+    }
+
+    pub fn events(&self) -> &Events {
+        &self.events
+    }
+
+    pub fn set_parameter(&mut self, j: usize, value: f64) {
+        self.parameters[j] = value;
+    }
+
+    // volume-scaled stoichiometry N_V
+    pub fn stoichiometry(&self) -> &Matrix {
+        &self.stoichiometry
+    }
+
+    pub fn rates(&self, x: &[f64]) -> Vec<f64> {
+        let mut r = vec![0.0; self.kinetics.num_reactions()];
+        self.kinetics.rates(x, &self.parameters, &mut r);
+        r
+    }
+
+    // d r / d x, reactions by species
+    pub fn rate_jacobian(&self, x: &[f64]) -> Matrix {
+        self.kinetics.rate_jacobian(x, &self.parameters)
+    }
+
+    // d r / d p, reactions by parameters
+    pub fn rate_parameter_jacobian(&self, x: &[f64]) -> Matrix {
+        self.kinetics.rate_parameter_jacobian(x, &self.parameters)
+    }
+
+    // d f / d p, species by parameters
+    pub fn parameter_jacobian(&self, x: &[f64]) -> Matrix {
+        self.stoichiometry.mul(&self.rate_parameter_jacobian(x))
+    }
+}
+
+impl OdeSystem for RateEquations {
+    fn dim(&self) -> usize {
+        self.stoichiometry.rows()
+    }
+
+    fn rhs(&self, _t: f64, y: &[f64], dy: &mut [f64]) {
+        let r = self.rates(y);
+        dy.copy_from_slice(&self.stoichiometry.mul_vec(&r));
+    }
+
+    fn jacobian(&self, _t: f64, y: &[f64]) -> Matrix {
+        self.stoichiometry.mul(&self.rate_jacobian(y))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimulationOptions {
+    pub t_end: f64,
+    // number of evenly spaced output times, including t = 0 and t_end
+    pub points: usize,
+    pub ode: OdeOptions,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            t_end: 10.0,
+            points: 101,
+            ode: OdeOptions::default(),
+        }
+    }
+}
+
+// evenly spaced sample times from 0 to t_end, at least two of them
+pub fn output_times(t_end: f64, points: usize) -> Result<Vec<f64>, SimulationError> {
+    if !(t_end.is_finite() && t_end > 0.0) {
+        return Err(SimulationError::InvalidOption(format!(
+            "End time must be finite and positive, not {t_end}"
+        )));
+    }
+    if points < 2 {
+        return Err(SimulationError::InvalidOption(format!(
+            "At least 2 output points are needed, not {points}"
+        )));
+    }
+    Ok((0..points)
+        .map(|k| t_end * k as f64 / (points - 1) as f64)
+        .collect())
+}
+
+// integrate the network's rate equations from its initial conditions
+pub fn simulate(
+    network: &Network,
+    options: &SimulationOptions,
+) -> Result<TimeCourse, SimulationError> {
+    let system = RateEquations::new(network)?;
+    let times = output_times(options.t_end, options.points)?;
+    let states = integrate(&system, network.initial_conditions(), &times, options.ode)?;
+    Ok(TimeCourse::new(
+        network.species_names().to_vec(),
+        times,
+        states,
+        &Observables::new(network)?,
+        system.parameters(),
+    ))
+}
+
+// states of `system` at the given increasing times, starting from x0 at t = 0
+pub fn integrate(
+    system: &RateEquations,
+    x0: Vec<f64>,
+    times: &[f64],
+    options: OdeOptions,
+) -> Result<Vec<Vec<f64>>, SimulationError> {
+    if !system.events().is_empty() {
+        return events::integrate(system, x0, times, options);
+    }
+    let mut samples = vec![0.0];
+    samples.extend(times.iter().copied().filter(|&t| t > 0.0));
+    let mut states = ode::integrate(system, x0, &samples, options)?;
+    if times.first() != Some(&0.0) {
+        states.remove(0);
+    }
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    #[test]
+    fn output_times_span_the_interval() {
+        assert_eq!(output_times(2.0, 5).unwrap(), vec![0.0, 0.5, 1.0, 1.5, 2.0]);
+    }
+
+    #[test]
+    fn output_times_reject_invalid_spans() {
+        for (t_end, points) in [(0.0, 11), (-1.0, 11), (f64::NAN, 11), (f64::INFINITY, 11)] {
+            assert!(output_times(t_end, points).is_err());
+        }
+        assert!(output_times(1.0, 1).is_err());
+        assert!(output_times(1.0, 0).is_err());
+    }
+
+    #[test]
+    fn observables_are_recorded_with_the_species() {
+        let network = Parser::new(Scanner::scan(
+            "species {\n  E = 1\n}\nobservable total_E = E + ES\nrule k = 2\nE <-> ES : k * E - ES\n",
+        ))
+        .parse()
+        .unwrap();
+        let options = SimulationOptions {
+            t_end: 1.0,
+            points: 3,
+            ..Default::default()
+        };
+        let course = simulate(&network, &options).unwrap();
+        assert_eq!(course.observables, ["total_E"]);
+        for total in course.column("total_E").unwrap() {
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+    }
+}
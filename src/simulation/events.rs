@@ -0,0 +1,310 @@
+// integration across discrete events
+//
+// The integrator is stopped exactly at scheduled times, and a condition that
+// turns true within a step is located by bisection on the step's
+// interpolant. Event assignments are then applied and the integrator is
+// restarted from the new state, so no step straddles a discontinuity.
+use super::SimulationError;
+use super::deterministic::RateEquations;
+use super::kinetics::Formula;
+use crate::codegen::{self, Symbol};
+use crate::network::{AssignOp, Comparison, Network, Trigger};
+use crate::numeric::ode::{Integrator, OdeOptions, Step};
+
+// assignments applied at one time before events are no longer re-checked
+const MAX_CASCADE: usize = 1000;
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Species(usize),
+    Parameter(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    When(Formula, Comparison, Formula),
+    At(Vec<f64>),
+}
+
+#[derive(Debug, Clone)]
+struct CompiledEvent {
+    condition: Condition,
+    assignments: Vec<(Target, AssignOp, Formula)>,
+}
+
+// the network's events compiled against its species and parameter tables
+#[derive(Debug, Clone, Default)]
+pub struct Events {
+    events: Vec<CompiledEvent>,
+}
+
+impl Events {
+    pub fn new(network: &Network) -> Result<Self, SimulationError> {
+        let mut events = Vec::with_capacity(network.events().len());
+        for event in network.events() {
+            let condition = match &event.trigger {
+                Trigger::When(left, cmp, right) => Condition::When(
+                    Formula::new(left, network)?,
+                    *cmp,
+                    Formula::new(right, network)?,
+                ),
+                Trigger::At(times) => Condition::At(times.clone()),
+            };
+            let mut assignments = Vec::with_capacity(event.assignments.len());
+            for a in &event.assignments {
+                let target = match codegen::resolve(network, &a.target) {
+                    Some(Symbol::Species(i)) => Target::Species(i),
+                    Some(Symbol::Parameter(k)) => Target::Parameter(k),
+                    _ => return Err(SimulationError::EventTarget(a.target.clone())),
+                };
+                assignments.push((target, a.op, Formula::new(&a.value, network)?));
+            }
+            events.push(CompiledEvent {
+                condition,
+                assignments,
+            });
+        }
+        Ok(Self { events })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // whether the condition of each event holds; scheduled events never do
+    fn conditions(&self, x: &[f64], p: &[f64]) -> Vec<bool> {
+        self.events
+            .iter()
+            .map(|e| match &e.condition {
+                Condition::When(a, cmp, b) => cmp.holds(a.eval(x, p), b.eval(x, p)),
+                Condition::At(_) => false,
+            })
+            .collect()
+    }
+
+    // earliest scheduled time strictly after t
+    fn next_time(&self, t: f64) -> Option<f64> {
+        self.events
+            .iter()
+            .filter_map(|e| match &e.condition {
+                Condition::At(times) => times.iter().copied().filter(|&s| s > t).reduce(f64::min),
+                Condition::When(..) => None,
+            })
+            .reduce(f64::min)
+    }
+
+    fn scheduled_at(&self, t: f64) -> Vec<usize> {
+        let scheduled = |e: &CompiledEvent| match &e.condition {
+            Condition::At(times) => times.contains(&t),
+            Condition::When(..) => false,
+        };
+        (0..self.events.len())
+            .filter(|&k| scheduled(&self.events[k]))
+            .collect()
+    }
+
+    // apply the given events, with every new value computed from the state
+    // before any of them
+    fn fire(&self, fired: &[usize], x: &mut [f64], p: &mut [f64]) {
+        let updates: Vec<(Target, f64)> = fired
+            .iter()
+            .flat_map(|&k| &self.events[k].assignments)
+            .map(|(target, op, value)| {
+                let old = match *target {
+                    Target::Species(i) => x[i],
+                    Target::Parameter(k) => p[k],
+                };
+                (*target, op.apply(old, value.eval(x, p)))
+            })
+            .collect();
+        for (target, value) in updates {
+            match target {
+                Target::Species(i) => x[i] = value,
+                Target::Parameter(k) => p[k] = value,
+            }
+        }
+    }
+
+    // fire events, then any conditions they made true, until none are left;
+    // `armed` marks conditions that are false and may trigger
+    fn cascade(
+        &self,
+        mut fired: Vec<usize>,
+        t: f64,
+        x: &mut [f64],
+        p: &mut [f64],
+        armed: &mut [bool],
+    ) -> Result<(), SimulationError> {
+        for _ in 0..MAX_CASCADE {
+            if fired.is_empty() {
+                return Ok(());
+            }
+            self.fire(&fired, x, p);
+            let holds = self.conditions(x, p);
+            fired = (0..holds.len()).filter(|&k| holds[k] && armed[k]).collect();
+            for (a, h) in armed.iter_mut().zip(holds) {
+                *a = !h;
+            }
+        }
+        Err(SimulationError::EventCascade(t))
+    }
+
+    // time within the step at which the first armed condition becomes true,
+    // with the events triggered then
+    fn locate(&self, step: &Step, p: &[f64], armed: &[bool]) -> Option<(f64, Vec<usize>)> {
+        let triggered = |x: &[f64]| -> Vec<usize> {
+            let holds = self.conditions(x, p);
+            (0..holds.len()).filter(|&k| holds[k] && armed[k]).collect()
+        };
+        if triggered(&step.y1).is_empty() {
+            return None;
+        }
+        let (mut lo, mut hi) = (step.t0, step.t1);
+        let tolerance = 4.0 * f64::EPSILON * step.t1.abs().max(1.0);
+        while hi - lo > tolerance {
+            let mid = 0.5 * (lo + hi);
+            if triggered(&step.interpolate(mid)).is_empty() {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        // report the high end, where the condition is known to hold
+        Some((hi, triggered(&step.interpolate(hi))))
+    }
+}
+
+// states at the given increasing times, starting from x0 at t = 0 and
+// applying the events of `system` along the way; a state sampled at the
+// time of an event is the one after it
+pub fn integrate(
+    system: &RateEquations,
+    x0: Vec<f64>,
+    times: &[f64],
+    options: OdeOptions,
+) -> Result<Vec<Vec<f64>>, SimulationError> {
+    let events = system.events();
+    let Some(&t_end) = times.last() else {
+        return Ok(Vec::new());
+    };
+    let mut system = system.clone();
+    let mut parameters = system.parameters().to_vec();
+    let mut x = x0;
+    let mut t = 0.0;
+
+    // conditions that already hold at the start do not trigger
+    let mut armed: Vec<bool> = events
+        .conditions(&x, &parameters)
+        .into_iter()
+        .map(|h| !h)
+        .collect();
+    events.cascade(
+        events.scheduled_at(0.0),
+        t,
+        &mut x,
+        &mut parameters,
+        &mut armed,
+    )?;
+
+    let mut out = Vec::with_capacity(times.len());
+    let mut next = 0;
+    loop {
+        while next < times.len() && times[next] <= t {
+            out.push(x.clone());
+            next += 1;
+        }
+        if next == times.len() || t >= t_end {
+            break;
+        }
+        for (k, &p) in parameters.iter().enumerate() {
+            system.set_parameter(k, p);
+        }
+        let t_stop = events.next_time(t).map_or(t_end, |s| s.min(t_end));
+        let mut integrator = Integrator::new(&system, t, x.clone(), options);
+        let fired = loop {
+            let step = integrator.step(t_stop)?;
+            if let Some((tc, fired)) = events.locate(&step, &parameters, &armed) {
+                while next < times.len() && times[next] < tc {
+                    out.push(step.interpolate(times[next]));
+                    next += 1;
+                }
+                t = tc;
+                x = step.interpolate(tc);
+                break fired;
+            }
+            while next < times.len() && times[next] < step.t1 {
+                out.push(step.interpolate(times[next]));
+                next += 1;
+            }
+            for (a, h) in armed
+                .iter_mut()
+                .zip(events.conditions(&step.y1, &parameters))
+            {
+                *a = !h;
+            }
+            if step.t1 >= t_stop {
+                t = t_stop;
+                x = step.y1;
+                break events.scheduled_at(t_stop);
+            }
+        };
+        for (a, h) in armed.iter_mut().zip(events.conditions(&x, &parameters)) {
+            *a = *a && !h;
+        }
+        events.cascade(fired, t, &mut x, &mut parameters, &mut armed)?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn states(text: &str, times: &[f64]) -> Vec<Vec<f64>> {
+        let network = Parser::new(Scanner::scan(text)).parse().unwrap();
+        let system = RateEquations::new(&network).unwrap();
+        let options = OdeOptions {
+            rtol: 1e-10,
+            atol: 1e-12,
+            ..Default::default()
+        };
+        integrate(&system, network.initial_conditions(), times, options).unwrap()
+    }
+
+    #[test]
+    fn scheduled_bolus_applies_at_its_time() {
+        let x = states(
+            "species S = 1\nS -> 0 : S\nat t = 1: S += 10\n",
+            &[0.5, 1.0, 2.0],
+        );
+        let e = (-1.0f64).exp();
+        assert!((x[0][0] - (-0.5f64).exp()).abs() < 1e-8);
+        // sampled at the event, the state is the one after it
+        assert!((x[1][0] - (e + 10.0)).abs() < 1e-8);
+        assert!((x[2][0] - (e + 10.0) * e).abs() < 1e-7);
+    }
+
+    #[test]
+    fn condition_stops_production_where_it_turns_true() {
+        let x = states(
+            "param k = 1\n0 -> A : k\nwhen A > 2 then k = 0\n",
+            &[1.5, 3.0],
+        );
+        assert!((x[0][0] - 1.5).abs() < 1e-8);
+        assert!((x[1][0] - 2.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn condition_true_at_the_start_waits_to_turn_true_again() {
+        // A starts above the threshold, falls below it and rises again
+        let x = states(
+            "species A = 3\nparam k = 0\nA -> 0 : A\n0 -> A : k\n\
+             at t = 1: k = 10\nwhen A > 2 then A = 0\n",
+            &[0.5, 3.0],
+        );
+        assert!((x[0][0] - 3.0 * (-0.5f64).exp()).abs() < 1e-8);
+        assert!(x[1][0] < 2.0);
+    }
+}
@@ -0,0 +1,183 @@
+// rate laws compiled against the network's species and parameter tables,
+// so they can be evaluated without looking up names
+use super::SimulationError;
+use crate::codegen::{self, Symbol};
+use crate::data::Matrix;
+use crate::network::{BinaryOp, Expr, Function, Network};
+
+#[derive(Debug, Clone)]
+enum Node {
+    Constant(f64),
+    Species(usize),
+    Parameter(usize),
+    Neg(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Box<Node>),
+}
+
+impl Node {
+    fn compile(expr: &Expr, network: &Network) -> Result<Node, SimulationError> {
+        Ok(match expr {
+            Expr::Number(x) => Node::Constant(*x),
+            Expr::Symbol(s) => match codegen::resolve(network, s) {
+                Some(Symbol::Species(i)) => Node::Species(i),
+                Some(Symbol::Parameter(j)) => Node::Parameter(j),
+                Some(Symbol::Compartment(c)) => {
+                    Node::Constant(network.compartment_attributes(c).volume())
+                }
+                None => return Err(SimulationError::UnknownSymbol(s.clone())),
+            },
+            Expr::Neg(e) => Node::Neg(Box::new(Node::compile(e, network)?)),
+            Expr::Binary(op, a, b) => Node::Binary(
+                *op,
+                Box::new(Node::compile(a, network)?),
+                Box::new(Node::compile(b, network)?),
+            ),
+            Expr::Call(f, e) => Node::Call(*f, Box::new(Node::compile(e, network)?)),
+        })
+    }
+
+    fn eval(&self, x: &[f64], p: &[f64]) -> f64 {
+        match self {
+            Node::Constant(c) => *c,
+            Node::Species(i) => x[*i],
+            Node::Parameter(j) => p[*j],
+            Node::Neg(e) => -e.eval(x, p),
+            Node::Binary(op, a, b) => {
+                let (a, b) = (a.eval(x, p), b.eval(x, p));
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Pow => a.powf(b),
+                }
+            }
+            Node::Call(f, e) => f.apply(e.eval(x, p)),
+        }
+    }
+}
+
+// a single expression, such as an event condition or assignment
+#[derive(Debug, Clone)]
+pub struct Formula(Node);
+
+impl Formula {
+    // rules and observables in the expression are written out in full
+    pub fn new(expr: &Expr, network: &Network) -> Result<Self, SimulationError> {
+        Ok(Self(Node::compile(&network.expand(expr), network)?))
+    }
+
+    pub fn eval(&self, x: &[f64], p: &[f64]) -> f64 {
+        self.0.eval(x, p)
+    }
+}
+
+// the network's observables, evaluated alongside a trajectory
+#[derive(Debug, Clone, Default)]
+pub struct Observables {
+    names: Vec<String>,
+    formulas: Vec<Formula>,
+}
+
+impl Observables {
+    pub fn new(network: &Network) -> Result<Self, SimulationError> {
+        let mut out = Self::default();
+        for d in network.observables() {
+            out.names.push(d.name.clone());
+            out.formulas.push(Formula::new(&d.value, network)?);
+        }
+        Ok(out)
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn eval(&self, x: &[f64], p: &[f64]) -> Vec<f64> {
+        self.formulas.iter().map(|f| f.eval(x, p)).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Kinetics {
+    rates: Vec<Node>,
+    // d r_j / d x_i for each species i in the rate law of reaction j
+    derivatives: Vec<Vec<(usize, Node)>>,
+    // d r_j / d p_k for each parameter k in the rate law of reaction j
+    parameter_derivatives: Vec<Vec<(usize, Node)>>,
+    num_species: usize,
+    num_parameters: usize,
+}
+
+impl Kinetics {
+    pub fn new(network: &Network) -> Result<Self, SimulationError> {
+        let mut rates = Vec::with_capacity(network.num_reactions());
+        let mut derivatives = Vec::with_capacity(network.num_reactions());
+        let mut parameter_derivatives = Vec::with_capacity(network.num_reactions());
+        for j in 0..network.num_reactions() {
+            let law = network.rate_law(j);
+            let mut partials = Vec::new();
+            let mut parameter_partials = Vec::new();
+            for s in law.symbols() {
+                let partial = || Node::compile(&law.derivative(&s).simplify(), network);
+                match codegen::resolve(network, &s) {
+                    Some(Symbol::Species(i)) => partials.push((i, partial()?)),
+                    Some(Symbol::Parameter(k)) => parameter_partials.push((k, partial()?)),
+                    _ => {}
+                }
+            }
+            rates.push(Node::compile(&law, network)?);
+            derivatives.push(partials);
+            parameter_derivatives.push(parameter_partials);
+        }
+        Ok(Self {
+            rates,
+            derivatives,
+            parameter_derivatives,
+            num_species: network.num_species(),
+            num_parameters: network.num_parameters(),
+        })
+    }
+
+    pub fn num_reactions(&self) -> usize {
+        self.rates.len()
+    }
+
+    pub fn rate(&self, j: usize, x: &[f64], p: &[f64]) -> f64 {
+        self.rates[j].eval(x, p)
+    }
+
+    pub fn rates(&self, x: &[f64], p: &[f64], out: &mut [f64]) {
+        for (r, node) in out.iter_mut().zip(&self.rates) {
+            *r = node.eval(x, p);
+        }
+    }
+
+    // species each rate law depends on
+    pub fn dependencies(&self, j: usize) -> impl Iterator<Item = usize> + '_ {
+        self.derivatives[j].iter().map(|(i, _)| *i)
+    }
+
+    // reactions by species matrix of d r_j / d x_i
+    pub fn rate_jacobian(&self, x: &[f64], p: &[f64]) -> Matrix {
+        let mut jac = Matrix::zeros(self.rates.len(), self.num_species);
+        for (j, partials) in self.derivatives.iter().enumerate() {
+            for (i, node) in partials {
+                jac[(j, *i)] = node.eval(x, p);
+            }
+        }
+        jac
+    }
+
+    // reactions by parameters matrix of d r_j / d p_k
+    pub fn rate_parameter_jacobian(&self, x: &[f64], p: &[f64]) -> Matrix {
+        let mut jac = Matrix::zeros(self.rates.len(), self.num_parameters);
+        for (j, partials) in self.parameter_derivatives.iter().enumerate() {
+            for (k, node) in partials {
+                jac[(j, *k)] = node.eval(x, p);
+            }
+        }
+        jac
+    }
+}
@@ -0,0 +1,638 @@
+// exact stochastic simulation over integer copy numbers
+//
+// Each reaction is a channel firing at random with probability a(x) dt; a
+// reversible reaction is split into a forward and a reverse channel. For
+// mass action, a = k * prod(binomial(x_i, n_i)) over the reactants, with k
+// the stochastic rate constant. Explicit rate laws are evaluated at the copy
+// numbers; the net rate of a reversible reaction fires its forward channel
+// when positive and its reverse channel when negative. Approximations that
+// trade exactness for speed are in `approximate`.
+use std::thread;
+
+use super::deterministic::output_times;
+use super::kinetics::{Kinetics, Observables};
+use super::{SimulationError, TimeCourse};
+use crate::data::Overflow;
+use crate::network::{Complex, Network};
+use crate::numeric::random::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StochasticMethod {
+    // Gillespie's direct method
+    Direct,
+    // Gibson and Bruck's next reaction method
+    NextReaction,
+    // adaptive explicit tau-leaping of Cao, Gillespie and Petzold
+    TauLeaping,
+    // chemical Langevin equation, integrated by Euler-Maruyama
+    Langevin,
+    // Langevin for fast channels, exact events for slow channels
+    Hybrid,
+}
+
+impl StochasticMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ssa" | "gillespie" | "direct" => Some(StochasticMethod::Direct),
+            "next-reaction" | "gibson-bruck" | "nrm" => Some(StochasticMethod::NextReaction),
+            "tau-leaping" | "tau" => Some(StochasticMethod::TauLeaping),
+            "langevin" | "cle" => Some(StochasticMethod::Langevin),
+            "hybrid" => Some(StochasticMethod::Hybrid),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StochasticOptions {
+    pub method: StochasticMethod,
+    pub t_end: f64,
+    pub points: usize,
+    pub seed: u64,
+    pub max_events: u64,
+    // tau-leaping: bound on the relative change of propensities per leap
+    pub epsilon: f64,
+    // tau-leaping: channels within this many firings of exhausting a
+    // reactant are critical and fire one at a time
+    pub critical: u64,
+    // time step of the Langevin and hybrid methods; by default a tenth of
+    // the sampling interval
+    pub step: Option<f64>,
+    // hybrid: channels expected to fire at least this often per step are fast
+    pub fast: f64,
+}
+
+impl Default for StochasticOptions {
+    fn default() -> Self {
+        Self {
+            method: StochasticMethod::Direct,
+            t_end: 10.0,
+            points: 101,
+            seed: 0,
+            max_events: 100_000_000,
+            epsilon: 0.03,
+            critical: 10,
+            step: None,
+            fast: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Propensity {
+    // rate constant parameter and (species, coefficient) of the reactants
+    MassAction(usize, Vec<(usize, u64)>),
+    // explicit rate law of a reaction; the sign selects the direction
+    Law(usize, f64),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Channel {
+    propensity: Propensity,
+    // copy number changes when the channel fires
+    pub(crate) change: Vec<(usize, i64)>,
+    // species the propensity depends on
+    depends_on: Vec<usize>,
+}
+
+impl Channel {
+    // molecules consumed by one firing, with the mass-action reactant
+    // coefficients where the net change hides them
+    pub(crate) fn reactants(&self) -> Vec<(usize, u64)> {
+        match &self.propensity {
+            Propensity::MassAction(_, terms) => terms.clone(),
+            Propensity::Law(..) => self
+                .change
+                .iter()
+                .filter(|(_, n)| *n < 0)
+                .map(|&(i, n)| (i, n.unsigned_abs()))
+                .collect(),
+        }
+    }
+}
+
+fn binomial(x: f64, n: u64) -> f64 {
+    if n == 1 {
+        return x;
+    }
+    (0..n).fold(1.0, |acc, k| acc * (x - k as f64).max(0.0) / (k + 1) as f64)
+}
+
+fn integer_terms(
+    network: &Network,
+    j: usize,
+    complex: &Complex,
+) -> Result<Vec<(usize, u64)>, SimulationError> {
+    complex
+        .terms()
+        .into_iter()
+        .map(|(i, c)| match u64::try_from(c.numer()) {
+            Ok(n) if c.is_integer() => Ok((i, n)),
+            _ => Err(SimulationError::NonIntegerStoichiometry(
+                network.reaction_name(j),
+            )),
+        })
+        .collect()
+}
+
+// reactions as stochastic channels, with the dependency graph between them
+#[derive(Debug, Clone)]
+pub struct StochasticModel {
+    kinetics: Kinetics,
+    parameters: Vec<f64>,
+    pub(crate) channels: Vec<Channel>,
+    // channels whose propensity changes when a channel fires
+    pub(crate) dependents: Vec<Vec<usize>>,
+    initial: Vec<f64>,
+    species: Vec<String>,
+    observables: Observables,
+}
+
+impl StochasticModel {
+    pub fn new(network: &Network) -> Result<Self, SimulationError> {
+        if !network.events().is_empty() {
+            return Err(SimulationError::UnsupportedEvents("stochastic simulation"));
+        }
+        let kinetics = Kinetics::new(network)?;
+        let stoichiometry = network.exact_stoichiometric_matrix();
+        let mut channels = Vec::new();
+        for (j, rxn) in network.reactions().iter().enumerate() {
+            let mut change = Vec::new();
+            for i in 0..network.num_species() {
+                let c = stoichiometry[(i, j)];
+                if c.is_zero() || network.species_attributes(i).is_fixed() {
+                    continue;
+                }
+                if !c.is_integer() {
+                    return Err(SimulationError::NonIntegerStoichiometry(
+                        network.reaction_name(j),
+                    ));
+                }
+                change.push((i, i64::try_from(c.numer()).map_err(|_| Overflow)?));
+            }
+            let reverse: Vec<(usize, i64)> = change.iter().map(|&(i, c)| (i, -c)).collect();
+
+            if rxn.get_kinetics().is_some() {
+                let depends_on: Vec<usize> = kinetics.dependencies(j).collect();
+                channels.push(Channel {
+                    propensity: Propensity::Law(j, 1.0),
+                    change,
+                    depends_on: depends_on.clone(),
+                });
+                if rxn.is_reversible() {
+                    channels.push(Channel {
+                        propensity: Propensity::Law(j, -1.0),
+                        change: reverse,
+                        depends_on,
+                    });
+                }
+                continue;
+            }
+
+            let (kf, kr) = network.rate_constant_names(j);
+            let mut directions = vec![(kf, rxn.get_reactants(), change)];
+            if let Some(kr) = kr {
+                directions.push((kr, rxn.get_products(), reverse));
+            }
+            for (k, reactants, change) in directions {
+                let k = network
+                    .find_parameter(&k)
+                    .ok_or(SimulationError::UnknownSymbol(k))?;
+                let terms = integer_terms(network, j, reactants)?;
+                channels.push(Channel {
+                    depends_on: terms.iter().map(|&(i, _)| i).collect(),
+                    propensity: Propensity::MassAction(k, terms),
+                    change,
+                });
+            }
+        }
+
+        let dependents = channels
+            .iter()
+            .map(|fired| {
+                (0..channels.len())
+                    .filter(|&other| {
+                        channels[other]
+                            .depends_on
+                            .iter()
+                            .any(|s| fired.change.iter().any(|(i, _)| i == s))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let initial = network
+            .initial_conditions()
+            .iter()
+            .map(|x| x.round().max(0.0))
+            .collect();
+        Ok(Self {
+            kinetics,
+            parameters: network.parameter_values(),
+            channels,
+            dependents,
+            initial,
+            species: network.species_names().to_vec(),
+            observables: Observables::new(network)?,
+        })
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn initial_state(&self) -> &[f64] {
+        &self.initial
+    }
+
+    pub fn set_parameter(&mut self, j: usize, value: f64) {
+        self.parameters[j] = value;
+    }
+
+    pub fn propensity(&self, c: usize, x: &[f64]) -> f64 {
+        let a = match &self.channels[c].propensity {
+            Propensity::MassAction(k, terms) => terms
+                .iter()
+                .fold(self.parameters[*k], |acc, &(i, n)| acc * binomial(x[i], n)),
+            Propensity::Law(j, sign) => sign * self.kinetics.rate(*j, x, &self.parameters),
+        };
+        // a negative rate law value means the channel is not firing
+        if a > 0.0 { a } else { 0.0 }
+    }
+
+    pub(crate) fn fire(&self, c: usize, x: &mut [f64], times: f64) {
+        for &(i, n) in &self.channels[c].change {
+            x[i] += n as f64 * times;
+        }
+    }
+
+    // one trajectory sampled at evenly spaced times
+    pub fn run(
+        &self,
+        options: &StochasticOptions,
+        rng: &mut Rng,
+    ) -> Result<TimeCourse, SimulationError> {
+        let times = output_times(options.t_end, options.points)?;
+        let mut recorder = Recorder::new(&times);
+        let mut x = self.initial.clone();
+        match options.method {
+            StochasticMethod::Direct => self.direct(options, rng, &mut x, &mut recorder)?,
+            StochasticMethod::NextReaction => {
+                self.next_reaction(options, rng, &mut x, &mut recorder)?
+            }
+            StochasticMethod::TauLeaping => {
+                self.tau_leaping(options, rng, &mut x, &mut recorder)?
+            }
+            StochasticMethod::Langevin => self.langevin(options, rng, &mut x, &mut recorder)?,
+            StochasticMethod::Hybrid => self.hybrid(options, rng, &mut x, &mut recorder)?,
+        }
+        let states = recorder.finish(&x);
+        Ok(TimeCourse::new(
+            self.species.clone(),
+            times,
+            states,
+            &self.observables,
+            &self.parameters,
+        ))
+    }
+
+    fn direct(
+        &self,
+        options: &StochasticOptions,
+        rng: &mut Rng,
+        x: &mut [f64],
+        recorder: &mut Recorder,
+    ) -> Result<(), SimulationError> {
+        let mut a: Vec<f64> = (0..self.num_channels())
+            .map(|c| self.propensity(c, x))
+            .collect();
+        let mut t = 0.0;
+        for _ in 0..options.max_events {
+            let total: f64 = a.iter().sum();
+            if total <= 0.0 {
+                return Ok(());
+            }
+            t += rng.exponential(total);
+            if t > options.t_end {
+                return Ok(());
+            }
+            recorder.record_until(t, x);
+
+            let target = rng.uniform() * total;
+            let mut cumulative = 0.0;
+            let mut fired = a.len() - 1;
+            for (c, ac) in a.iter().enumerate() {
+                cumulative += ac;
+                if target < cumulative {
+                    fired = c;
+                    break;
+                }
+            }
+            self.fire(fired, x, 1.0);
+            for &c in &self.dependents[fired] {
+                a[c] = self.propensity(c, x);
+            }
+        }
+        Err(SimulationError::TooManyEvents(t))
+    }
+
+    fn next_reaction(
+        &self,
+        options: &StochasticOptions,
+        rng: &mut Rng,
+        x: &mut [f64],
+        recorder: &mut Recorder,
+    ) -> Result<(), SimulationError> {
+        let mut a: Vec<f64> = (0..self.num_channels())
+            .map(|c| self.propensity(c, x))
+            .collect();
+        let putative = a
+            .iter()
+            .map(|&ac| {
+                if ac > 0.0 {
+                    rng.exponential(ac)
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .collect();
+        let mut queue = IndexedQueue::new(putative);
+        for _ in 0..options.max_events {
+            let (fired, t) = queue.min();
+            if t > options.t_end {
+                return Ok(());
+            }
+            recorder.record_until(t, x);
+            self.fire(fired, x, 1.0);
+
+            let unaffected = (!self.dependents[fired].contains(&fired)).then_some(fired);
+            for &c in self.dependents[fired].iter().chain(&unaffected) {
+                let old = a[c];
+                let new = self.propensity(c, x);
+                a[c] = new;
+                let tau = if new <= 0.0 {
+                    f64::INFINITY
+                } else if c != fired && old > 0.0 {
+                    // reuse the waiting time, rescaled to the new propensity
+                    t + old / new * (queue.time(c) - t)
+                } else {
+                    t + rng.exponential(new)
+                };
+                queue.update(c, tau);
+            }
+        }
+        Err(SimulationError::TooManyEvents(queue.min().1))
+    }
+}
+
+// state at each sample time, held constant between events
+pub(crate) struct Recorder<'a> {
+    times: &'a [f64],
+    next: usize,
+    states: Vec<Vec<f64>>,
+}
+
+impl<'a> Recorder<'a> {
+    pub(crate) fn new(times: &'a [f64]) -> Self {
+        Self {
+            times,
+            next: 0,
+            states: Vec::with_capacity(times.len()),
+        }
+    }
+
+    // record the current state at all sample times before t
+    pub(crate) fn record_until(&mut self, t: f64, x: &[f64]) {
+        while self.next < self.times.len() && self.times[self.next] < t {
+            self.states.push(x.to_vec());
+            self.next += 1;
+        }
+    }
+
+    pub(crate) fn next_time(&self) -> f64 {
+        self.times.get(self.next).copied().unwrap_or(f64::INFINITY)
+    }
+
+    // record the current state at all sample times up to and including t
+    pub(crate) fn record_through(&mut self, t: f64, x: &[f64]) {
+        while self.next < self.times.len() && self.times[self.next] <= t {
+            self.states.push(x.to_vec());
+            self.next += 1;
+        }
+    }
+
+    pub(crate) fn finish(mut self, x: &[f64]) -> Vec<Vec<f64>> {
+        self.record_until(f64::INFINITY, x);
+        self.states
+    }
+}
+
+// binary min-heap of firing times that can update any channel's time
+struct IndexedQueue {
+    times: Vec<f64>,
+    heap: Vec<usize>,
+    position: Vec<usize>,
+}
+
+impl IndexedQueue {
+    fn new(times: Vec<f64>) -> Self {
+        let n = times.len();
+        let mut queue = Self {
+            times,
+            heap: (0..n).collect(),
+            position: (0..n).collect(),
+        };
+        for k in (0..n / 2).rev() {
+            queue.sift_down(k);
+        }
+        queue
+    }
+
+    fn min(&self) -> (usize, f64) {
+        match self.heap.first() {
+            Some(&c) => (c, self.times[c]),
+            None => (0, f64::INFINITY),
+        }
+    }
+
+    fn time(&self, c: usize) -> f64 {
+        self.times[c]
+    }
+
+    fn update(&mut self, c: usize, t: f64) {
+        self.times[c] = t;
+        let k = self.position[c];
+        self.sift_up(k);
+        self.sift_down(self.position[c]);
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.position[self.heap[a]] = a;
+        self.position[self.heap[b]] = b;
+    }
+
+    fn less(&self, a: usize, b: usize) -> bool {
+        self.times[self.heap[a]] < self.times[self.heap[b]]
+    }
+
+    fn sift_up(&mut self, mut k: usize) {
+        while k > 0 {
+            let parent = (k - 1) / 2;
+            if !self.less(k, parent) {
+                break;
+            }
+            self.swap(k, parent);
+            k = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut k: usize) {
+        loop {
+            let mut smallest = k;
+            for child in [2 * k + 1, 2 * k + 2] {
+                if child < self.heap.len() && self.less(child, smallest) {
+                    smallest = child;
+                }
+            }
+            if smallest == k {
+                break;
+            }
+            self.swap(k, smallest);
+            k = smallest;
+        }
+    }
+}
+
+// a single trajectory of the network
+pub fn simulate_stochastic(
+    network: &Network,
+    options: &StochasticOptions,
+) -> Result<TimeCourse, SimulationError> {
+    let model = StochasticModel::new(network)?;
+    model.run(options, &mut Rng::new(options.seed))
+}
+
+// independent trajectories, run `i` seeded by stream i of the options' seed,
+// so results do not depend on the number of threads
+pub fn ensemble(
+    network: &Network,
+    options: &StochasticOptions,
+    runs: usize,
+    threads: usize,
+) -> Result<Vec<TimeCourse>, SimulationError> {
+    let model = StochasticModel::new(network)?;
+    let threads = threads.clamp(1, runs.max(1));
+    let mut results: Vec<Option<Result<TimeCourse, SimulationError>>> =
+        (0..runs).map(|_| None).collect();
+    thread::scope(|scope| {
+        for (worker, chunk) in results
+            .chunks_mut(runs.div_ceil(threads).max(1))
+            .enumerate()
+        {
+            let model = &model;
+            let first = worker * runs.div_ceil(threads).max(1);
+            scope.spawn(move || {
+                for (k, slot) in chunk.iter_mut().enumerate() {
+                    let mut rng = Rng::stream(options.seed, (first + k) as u64);
+                    *slot = Some(model.run(options, &mut rng));
+                }
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|r| r.expect("every run is assigned to a thread"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::parser::Parser;
+    use crate::language::scanner::Scanner;
+
+    fn network(text: &str) -> Network {
+        Parser::new(Scanner::scan(text)).parse().unwrap()
+    }
+
+    const ISOMERIZATION: &str = "species {\n  A = 100\n  B = 0\n}\n\
+        param kf_conv = 1\nparam kr_conv = 0.5\nconv : A <-> B\n";
+
+    fn options(method: StochasticMethod) -> StochasticOptions {
+        StochasticOptions {
+            method,
+            t_end: 2.0,
+            points: 5,
+            seed: 7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exact_methods_conserve_copy_numbers() {
+        let network = network(ISOMERIZATION);
+        for method in [StochasticMethod::Direct, StochasticMethod::NextReaction] {
+            let course = simulate_stochastic(&network, &options(method)).unwrap();
+            assert_eq!(course.times, vec![0.0, 0.5, 1.0, 1.5, 2.0]);
+            assert_eq!(course.states[0], vec![100.0, 0.0]);
+            for x in &course.states {
+                assert_eq!(x[0] + x[1], 100.0);
+                assert!(x.iter().all(|&n| n >= 0.0 && n.fract() == 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_trajectory() {
+        let network = network(ISOMERIZATION);
+        let options = options(StochasticMethod::Direct);
+        let a = simulate_stochastic(&network, &options).unwrap();
+        let b = simulate_stochastic(&network, &options).unwrap();
+        assert_eq!(a.states, b.states);
+        let states = |threads| -> Vec<Vec<Vec<f64>>> {
+            let runs = ensemble(&network, &options, 4, threads).unwrap();
+            runs.into_iter().map(|c| c.states).collect()
+        };
+        assert_eq!(states(2), states(3));
+    }
+
+    #[test]
+    fn ensemble_mean_follows_rate_equations() {
+        // A relaxes to 100/3 with rate 1.5; at t = 2 the mean is about 35.0
+        let network = network(ISOMERIZATION);
+        let runs = ensemble(&network, &options(StochasticMethod::Direct), 400, 4).unwrap();
+        let mean = runs.iter().map(|c| c.states[4][0]).sum::<f64>() / runs.len() as f64;
+        let expected = 100.0 / 3.0 + 200.0 / 3.0 * (-3.0_f64).exp();
+        assert!(
+            (mean - expected).abs() < 1.5,
+            "mean {mean}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn approximate_methods_reject_invalid_steps() {
+        let network = network(ISOMERIZATION);
+        for method in [StochasticMethod::Langevin, StochasticMethod::Hybrid] {
+            for dt in [0.0, -1.0, f64::NAN] {
+                let options = StochasticOptions {
+                    step: Some(dt),
+                    ..options(method)
+                };
+                assert!(matches!(
+                    simulate_stochastic(&network, &options),
+                    Err(SimulationError::InvalidOption(_))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn fractional_stoichiometry_is_rejected() {
+        let network = network("species {\n  A = 2\n}\nparam k_split = 1\nsplit : A -> 1/2 B\n");
+        assert!(matches!(
+            StochasticModel::new(&network),
+            Err(SimulationError::NonIntegerStoichiometry(_))
+        ));
+    }
+}